@@ -7,13 +7,8 @@
 /// Pressing the escape key closes the popup box.
 use std::{error::Error, io};
 
-use config::Config;
-use env_logger;
 use log::{debug, error, info};
 
-// This adds a width() method to String
-use unicode_width::UnicodeWidthStr;
-
 use tui::{
     backend::{Backend, CrosstermBackend},
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -24,47 +19,42 @@ use tui::{
 };
 
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
 use tui_command_bar_widget::widgets::popup::Popup;
 
-use tui_command_bar_widget::key_hook::key_hook::KeyHook;
-use tui_command_bar_widget::widgets::command_bar::{CommandBar, EventHandlerResult};
+use tui_command_bar_widget::key_hook::key_sequence::KeySequence;
+use tui_command_bar_widget::settings::command_bar_config::CommandBarConfig;
+use tui_command_bar_widget::widgets::command_bar::{EventHandlerResult, InputMode};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    // Load config
-    let mut debug = true;
-    let mut command_key = ':';
-
     // Initialize logger
     if let Err(e) = env_logger::try_init() {
         panic!("couldn't initialize logger: {:?}", e);
     }
 
-    let settings_result = load_settings("config/tui-command-bar-widget.toml");
-    match settings_result {
-        Ok(settings) => {
+    // Load config
+    let config = match CommandBarConfig::load("config/tui-command-bar-widget.toml") {
+        Ok(config) => {
             info!("merged in config");
-            if let Ok(b) = settings.get_bool("debug") {
-                debug = b;
-            }
-            if let Ok(k) = settings.get_string("command-key") {
-                command_key = match k.chars().next() {
-                    Some(c) => c,
-                    None => command_key,
-                };
-                debug!("command_key: {}", command_key);
-            }
+            config
         }
-        Err(s) => {
-            error!("error loading config: {:?}", s)
+        Err(e) => {
+            error!("error loading config: {}", e);
+            CommandBarConfig {
+                debug: true,
+                ..Default::default()
+            }
         }
     };
 
-    match debug {
+    match config.debug {
         true => {
             debug!("debug mode is enabled");
         }
@@ -76,14 +66,26 @@ fn main() -> Result<(), Box<dyn Error>> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
     let mut command_bar_widget = Popup::default();
-    let mut closure = |cb: &mut CommandBar, key| cb.command_key_handler(key);
-    command_bar_widget.register_key(command_key, &mut closure);
+    command_bar_widget.command_bar.set_command_key(':');
+    if let Err(e) = config.apply(&mut command_bar_widget) {
+        error!("error applying config: {}", e);
+    }
+    for mode in [InputMode::Normal, InputMode::Editing] {
+        for conflict in command_bar_widget.command_bar.conflicts(mode) {
+            error!("key binding conflict in {:?} mode: {}", mode, conflict);
+        }
+    }
     let res = run_app(&mut terminal, command_bar_widget);
 
     // restore terminal
@@ -91,7 +93,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -116,9 +119,8 @@ fn run_app<B: Backend>(
             EventHandlerResult::Ok => {}
             EventHandlerResult::Unhandled(event) => {
                 if let Event::Key(key) = event {
-                    match key.code {
-                        KeyCode::Char('q') => return Ok(()),
-                        _ => {}
+                    if let KeyCode::Char('q') = key.code {
+                        return Ok(());
                     }
                 }
             }
@@ -133,10 +135,11 @@ fn ui<B: Backend>(f: &mut Frame<B>, command_bar_widget: &mut Popup) {
         .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
         .split(size);
 
-    let command_key = match command_bar_widget.command_bar.command_key {
-        Some(key) => key,
-        None => 'p',
-    };
+    let command_key = command_bar_widget
+        .command_bar
+        .command_key
+        .clone()
+        .unwrap_or_else(|| KeySequence::from('p'));
 
     let escape_key = "Esc";
 
@@ -174,12 +177,12 @@ fn ui<B: Backend>(f: &mut Frame<B>, command_bar_widget: &mut Popup) {
 
     if command_bar_widget.show_popup {
         let area = fixed_height_centered_rect(80, 3, size);
-        let width = command_bar_widget.command_bar.input.width();
+        let (x, y) = command_bar_widget.command_bar.cursor_position(area);
 
         f.render_widget(Clear, area); // this clears out the background
         f.render_widget(command_bar_widget, area);
 
-        f.set_cursor(area.x + width as u16 + 1, area.y + 1);
+        f.set_cursor(x, y);
     }
 }
 
@@ -211,15 +214,3 @@ fn fixed_height_centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
         )
         .split(popup_layout[1])[1]
 }
-
-/// load settings from a config file
-/// returns the config settings as a Config on success, or a ConfigError on failure
-fn load_settings<'a>(config_name: &str) -> Result<Config, config::ConfigError> {
-    Config::builder()
-        // Add in config file
-        .add_source(config::File::with_name(config_name))
-        // Add in settings from the environment (with a prefix of APP)
-        // Eg.. `APP_DEBUG=1 ./target/command_bar_widget would set the `debug` key
-        .add_source(config::Environment::with_prefix("APP"))
-        .build()
-}
@@ -5,14 +5,15 @@
 /// Pressing the command key focuses the command bar.
 /// When focused, typing enters data.
 /// Pressing the escape key removes focus.
-use std::{error::Error, io};
+use std::{error::Error, io, rc::Rc};
 
-use config::Config;
-use env_logger;
 use log::{debug, error, info};
 
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -25,53 +26,39 @@ use tui::{
     Frame, Terminal,
 };
 
-use tui_command_bar_widget::key_hook::key_hook::KeyHook;
+use tui_command_bar_widget::completion::completer::WordCompleter;
+use tui_command_bar_widget::key_hook::key_sequence::KeySequence;
+use tui_command_bar_widget::settings::command_bar_config::CommandBarConfig;
 use tui_command_bar_widget::widgets::command_bar::{CommandBar, EventHandlerResult, InputMode};
 
+#[derive(Default)]
 pub struct App {
     /// History of recorded messages
     pub messages: Vec<String>,
 }
 
-impl Default for App {
-    fn default() -> App {
-        App {
-            messages: Vec::new(),
-        }
-    }
-}
-
 fn main() -> Result<(), Box<dyn Error>> {
-    // Load config
-    let mut debug = true;
-    let mut command_key = ':';
-
     // Initialize logger
     if let Err(e) = env_logger::try_init() {
         panic!("couldn't initialize logger: {:?}", e);
     }
 
-    let settings_result = load_settings("config/tui-command-bar-widget.toml");
-    match settings_result {
-        Ok(settings) => {
+    // Load config
+    let config = match CommandBarConfig::load("config/tui-command-bar-widget.toml") {
+        Ok(config) => {
             info!("merged in config");
-            if let Ok(b) = settings.get_bool("debug") {
-                debug = b;
-            }
-            if let Ok(k) = settings.get_string("command-key") {
-                command_key = match k.chars().next() {
-                    Some(c) => c,
-                    None => command_key,
-                };
-                debug!("command_key: {}", command_key);
-            }
+            config
         }
-        Err(s) => {
-            error!("error loading config: {:?}", s)
+        Err(e) => {
+            error!("error loading config: {}", e);
+            CommandBarConfig {
+                debug: true,
+                ..Default::default()
+            }
         }
     };
 
-    match debug {
+    match config.debug {
         true => {
             debug!("debug mode is enabled");
         }
@@ -83,7 +70,12 @@ fn main() -> Result<(), Box<dyn Error>> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
@@ -91,8 +83,18 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // create command_bar_widget and run it
     let mut command_bar_widget = CommandBar::default();
-    let mut closure = |cb: &mut CommandBar, key| cb.command_key_handler(key);
-    command_bar_widget.register_key(command_key, &mut closure);
+    command_bar_widget.set_command_key(':');
+    if let Err(e) = config.apply(&mut command_bar_widget) {
+        error!("error applying config: {}", e);
+    }
+    for mode in [InputMode::Normal, InputMode::Editing] {
+        for conflict in command_bar_widget.conflicts(mode) {
+            error!("key binding conflict in {:?} mode: {}", mode, conflict);
+        }
+    }
+    command_bar_widget.completer = Some(Rc::new(WordCompleter::new(&[
+        "open", "quit", "write", "writeall",
+    ])));
 
     let res = run_app(&mut terminal, app, command_bar_widget);
 
@@ -101,7 +103,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -114,14 +117,22 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
-    mut app: App,
+    app: App,
     mut command_bar_widget: CommandBar,
 ) -> io::Result<()> {
     loop {
-        terminal.draw(|f| ui(f, &mut app, &mut command_bar_widget))?;
+        terminal.draw(|f| ui(f, &app, &mut command_bar_widget))?;
 
-        // TODO: refactor into proper event handling tree
-        match command_bar_widget.handle_event() {
+        // Finish a pending key sequence if no key follows in time
+        if let Some(timeout) = command_bar_widget.key_timeout() {
+            if !event::poll(timeout)? {
+                command_bar_widget.handle_timeout();
+                continue;
+            }
+        }
+
+        // The app reads events and offers them to the command bar first
+        match command_bar_widget.handle_key_event(event::read()?) {
             // The widget returned an error, quit the event loop
             EventHandlerResult::Err => {
                 return Ok(());
@@ -131,12 +142,9 @@ fn run_app<B: Backend>(
             // The widget didn't know how to handle the event, so we should
             EventHandlerResult::Unhandled(event) => {
                 if let Event::Key(key) = event {
-                    match key.code {
-                        KeyCode::Char('q') => {
-                            return Ok(());
-                        }
-                        _ => {}
-                    };
+                    if let KeyCode::Char('q') = key.code {
+                        return Ok(());
+                    }
                 };
             }
         }
@@ -159,7 +167,10 @@ fn ui<B: Backend>(f: &mut Frame<B>, _app: &App, command_bar_widget: &mut Command
         )
         .split(f.size());
 
-    let cmd_key = command_bar_widget.command_key.unwrap_or(':');
+    let cmd_key = command_bar_widget
+        .command_key
+        .clone()
+        .unwrap_or_else(|| KeySequence::from(':'));
     let cmd_key_str = format!("{}", cmd_key);
 
     let (msg, style) = match command_bar_widget.input_mode {
@@ -188,10 +199,9 @@ fn ui<B: Backend>(f: &mut Frame<B>, _app: &App, command_bar_widget: &mut Command
     text.patch_style(style);
     let help_message = Paragraph::new(text);
     f.render_widget(help_message, chunks[0]);
-    let width = command_bar_widget.input.len();
-
     if let InputMode::Editing = command_bar_widget.input_mode {
-        f.set_cursor(chunks[1].x + width as u16 + 1, chunks[1].y + 1);
+        let (x, y) = command_bar_widget.cursor_position(chunks[1]);
+        f.set_cursor(x, y);
     }
 
     let messages: Vec<ListItem> = command_bar_widget
@@ -206,18 +216,12 @@ fn ui<B: Backend>(f: &mut Frame<B>, _app: &App, command_bar_widget: &mut Command
     let messages =
         List::new(messages).block(Block::default().borders(Borders::ALL).title("Messages"));
 
-    f.render_widget(command_bar_widget, chunks[1]);
+    f.render_widget(&mut *command_bar_widget, chunks[1]);
     f.render_widget(messages, chunks[2]);
-}
 
-/// load settings from a config file
-/// returns the config settings as a Config on success, or a ConfigError on failure
-fn load_settings<'a>(config_name: &str) -> Result<Config, config::ConfigError> {
-    Config::builder()
-        // Add in config file
-        .add_source(config::File::with_name(config_name))
-        // Add in settings from the environment (with a prefix of APP)
-        // Eg.. `APP_DEBUG=1 ./target/command_bar_widget would set the `debug` key
-        .add_source(config::Environment::with_prefix("APP"))
-        .build()
+    // The completion menu is drawn last so it's layered over the messages
+    if let Some(menu) = command_bar_widget.completion_menu() {
+        let area = menu.area(chunks[1], f.size());
+        f.render_widget(menu, area);
+    }
 }
@@ -0,0 +1,9 @@
+///
+/// command is a module for running the commands submitted in a CommandBar
+/// Applications register named commands in a CommandRegistry, and the
+/// CommandBar parses each submitted line and dispatches it to a handler.
+///
+pub mod registry;
+
+/// Splitting of command lines into arguments
+pub mod parser;
@@ -0,0 +1,236 @@
+///
+/// Shell-like splitting of a command line into arguments
+///
+/// Arguments are separated by whitespace.
+/// Text in single quotes is taken literally, text in double quotes may
+/// contain \" and \\ escapes, and outside quotes a backslash escapes the
+/// next character.
+///
+use std::fmt;
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// An argument split from a command line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    /// The argument with quotes and escapes removed
+    pub text: String,
+    /// Byte range of the argument in the command line, including quotes
+    pub span: Range<usize>,
+}
+
+/// The kinds of malformed command line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// A quote was opened and not closed
+    UnterminatedQuote(char),
+    /// The line ends with a backslash that escapes nothing
+    TrailingBackslash,
+}
+
+/// An error splitting a command line
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// What went wrong
+    pub kind: ParseErrorKind,
+    /// Byte range of the offending text, such as an unterminated quote
+    /// through to the end of the line
+    pub span: Range<usize>,
+    /// Column where the offending text starts, counted in graphemes from 0
+    /// like the CommandBar cursor
+    pub column: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ParseErrorKind::UnterminatedQuote(quote) => {
+                write!(f, "unterminated {} at column {}", quote, self.column + 1)
+            }
+            ParseErrorKind::TrailingBackslash => {
+                write!(f, "trailing \\ at column {}", self.column + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Split a line into tokens, stopping at the end of the line even if a
+/// quote is still open
+///
+/// The last token is returned along with the error, which lets completers
+/// work on an argument that is still being typed.
+pub fn scan(line: &str) -> (Vec<Token>, Option<ParseError>) {
+    let mut tokens = Vec::new();
+    let mut token: Option<Token> = None;
+    // The open quote and its offset
+    let mut quote: Option<(char, usize)> = None;
+    // Offset of a backslash waiting for the character it escapes
+    let mut escape: Option<usize> = None;
+
+    for (i, c) in line.char_indices() {
+        let end = i + c.len_utf8();
+        if escape.take().is_some() {
+            // Inside double quotes only \" and \\ are escapes
+            if let Some(('"', _)) = quote {
+                if c != '"' && c != '\\' {
+                    push(&mut token, i, '\\');
+                }
+            }
+            push(&mut token, i, c);
+        } else {
+            match quote {
+                Some((q, _)) if c == q => quote = None,
+                Some(('"', _)) if c == '\\' => escape = Some(i),
+                Some(_) => push(&mut token, i, c),
+                None if c.is_whitespace() => {
+                    tokens.extend(token.take());
+                    continue;
+                }
+                None => match c {
+                    '"' | '\'' => {
+                        quote = Some((c, i));
+                        token.get_or_insert(Token {
+                            text: String::new(),
+                            span: i..i,
+                        });
+                    }
+                    '\\' => {
+                        escape = Some(i);
+                        token.get_or_insert(Token {
+                            text: String::new(),
+                            span: i..i,
+                        });
+                    }
+                    _ => push(&mut token, i, c),
+                },
+            }
+        }
+        if let Some(token) = token.as_mut() {
+            token.span.end = end;
+        }
+    }
+    tokens.extend(token);
+
+    let error = match (quote, escape) {
+        (Some((q, start)), _) => Some((ParseErrorKind::UnterminatedQuote(q), start)),
+        (None, Some(start)) => Some((ParseErrorKind::TrailingBackslash, start)),
+        (None, None) => None,
+    }
+    .map(|(kind, start)| ParseError {
+        kind,
+        span: start..line.len(),
+        column: line[..start].graphemes(true).count(),
+    });
+
+    (tokens, error)
+}
+
+/// Add a character to the current token, starting a token at offset if
+/// there isn't one
+fn push(token: &mut Option<Token>, offset: usize, c: char) {
+    token
+        .get_or_insert(Token {
+            text: String::new(),
+            span: offset..offset,
+        })
+        .text
+        .push(c);
+}
+
+/// Split a line into arguments
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::command::parser::tokenize;
+///
+/// let tokens = tokenize(r#"open "My Docs/notes.txt" it\'s"#).unwrap();
+/// let words: Vec<&str> = tokens.iter().map(|t| t.text.as_str()).collect();
+/// assert_eq!(words, vec!["open", "My Docs/notes.txt", "it's"]);
+///
+/// let error = tokenize("open 'notes").unwrap_err();
+/// assert_eq!(error.column, 5);
+/// assert_eq!(error.to_string(), "unterminated ' at column 6");
+/// ```
+pub fn tokenize(line: &str) -> Result<Vec<Token>, ParseError> {
+    match scan(line) {
+        (tokens, None) => Ok(tokens),
+        (_, Some(error)) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command::parser::{scan, tokenize, ParseError, ParseErrorKind, Token};
+
+    fn words(line: &str) -> Vec<String> {
+        tokenize(line)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.text)
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_splits_on_whitespace() {
+        assert_eq!(words("  set   wrap\ton "), vec!["set", "wrap", "on"]);
+        assert!(words("   ").is_empty());
+    }
+
+    #[test]
+    fn tokenize_quotes_and_escapes() {
+        assert_eq!(words(r#"a"b c"d"#), vec!["ab cd"]);
+        assert_eq!(words(r#"'a \n "b"'"#), vec![r#"a \n "b""#]);
+        assert_eq!(words(r#""a \"b\" \\ \n""#), vec![r#"a "b" \ \n"#]);
+        assert_eq!(words(r"a\ b \'"), vec!["a b", "'"]);
+        assert_eq!(words(r#"'' """#), vec!["", ""]);
+    }
+
+    #[test]
+    fn tokenize_spans() {
+        assert_eq!(
+            tokenize(r#"é "x y""#).unwrap(),
+            vec![
+                Token {
+                    text: String::from("é"),
+                    span: 0..2,
+                },
+                Token {
+                    text: String::from("x y"),
+                    span: 3..8,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_reports_errors() {
+        assert_eq!(
+            tokenize("é \"x"),
+            Err(ParseError {
+                kind: ParseErrorKind::UnterminatedQuote('"'),
+                span: 3..5,
+                column: 2,
+            })
+        );
+        assert_eq!(
+            tokenize("ab\\"),
+            Err(ParseError {
+                kind: ParseErrorKind::TrailingBackslash,
+                span: 2..3,
+                column: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn scan_keeps_unterminated_token() {
+        let (tokens, error) = scan("open \"My D");
+        assert!(error.is_some());
+        assert_eq!(tokens[1].text, "My D");
+        assert_eq!(tokens[1].span, 5..10);
+    }
+}
@@ -0,0 +1,703 @@
+///
+/// CommandRegistry maps command names to handlers
+///
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::command::parser::{tokenize, ParseError, Token};
+
+/// The type of value an argument accepts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArgKind {
+    /// Any text
+    String,
+    /// A whole number
+    Integer,
+    /// A number with an optional fraction
+    Float,
+    /// true or false, also accepts yes/no, on/off and 1/0
+    Bool,
+}
+
+impl fmt::Display for ArgKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ArgKind::String => "string",
+            ArgKind::Integer => "integer",
+            ArgKind::Float => "number",
+            ArgKind::Bool => "boolean",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A parsed argument value
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArgValue {
+    /// A String argument
+    String(String),
+    /// An Integer argument
+    Integer(i64),
+    /// A Float argument
+    Float(f64),
+    /// A Bool argument
+    Bool(bool),
+}
+
+impl ArgValue {
+    /// Parse text as a value of kind
+    /// Returns None if the text isn't a valid value
+    pub fn parse(kind: ArgKind, text: &str) -> Option<ArgValue> {
+        match kind {
+            ArgKind::String => Some(ArgValue::String(String::from(text))),
+            ArgKind::Integer => text.parse().ok().map(ArgValue::Integer),
+            ArgKind::Float => text.parse().ok().map(ArgValue::Float),
+            ArgKind::Bool => match text.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Some(ArgValue::Bool(true)),
+                "false" | "no" | "off" | "0" => Some(ArgValue::Bool(false)),
+                _ => None,
+            },
+        }
+    }
+
+    /// The value of a String argument
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ArgValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The value of an Integer argument
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            ArgValue::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// The value of a Float or Integer argument
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            ArgValue::Float(f) => Some(*f),
+            ArgValue::Integer(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    /// The value of a Bool argument
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ArgValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+/// How many values an argument takes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    /// Exactly one value
+    Required,
+    /// Zero or one value
+    Optional,
+    /// Any number of values, only allowed as the last argument
+    Variadic,
+}
+
+/// The specification of a command argument
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArgSpec {
+    /// Name of the argument, used in help text and to look up the value
+    pub name: String,
+    /// The type of value the argument accepts
+    pub kind: ArgKind,
+    /// How many values the argument takes
+    pub arity: Arity,
+}
+
+impl ArgSpec {
+    /// An argument that must be given
+    pub fn required(name: &str, kind: ArgKind) -> Self {
+        ArgSpec {
+            name: String::from(name),
+            kind,
+            arity: Arity::Required,
+        }
+    }
+
+    /// An argument that may be left out
+    pub fn optional(name: &str, kind: ArgKind) -> Self {
+        ArgSpec {
+            arity: Arity::Optional,
+            ..ArgSpec::required(name, kind)
+        }
+    }
+
+    /// An argument that takes all the remaining values
+    pub fn variadic(name: &str, kind: ArgKind) -> Self {
+        ArgSpec {
+            arity: Arity::Variadic,
+            ..ArgSpec::required(name, kind)
+        }
+    }
+
+    /// The argument as shown in a usage line, like FILE, [LINE] or [FILE...]
+    fn usage(&self) -> String {
+        let name = self.name.to_uppercase();
+        match self.arity {
+            Arity::Required => name,
+            Arity::Optional => format!("[{}]", name),
+            Arity::Variadic => format!("[{}...]", name),
+        }
+    }
+}
+
+/// A parsed command line, ready to be passed to a handler
+#[derive(Clone, Debug, PartialEq)]
+pub struct Invocation {
+    /// The name the command was registered with, even if an alias was typed
+    pub command: String,
+    /// The argument names and values in the order they were given
+    pub args: Vec<(String, ArgValue)>,
+}
+
+impl Invocation {
+    /// The first value of the argument called name
+    pub fn get(&self, name: &str) -> Option<&ArgValue> {
+        self.args.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    /// All the values of the argument called name
+    /// Useful for variadic arguments
+    pub fn values(&self, name: &str) -> Vec<&ArgValue> {
+        self.args
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| v)
+            .collect()
+    }
+}
+
+/// The result of running a command
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandOutcome {
+    /// The command that was run
+    pub invocation: Invocation,
+    /// Optional message from the handler to show the user
+    pub message: Option<String>,
+}
+
+/// Errors from parsing or running a command
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandError {
+    /// The line was empty
+    Empty,
+    /// The line couldn't be split into arguments
+    Syntax(ParseError),
+    /// No command is registered with the name
+    UnknownCommand(String),
+    /// A required argument was not given
+    MissingArgument {
+        /// The command name
+        command: String,
+        /// The argument name
+        argument: String,
+    },
+    /// More arguments were given than the command takes
+    TooManyArguments {
+        /// The command name
+        command: String,
+        /// The number of arguments the command takes
+        expected: usize,
+    },
+    /// An argument value couldn't be parsed
+    InvalidArgument {
+        /// The argument name
+        argument: String,
+        /// The text that was given
+        value: String,
+        /// The type of value expected
+        kind: ArgKind,
+        /// Byte range of the value in the line
+        span: Range<usize>,
+    },
+    /// The handler reported an error
+    Failed(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::Empty => write!(f, "no command given"),
+            CommandError::Syntax(e) => write!(f, "{}", e),
+            CommandError::UnknownCommand(name) => write!(f, "unknown command: {}", name),
+            CommandError::MissingArgument { command, argument } => {
+                write!(f, "{}: missing argument {}", command, argument)
+            }
+            CommandError::TooManyArguments { command, expected } => {
+                write!(f, "{}: expected at most {} arguments", command, expected)
+            }
+            CommandError::InvalidArgument {
+                argument,
+                value,
+                kind,
+                ..
+            } => write!(f, "{}: expected {}, got {:?}", argument, kind, value),
+            CommandError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl CommandError {
+    /// Byte range of the text in the line that caused the error, if the
+    /// error can be pinned to part of the line
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            CommandError::Syntax(e) => Some(e.span.clone()),
+            CommandError::InvalidArgument { span, .. } => Some(span.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Error registering a command with arguments that can't be matched to
+/// words in order
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArgOrderError {
+    /// The command name
+    pub command: String,
+    /// The argument that is out of order
+    pub argument: String,
+    /// The argument before it
+    pub previous: ArgSpec,
+}
+
+impl fmt::Display for ArgOrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.previous.arity {
+            Arity::Variadic => write!(
+                f,
+                "{}: argument {} can't follow the variadic argument {}",
+                self.command, self.argument, self.previous.name
+            ),
+            _ => write!(
+                f,
+                "{}: required argument {} can't follow the optional argument {}",
+                self.command, self.argument, self.previous.name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ArgOrderError {}
+
+/// A command handler gets the parsed invocation and returns an optional
+/// message to show the user, or an error message
+pub type CommandHandler = Rc<dyn Fn(&Invocation) -> Result<Option<String>, String>>;
+
+/// A command that can be registered in a CommandRegistry
+#[derive(Clone)]
+pub struct Command {
+    /// The name of the command
+    pub name: String,
+    /// Other names the command can be run with
+    pub aliases: Vec<String>,
+    /// The arguments the command takes, in order
+    pub args: Vec<ArgSpec>,
+    /// A short description of the command
+    pub help: String,
+    /// The function run when the command is dispatched
+    pub handler: CommandHandler,
+}
+
+impl Command {
+    /// Build a command called name that runs handler
+    pub fn new<F>(name: &str, handler: F) -> Self
+    where
+        F: Fn(&Invocation) -> Result<Option<String>, String> + 'static,
+    {
+        Command {
+            name: String::from(name),
+            aliases: Vec::new(),
+            args: Vec::new(),
+            help: String::new(),
+            handler: Rc::new(handler),
+        }
+    }
+
+    /// Add another name for the command
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.aliases.push(String::from(alias));
+        self
+    }
+
+    /// Add an argument after the existing ones
+    /// The order is checked when the command is registered.
+    pub fn arg(mut self, spec: ArgSpec) -> Self {
+        self.args.push(spec);
+        self
+    }
+
+    /// Check that the arguments can be matched to words in order
+    /// A required argument can't follow an optional one, and no argument
+    /// can follow a variadic one.
+    pub fn check_args(&self) -> Result<(), ArgOrderError> {
+        for pair in self.args.windows(2) {
+            let (previous, spec) = (&pair[0], &pair[1]);
+            let out_of_order = match previous.arity {
+                Arity::Variadic => true,
+                Arity::Optional => spec.arity == Arity::Required,
+                Arity::Required => false,
+            };
+            if out_of_order {
+                return Err(ArgOrderError {
+                    command: self.name.clone(),
+                    argument: spec.name.clone(),
+                    previous: previous.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the description of the command
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = String::from(help);
+        self
+    }
+
+    /// Whether the command can be run as name
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|a| a == name)
+    }
+
+    /// The usage line of the command, like "open FILE [LINE]"
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.clone();
+        for spec in &self.args {
+            usage.push(' ');
+            usage.push_str(&spec.usage());
+        }
+        usage
+    }
+
+    /// Match words against the argument specs
+    fn parse_args(&self, words: &[Token]) -> Result<Vec<(String, ArgValue)>, CommandError> {
+        let mut args = Vec::new();
+        let mut words = words.iter();
+        for spec in &self.args {
+            let count = match spec.arity {
+                Arity::Variadic => words.len(),
+                _ => 1,
+            };
+            let mut given = 0;
+            for word in words.by_ref().take(count) {
+                let value = ArgValue::parse(spec.kind, &word.text).ok_or_else(|| {
+                    CommandError::InvalidArgument {
+                        argument: spec.name.clone(),
+                        value: word.text.clone(),
+                        kind: spec.kind,
+                        span: word.span.clone(),
+                    }
+                })?;
+                args.push((spec.name.clone(), value));
+                given += 1;
+            }
+            if given == 0 && spec.arity == Arity::Required {
+                return Err(CommandError::MissingArgument {
+                    command: self.name.clone(),
+                    argument: spec.name.clone(),
+                });
+            }
+        }
+        if words.next().is_some() {
+            return Err(CommandError::TooManyArguments {
+                command: self.name.clone(),
+                expected: self.args.len(),
+            });
+        }
+        Ok(args)
+    }
+}
+
+/// CommandRegistry holds the commands an application understands
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::command::registry::{ArgKind, ArgSpec, Command, CommandRegistry};
+///
+/// let mut commands = CommandRegistry::default();
+/// commands.register(
+///     Command::new("open", |invocation| {
+///         let file = invocation.get("file").and_then(|v| v.as_str()).unwrap();
+///         Ok(Some(format!("opened {}", file)))
+///     })
+///     .alias("e")
+///     .arg(ArgSpec::required("file", ArgKind::String))
+///     .help("Open a file"),
+/// )
+/// .unwrap();
+///
+/// let outcome = commands.dispatch("e notes.txt").unwrap();
+/// assert_eq!(outcome.invocation.command, "open");
+/// assert_eq!(outcome.message, Some(String::from("opened notes.txt")));
+/// assert!(commands.dispatch("e").is_err());
+/// ```
+#[derive(Clone, Default)]
+pub struct CommandRegistry {
+    /// The registered commands, in the order they were registered
+    pub commands: Vec<Command>,
+}
+
+impl CommandRegistry {
+    /// Register a command
+    /// A command with the same name is replaced and returned.
+    /// Returns an error, and leaves the registry unchanged, if the
+    /// command's arguments are out of order.
+    pub fn register(&mut self, command: Command) -> Result<Option<Command>, ArgOrderError> {
+        command.check_args()?;
+        match self.commands.iter_mut().find(|c| c.name == command.name) {
+            Some(existing) => Ok(Some(std::mem::replace(existing, command))),
+            None => {
+                self.commands.push(command);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Remove the command called name
+    pub fn unregister(&mut self, name: &str) -> Option<Command> {
+        let index = self.commands.iter().position(|c| c.name == name)?;
+        Some(self.commands.remove(index))
+    }
+
+    /// Find the command that can be run as name
+    /// Names are checked before aliases.
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands
+            .iter()
+            .find(|c| c.name == name)
+            .or_else(|| self.commands.iter().find(|c| c.matches(name)))
+    }
+
+    /// Parse a line into an Invocation without running it
+    /// The line is split into arguments with the parser module.
+    pub fn parse(&self, line: &str) -> Result<Invocation, CommandError> {
+        let words = tokenize(line).map_err(CommandError::Syntax)?;
+        let (name, words) = words.split_first().ok_or(CommandError::Empty)?;
+        let command = self
+            .get(&name.text)
+            .ok_or_else(|| CommandError::UnknownCommand(name.text.clone()))?;
+        Ok(Invocation {
+            command: command.name.clone(),
+            args: command.parse_args(words)?,
+        })
+    }
+
+    /// Parse a line and run the matching command handler
+    pub fn dispatch(&self, line: &str) -> Result<CommandOutcome, CommandError> {
+        let invocation = self.parse(line)?;
+        let command = self
+            .get(&invocation.command)
+            .ok_or_else(|| CommandError::UnknownCommand(invocation.command.clone()))?;
+        let message = (command.handler)(&invocation).map_err(CommandError::Failed)?;
+        Ok(CommandOutcome {
+            invocation,
+            message,
+        })
+    }
+
+    /// Help text for the command called name, or a list of all commands
+    pub fn help(&self, name: Option<&str>) -> Option<String> {
+        let summary = |command: &Command| {
+            if command.help.is_empty() {
+                command.usage()
+            } else {
+                format!("{} - {}", command.usage(), command.help)
+            }
+        };
+        match name {
+            Some(name) => {
+                let command = self.get(name)?;
+                let mut help = summary(command);
+                if !command.aliases.is_empty() {
+                    help.push_str(&format!(" (aliases: {})", command.aliases.join(", ")));
+                }
+                Some(help)
+            }
+            None => Some(
+                self.commands
+                    .iter()
+                    .map(summary)
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::command::registry::{
+        ArgKind, ArgSpec, ArgValue, Arity, Command, CommandError, CommandRegistry,
+    };
+
+    fn registry() -> CommandRegistry {
+        let mut commands = CommandRegistry::default();
+        commands
+            .register(
+                Command::new("goto", |_| Ok(None))
+                    .alias("g")
+                    .arg(ArgSpec::required("line", ArgKind::Integer))
+                    .arg(ArgSpec::optional("column", ArgKind::Integer))
+                    .help("Move the cursor"),
+            )
+            .unwrap();
+        commands
+            .register(
+                Command::new("set", |_| Ok(None))
+                    .arg(ArgSpec::required("option", ArgKind::String))
+                    .arg(ArgSpec::variadic("values", ArgKind::Bool)),
+            )
+            .unwrap();
+        commands
+            .register(Command::new("fail", |_| Err(String::from("it broke"))))
+            .unwrap();
+        commands
+    }
+
+    #[test]
+    fn registry_rejects_arguments_out_of_order() {
+        let mut commands = registry();
+        let error = commands
+            .register(
+                Command::new("goto", |_| Ok(None))
+                    .arg(ArgSpec::optional("line", ArgKind::Integer))
+                    .arg(ArgSpec::required("column", ArgKind::Integer)),
+            )
+            .err();
+        assert_eq!(
+            error.map(|e| e.to_string()).as_deref(),
+            Some("goto: required argument column can't follow the optional argument line")
+        );
+        // The registered goto is kept
+        assert_eq!(commands.get("g").unwrap().args.len(), 2);
+        assert_eq!(commands.get("g").unwrap().args[0].arity, Arity::Required);
+
+        let mut set = Command::new("set", |_| Ok(None));
+        set.args = vec![
+            ArgSpec::variadic("values", ArgKind::Bool),
+            ArgSpec::optional("scope", ArgKind::String),
+        ];
+        assert_eq!(
+            commands
+                .register(set)
+                .err()
+                .map(|e| e.to_string())
+                .as_deref(),
+            Some("set: argument scope can't follow the variadic argument values")
+        );
+    }
+
+    #[test]
+    fn registry_parses_arguments() {
+        let commands = registry();
+        let invocation = commands.parse("g 10 4").unwrap();
+        assert_eq!(invocation.command, "goto");
+        assert_eq!(invocation.get("line"), Some(&ArgValue::Integer(10)));
+        assert_eq!(invocation.get("column"), Some(&ArgValue::Integer(4)));
+
+        let invocation = commands.parse("goto 3").unwrap();
+        assert_eq!(invocation.get("column"), None);
+
+        let invocation = commands.parse("set \"word wrap\" on no").unwrap();
+        assert_eq!(
+            invocation.get("option"),
+            Some(&ArgValue::String(String::from("word wrap")))
+        );
+        assert_eq!(
+            invocation.values("values"),
+            vec![&ArgValue::Bool(true), &ArgValue::Bool(false)]
+        );
+    }
+
+    #[test]
+    fn registry_reports_parse_errors() {
+        let commands = registry();
+        assert_eq!(commands.parse("  "), Err(CommandError::Empty));
+        assert_eq!(
+            commands.parse("quit"),
+            Err(CommandError::UnknownCommand(String::from("quit")))
+        );
+        assert_eq!(
+            commands.parse("goto"),
+            Err(CommandError::MissingArgument {
+                command: String::from("goto"),
+                argument: String::from("line"),
+            })
+        );
+        assert_eq!(
+            commands.parse("goto 1 2 3"),
+            Err(CommandError::TooManyArguments {
+                command: String::from("goto"),
+                expected: 2,
+            })
+        );
+        let error = commands.parse("goto x").unwrap_err();
+        assert_eq!(error.to_string(), "line: expected integer, got \"x\"");
+        assert_eq!(error.span(), Some(5..6));
+
+        let error = commands.parse("set 'wrap").unwrap_err();
+        assert!(matches!(error, CommandError::Syntax(_)));
+        assert_eq!(error.span(), Some(4..9));
+    }
+
+    #[test]
+    fn registry_dispatches_to_handler() {
+        let mut commands = registry();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&calls);
+        let replaced = commands
+            .register(Command::new("goto", move |invocation| {
+                recorded.borrow_mut().push(invocation.clone());
+                Ok(Some(String::from("moved")))
+            }))
+            .unwrap();
+        assert!(replaced.is_some());
+
+        let outcome = commands.dispatch("goto").unwrap();
+        assert_eq!(outcome.message, Some(String::from("moved")));
+        assert_eq!(calls.borrow().len(), 1);
+        assert_eq!(
+            commands.dispatch("fail"),
+            Err(CommandError::Failed(String::from("it broke")))
+        );
+    }
+
+    #[test]
+    fn registry_help() {
+        let mut commands = registry();
+        assert_eq!(
+            commands.help(Some("g")),
+            Some(String::from(
+                "goto LINE [COLUMN] - Move the cursor (aliases: g)"
+            ))
+        );
+        assert_eq!(commands.help(Some("nothing")), None);
+        assert!(commands.unregister("fail").is_some());
+        assert_eq!(
+            commands.help(None),
+            Some(String::from(
+                "goto LINE [COLUMN] - Move the cursor\nset OPTION [VALUES...]"
+            ))
+        );
+    }
+}
@@ -0,0 +1,275 @@
+///
+/// Completer is a trait for offering completions of the CommandBar input
+///
+use std::cell::Cell;
+use std::ops::Range;
+
+/// A possible completion for the input
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    /// Byte range of the input that the candidate replaces
+    pub span: Range<usize>,
+    /// Text to replace the span with
+    pub replacement: String,
+    /// Optional description shown next to the candidate
+    pub description: Option<String>,
+    /// Character indexes in the replacement to highlight, such as the
+    /// characters matched by a fuzzy search
+    pub matches: Vec<usize>,
+}
+
+impl Candidate {
+    /// Build a candidate that replaces span with replacement
+    pub fn new(span: Range<usize>, replacement: &str) -> Self {
+        Candidate {
+            span,
+            replacement: String::from(replacement),
+            description: None,
+            matches: Vec::new(),
+        }
+    }
+
+    /// Add a description to the candidate
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(String::from(description));
+        self
+    }
+
+    /// Highlight the characters at indexes in the replacement
+    pub fn with_matches(mut self, matches: Vec<usize>) -> Self {
+        self.matches = matches;
+        self
+    }
+}
+
+/// Applications implement Completer to complete their own commands and
+/// arguments.
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::completion::completer::{Candidate, Completer};
+///
+/// struct Quit;
+///
+/// impl Completer for Quit {
+///     fn complete(&self, input: &str, cursor: usize) -> Vec<Candidate> {
+///         if "quit".starts_with(&input[..cursor]) {
+///             vec![Candidate::new(0..cursor, "quit")]
+///         } else {
+///             Vec::new()
+///         }
+///     }
+/// }
+///
+/// assert_eq!(Quit.complete("q", 1), vec![Candidate::new(0..1, "quit")]);
+/// ```
+pub trait Completer {
+    /// Candidates for completing input, with the cursor at byte offset cursor
+    fn complete(&self, input: &str, cursor: usize) -> Vec<Candidate>;
+}
+
+/// Byte offset of the start of the word ending at cursor
+/// Words are separated by whitespace.
+pub fn word_start(input: &str, cursor: usize) -> usize {
+    input[..cursor]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8())
+}
+
+/// WordCompleter completes the word before the cursor from a list of words
+#[derive(Clone, Debug, Default)]
+pub struct WordCompleter {
+    /// The words to complete
+    pub words: Vec<String>,
+}
+
+impl WordCompleter {
+    /// Build a WordCompleter from a list of words
+    pub fn new(words: &[&str]) -> Self {
+        WordCompleter {
+            words: words.iter().map(|w| String::from(*w)).collect(),
+        }
+    }
+}
+
+impl Completer for WordCompleter {
+    fn complete(&self, input: &str, cursor: usize) -> Vec<Candidate> {
+        let start = word_start(input, cursor);
+        let word = &input[start..cursor];
+        self.words
+            .iter()
+            .filter(|w| w.starts_with(word))
+            .map(|w| Candidate::new(start..cursor, w))
+            .collect()
+    }
+}
+
+/// The longest replacement shared by all candidates
+/// Returns None unless every candidate replaces the same span.
+pub fn common_prefix(candidates: &[Candidate]) -> Option<Candidate> {
+    let first = candidates.first()?;
+    if candidates.iter().any(|c| c.span != first.span) {
+        return None;
+    }
+
+    let mut prefix = first.replacement.as_str();
+    for candidate in &candidates[1..] {
+        let len = prefix
+            .char_indices()
+            .zip(candidate.replacement.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(
+                prefix.len().min(candidate.replacement.len()),
+                |((i, _), _)| i,
+            );
+        prefix = &prefix[..len];
+    }
+
+    Some(Candidate::new(first.span.clone(), prefix))
+}
+
+/// Whether the candidate span is a range of input on character boundaries
+/// Completers are application code, so their spans are checked before the
+/// input is sliced.
+pub fn is_valid_span(input: &str, candidate: &Candidate) -> bool {
+    input.get(candidate.span.clone()).is_some()
+}
+
+/// Replace the candidate span in input
+/// Returns the new input and the byte offset of the end of the replacement,
+/// or None if the span isn't valid for input.
+pub fn apply_candidate(input: &str, candidate: &Candidate) -> Option<(String, usize)> {
+    if !is_valid_span(input, candidate) {
+        return None;
+    }
+    let mut completed = String::from(input);
+    completed.replace_range(candidate.span.clone(), &candidate.replacement);
+    Some((
+        completed,
+        candidate.span.start + candidate.replacement.len(),
+    ))
+}
+
+/// The candidates being cycled through with Tab and Shift-Tab
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompletionState {
+    /// The input before completion started
+    pub original: String,
+    /// Byte offset of the cursor in the original input
+    pub cursor: usize,
+    /// The candidates returned by the Completer
+    pub candidates: Vec<Candidate>,
+    /// Index of the candidate currently applied to the input
+    pub selected: Option<usize>,
+    /// Index of the first candidate shown by the CompletionMenu
+    /// The menu keeps it between frames and only scrolls when the selected
+    /// candidate leaves the visible rows.
+    pub offset: Cell<usize>,
+}
+
+impl CompletionState {
+    /// Start cycling through candidates for the original input
+    pub fn new(original: &str, cursor: usize, candidates: Vec<Candidate>) -> Self {
+        CompletionState {
+            original: String::from(original),
+            cursor,
+            candidates,
+            selected: None,
+            offset: Cell::new(0),
+        }
+    }
+
+    /// Select the next candidate, wrapping around after the last one
+    pub fn select_next(&mut self) {
+        let len = self.candidates.len();
+        if len > 0 {
+            self.selected = Some(self.selected.map_or(0, |i| (i + 1) % len));
+        }
+    }
+
+    /// Select the previous candidate, wrapping around before the first one
+    pub fn select_previous(&mut self) {
+        let len = self.candidates.len();
+        if len > 0 {
+            self.selected = Some(self.selected.map_or(len - 1, |i| (i + len - 1) % len));
+        }
+    }
+
+    /// The original input completed with the selected candidate
+    /// Returns the input and the byte offset of the cursor
+    pub fn completed(&self) -> Option<(String, usize)> {
+        let candidate = self.candidates.get(self.selected?)?;
+        apply_candidate(&self.original, candidate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use crate::completion::completer::{
+        apply_candidate, common_prefix, word_start, Candidate, Completer, CompletionState,
+        WordCompleter,
+    };
+
+    #[test]
+    fn word_completer_completes_last_word() {
+        let completer = WordCompleter::new(&["write", "wq", "quit"]);
+        assert_eq!(word_start("set w", 5), 4);
+        assert_eq!(
+            completer.complete("set w", 5),
+            vec![Candidate::new(4..5, "write"), Candidate::new(4..5, "wq")]
+        );
+        assert!(completer.complete("x", 1).is_empty());
+    }
+
+    #[test]
+    fn common_prefix_of_candidates() {
+        let candidates = vec![
+            Candidate::new(0..1, "write"),
+            Candidate::new(0..1, "writeall"),
+            Candidate::new(0..1, "wrap"),
+        ];
+        assert_eq!(common_prefix(&candidates), Some(Candidate::new(0..1, "wr")));
+
+        let candidates = vec![Candidate::new(0..1, "write"), Candidate::new(2..3, "wq")];
+        assert_eq!(common_prefix(&candidates), None);
+        assert_eq!(common_prefix(&[]), None);
+    }
+
+    #[test]
+    fn apply_candidate_checks_span() {
+        let input = "\u{e9}t\u{e9}";
+        assert_eq!(
+            apply_candidate(input, &Candidate::new(2..3, "x")),
+            Some((String::from("\u{e9}x\u{e9}"), 3))
+        );
+        // Past the end, backwards, and inside a character
+        assert_eq!(apply_candidate(input, &Candidate::new(2..9, "x")), None);
+        let backwards = Candidate::new(Range { start: 3, end: 2 }, "x");
+        assert_eq!(apply_candidate(input, &backwards), None);
+        assert_eq!(apply_candidate(input, &Candidate::new(1..3, "x")), None);
+    }
+
+    #[test]
+    fn completion_state_cycles() {
+        let mut state = CompletionState::new(
+            "w x",
+            1,
+            vec![Candidate::new(0..1, "write"), Candidate::new(0..1, "wq")],
+        );
+        assert_eq!(state.completed(), None);
+
+        state.select_next();
+        assert_eq!(state.completed(), Some((String::from("write x"), 5)));
+        state.select_next();
+        assert_eq!(state.completed(), Some((String::from("wq x"), 2)));
+        state.select_next();
+        assert_eq!(state.selected, Some(0));
+        state.select_previous();
+        assert_eq!(state.selected, Some(1));
+    }
+}
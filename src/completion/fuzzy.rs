@@ -0,0 +1,269 @@
+///
+/// Fuzzy matching of a pattern against candidates
+/// A pattern matches text if its characters appear in the text in order.
+/// Matches are ranked with bonuses for matching at the start of words and
+/// for runs of consecutive characters.
+///
+use crate::completion::completer::{word_start, Candidate, Completer};
+
+/// Score for each matched character
+const SCORE_MATCH: i64 = 16;
+/// Bonus for a character matched right after the previous match
+const BONUS_CONSECUTIVE: i64 = 16;
+/// Bonus for a character matched at the start of a word
+const BONUS_BOUNDARY: i64 = 12;
+/// Penalty for each character skipped between two matches
+const PENALTY_GAP: i64 = 1;
+
+/// The result of matching a pattern against some text
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuzzyMatch {
+    /// Higher scores are better matches
+    pub score: i64,
+    /// Character indexes in the text of each matched pattern character
+    pub positions: Vec<usize>,
+}
+
+/// Whether the character at index starts a word
+fn is_boundary(text: &[char], index: usize) -> bool {
+    if index == 0 {
+        return true;
+    }
+    let previous = text[index - 1];
+    let current = text[index];
+    !previous.is_alphanumeric() || (previous.is_lowercase() && current.is_uppercase())
+}
+
+/// Lowercase c unless the match is case sensitive
+fn fold(c: char, case_sensitive: bool) -> char {
+    if case_sensitive {
+        c
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Whether the pattern characters all appear in text in order
+///
+/// This is the test fuzzy_match uses, without scoring the match.
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::completion::fuzzy::is_fuzzy_match;
+///
+/// assert!(is_fuzzy_match("wa", "write-all"));
+/// assert!(!is_fuzzy_match("aw", "write-all"));
+/// ```
+pub fn is_fuzzy_match(pattern: &str, text: &str) -> bool {
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let mut text = text.chars().map(|c| fold(c, case_sensitive));
+    pattern
+        .chars()
+        .map(|c| fold(c, case_sensitive))
+        .all(|p| text.any(|c| c == p))
+}
+
+/// Match pattern against text
+///
+/// Matching ignores case unless the pattern contains an uppercase letter.
+/// Returns None if the pattern characters don't all appear in order.
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::completion::fuzzy::fuzzy_match;
+///
+/// let m = fuzzy_match("wa", "write-all").unwrap();
+/// assert_eq!(m.positions, vec![0, 6]);
+/// assert!(fuzzy_match("aw", "write-all").is_none());
+/// ```
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    if !is_fuzzy_match(pattern, text) {
+        return None;
+    }
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let pattern: Vec<char> = pattern.chars().map(|c| fold(c, case_sensitive)).collect();
+    let original: Vec<char> = text.chars().collect();
+    let text: Vec<char> = original.iter().map(|c| fold(*c, case_sensitive)).collect();
+
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    // scores[j] is the best score for matching pattern[..=i] with
+    // pattern[i] matched at text[j], and previous holds the scores for
+    // pattern[..i]. parents[i][j] is where pattern[i - 1] was matched for
+    // that score.
+    let n = text.len();
+    let mut previous: Vec<Option<i64>> = vec![None; n];
+    let mut scores: Vec<Option<i64>> = vec![None; n];
+    let mut parents: Vec<Vec<usize>> = vec![vec![0; n]; pattern.len()];
+
+    for (i, p) in pattern.iter().enumerate() {
+        // The best previous + PENALTY_GAP * k over the matches k of
+        // pattern[i - 1] at least one character before j. Subtracting
+        // PENALTY_GAP * (j - 1) gives the score after the gap, so the gap
+        // matches don't have to be scanned for each j.
+        let mut gap: Option<(i64, usize)> = None;
+        for j in 0..n {
+            scores[j] = None;
+            if i > 0 && j >= 2 {
+                let k = j - 2;
+                if let Some(score) = previous[k] {
+                    let score = score + PENALTY_GAP * k as i64;
+                    // Earlier matches win ties
+                    match gap {
+                        Some((best, _)) if score <= best => (),
+                        _ => gap = Some((score, k)),
+                    }
+                }
+            }
+            if j < i || text[j] != *p {
+                continue;
+            }
+            let mut score = SCORE_MATCH;
+            if is_boundary(&original, j) {
+                score += BONUS_BOUNDARY;
+            }
+            if i == 0 {
+                scores[j] = Some(score);
+                continue;
+            }
+
+            let after_gap = gap.map(|(best, k)| (best - PENALTY_GAP * (j - 1) as i64, k));
+            let consecutive = previous[j - 1].map(|s| (s + BONUS_CONSECUTIVE, j - 1));
+            let best = match (after_gap, consecutive) {
+                (Some(a), Some(c)) if c.0 > a.0 => Some(c),
+                (Some(a), _) => Some(a),
+                (None, c) => c,
+            };
+            if let Some((previous, k)) = best {
+                scores[j] = Some(previous + score);
+                parents[i][j] = k;
+            }
+        }
+        std::mem::swap(&mut previous, &mut scores);
+    }
+
+    let (score, end) = (0..n)
+        .filter_map(|j| previous[j].map(|s| (s, j)))
+        .max_by_key(|(s, j)| (*s, std::cmp::Reverse(*j)))?;
+
+    let mut positions = vec![end];
+    let mut j = end;
+    for i in (1..pattern.len()).rev() {
+        j = parents[i][j];
+        positions.push(j);
+    }
+    positions.reverse();
+
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Match pattern against each item and return the index and match of the
+/// items that matched, best match first
+/// Items with equal scores keep their original order.
+pub fn rank<'i, I>(pattern: &str, items: I) -> Vec<(usize, FuzzyMatch)>
+where
+    I: IntoIterator<Item = &'i str>,
+{
+    let mut ranked: Vec<(usize, FuzzyMatch)> = items
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, item)| fuzzy_match(pattern, item).map(|m| (i, m)))
+        .collect();
+    ranked.sort_by_key(|(_, m)| std::cmp::Reverse(m.score));
+    ranked
+}
+
+/// FuzzyCompleter completes the word before the cursor from a list of
+/// words, ranked by how well they fuzzy match the word
+#[derive(Clone, Debug, Default)]
+pub struct FuzzyCompleter {
+    /// The words to complete
+    pub words: Vec<String>,
+}
+
+impl FuzzyCompleter {
+    /// Build a FuzzyCompleter from a list of words
+    pub fn new(words: &[&str]) -> Self {
+        FuzzyCompleter {
+            words: words.iter().map(|w| String::from(*w)).collect(),
+        }
+    }
+}
+
+impl Completer for FuzzyCompleter {
+    fn complete(&self, input: &str, cursor: usize) -> Vec<Candidate> {
+        let start = word_start(input, cursor);
+        let word = &input[start..cursor];
+        rank(word, self.words.iter().map(String::as_str))
+            .into_iter()
+            .map(|(i, m)| Candidate::new(start..cursor, &self.words[i]).with_matches(m.positions))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::completion::completer::{Candidate, Completer};
+    use crate::completion::fuzzy::{fuzzy_match, is_fuzzy_match, rank, FuzzyCompleter};
+
+    #[test]
+    fn fuzzy_match_subsequence() {
+        assert_eq!(
+            fuzzy_match("", "abc").unwrap().positions,
+            Vec::<usize>::new()
+        );
+        assert_eq!(fuzzy_match("ac", "abc").unwrap().positions, vec![0, 2]);
+        assert!(fuzzy_match("ca", "abc").is_none());
+        assert!(fuzzy_match("abcd", "abc").is_none());
+        assert!(is_fuzzy_match("ac", "abc"));
+        assert!(!is_fuzzy_match("ca", "abc"));
+    }
+
+    #[test]
+    fn fuzzy_match_smart_case() {
+        assert!(fuzzy_match("ab", "AB").is_some());
+        assert!(fuzzy_match("Ab", "ab").is_none());
+        assert!(fuzzy_match("Ab", "Ab").is_some());
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_boundaries_and_runs() {
+        // The f of file at a word start beats the f in buffer
+        assert_eq!(fuzzy_match("f", "buffer-file").unwrap().positions, vec![7]);
+        // A consecutive run beats scattered matches
+        assert_eq!(fuzzy_match("ab", "a-xb-ab").unwrap().positions, vec![5, 6]);
+        // camelCase humps count as word starts
+        assert_eq!(fuzzy_match("of", "openFile").unwrap().positions, vec![0, 4]);
+    }
+
+    #[test]
+    fn fuzzy_match_long_text() {
+        let text = format!("{}write-all", "x-".repeat(5000));
+        let m = fuzzy_match("wa", &text).unwrap();
+        assert_eq!(m.positions, vec![10000, 10006]);
+        assert!(fuzzy_match("wq", &text).is_none());
+    }
+
+    #[test]
+    fn rank_orders_by_score() {
+        let ranked = rank("wq", ["write-quit", "wq", "xwxq", "read"]);
+        let order: Vec<usize> = ranked.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order, vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn fuzzy_completer_returns_positions() {
+        let completer = FuzzyCompleter::new(&["open", "write-all"]);
+        assert_eq!(
+            completer.complete("set wa", 6),
+            vec![Candidate::new(4..6, "write-all").with_matches(vec![0, 6])]
+        );
+    }
+}
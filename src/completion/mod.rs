@@ -0,0 +1,12 @@
+///
+/// completion is a module for completing the input in a CommandBar
+/// Applications implement the Completer trait to offer candidates when
+/// Tab is pressed.
+///
+pub mod completer;
+
+/// Fuzzy matching for ranking completions and searching history
+pub mod fuzzy;
+
+/// Completion of file and directory paths
+pub mod path;
@@ -0,0 +1,270 @@
+///
+/// Completion of file and directory paths
+///
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::command::parser::scan;
+use crate::completion::completer::{Candidate, Completer};
+
+/// The argument being completed
+#[derive(Clone, Debug, PartialEq)]
+struct Argument {
+    /// Byte offset of the start of the argument in the input
+    start: usize,
+    /// The argument with quotes and escapes removed
+    text: String,
+    /// The quote character the argument starts with, if any
+    quote: Option<char>,
+}
+
+/// Find the argument that ends at cursor
+/// The argument may still have an open quote.
+fn current_argument(input: &str, cursor: usize) -> Argument {
+    let (tokens, _) = scan(&input[..cursor]);
+    match tokens.into_iter().last() {
+        Some(token) if token.span.end == cursor => Argument {
+            start: token.span.start,
+            quote: input[token.span.start..]
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\''),
+            text: token.text,
+        },
+        // The cursor is after whitespace, so a new argument is starting
+        _ => Argument {
+            start: cursor,
+            text: String::new(),
+            quote: None,
+        },
+    }
+}
+
+/// Quote text so it is read back as a single argument
+/// quote is the quote character the user started the argument with.
+fn quote_argument(text: &str, quote: Option<char>) -> String {
+    let needs_quotes = text
+        .chars()
+        .any(|c| c.is_whitespace() || c == '"' || c == '\'' || c == '\\');
+    match quote {
+        Some('\'') if !text.contains('\'') => format!("'{}'", text),
+        None if !needs_quotes => String::from(text),
+        _ => format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")),
+    }
+}
+
+/// PathCompleter completes file and directory paths
+///
+/// Relative paths are completed from base_dir, and a leading ~ is
+/// expanded to the home directory.
+/// Directories are completed with a trailing /, and paths that contain
+/// spaces are quoted.
+///
+/// # Example
+///
+/// ```
+/// use std::rc::Rc;
+/// use tui_command_bar_widget::completion::path::PathCompleter;
+/// use tui_command_bar_widget::widgets::command_bar::CommandBar;
+///
+/// let mut command_bar_widget = CommandBar::default();
+/// command_bar_widget.completer = Some(Rc::new(PathCompleter::new(".")));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PathCompleter {
+    /// Directory that relative paths are completed from
+    pub base_dir: PathBuf,
+    /// Directory that ~ expands to, if any
+    pub home_dir: Option<PathBuf>,
+    /// Complete hidden files even if the typed name doesn't start with a dot
+    pub show_hidden: bool,
+}
+
+impl Default for PathCompleter {
+    fn default() -> PathCompleter {
+        PathCompleter {
+            base_dir: PathBuf::from("."),
+            home_dir: env::var_os("HOME").map(PathBuf::from),
+            show_hidden: false,
+        }
+    }
+}
+
+impl PathCompleter {
+    /// Build a PathCompleter that completes relative paths from base_dir
+    pub fn new<P: AsRef<Path>>(base_dir: P) -> Self {
+        PathCompleter {
+            base_dir: base_dir.as_ref().to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    /// The directory to list for the directory part of a typed path
+    fn directory(&self, dir: &str) -> Option<PathBuf> {
+        let expanded = if dir == "~" || dir.starts_with("~/") {
+            self.home_dir
+                .as_ref()?
+                .join(dir[1..].trim_start_matches('/'))
+        } else {
+            PathBuf::from(dir)
+        };
+        Some(self.base_dir.join(expanded))
+    }
+}
+
+impl Completer for PathCompleter {
+    fn complete(&self, input: &str, cursor: usize) -> Vec<Candidate> {
+        let argument = current_argument(input, cursor);
+        let text = argument.text.as_str();
+
+        // Split the typed path into the directory to list and the start of
+        // the name to complete, ~ on its own is completed as a directory
+        let (dir, prefix) = match text.rfind('/') {
+            Some(i) => text.split_at(i + 1),
+            None if text == "~" => return vec![Candidate::new(argument.start..cursor, "~/")],
+            None => ("", text),
+        };
+        let directory = match self.directory(dir) {
+            Some(directory) => directory,
+            None => return Vec::new(),
+        };
+
+        let entries = match fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("Can't complete in {:?}: {}", directory, e);
+                return Vec::new();
+            }
+        };
+        let show_hidden = self.show_hidden || prefix.starts_with('.');
+        let mut paths: Vec<String> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                if !name.starts_with(prefix) || (name.starts_with('.') && !show_hidden) {
+                    return None;
+                }
+                // Follow symlinks so links to directories get a /
+                let is_dir = entry.path().is_dir();
+                Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+            })
+            .collect();
+        paths.sort();
+
+        paths
+            .iter()
+            .map(|path| {
+                Candidate::new(
+                    argument.start..cursor,
+                    &quote_argument(path, argument.quote),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::completion::completer::Completer;
+    use crate::completion::path::{current_argument, quote_argument, PathCompleter};
+
+    /// Build a directory of files to complete, removing any old copy
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "tui-command-bar-widget-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("alps")).unwrap();
+        fs::create_dir_all(dir.join("My Docs")).unwrap();
+        fs::write(dir.join("alpha.txt"), "").unwrap();
+        fs::write(dir.join("alps").join("peak"), "").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+        dir
+    }
+
+    fn replacements(completer: &PathCompleter, input: &str) -> Vec<String> {
+        completer
+            .complete(input, input.len())
+            .into_iter()
+            .map(|c| c.replacement)
+            .collect()
+    }
+
+    #[test]
+    fn current_argument_handles_quotes() {
+        let argument = current_argument("open \"My D", 10);
+        assert_eq!(argument.start, 5);
+        assert_eq!(argument.text, "My D");
+        assert_eq!(argument.quote, Some('"'));
+
+        let argument = current_argument("open My\\ D", 10);
+        assert_eq!(argument.text, "My D");
+        assert_eq!(argument.quote, None);
+
+        assert_eq!(current_argument("open ", 5).start, 5);
+    }
+
+    #[test]
+    fn quote_argument_styles() {
+        assert_eq!(quote_argument("plain", None), "plain");
+        assert_eq!(quote_argument("My Docs/", None), "\"My Docs/\"");
+        assert_eq!(quote_argument("My Docs/", Some('\'')), "'My Docs/'");
+        assert_eq!(quote_argument("it's", Some('\'')), "\"it's\"");
+    }
+
+    #[test]
+    fn path_completer_completes_names() {
+        let dir = test_dir("path-names");
+        let completer = PathCompleter::new(&dir);
+
+        assert_eq!(
+            replacements(&completer, "open al"),
+            vec!["alpha.txt", "alps/"]
+        );
+        assert_eq!(replacements(&completer, "open alps/"), vec!["alps/peak"]);
+        assert_eq!(replacements(&completer, "open M"), vec!["\"My Docs/\""]);
+        assert_eq!(replacements(&completer, "open \"My"), vec!["\"My Docs/\""]);
+        assert!(replacements(&completer, "open missing/").is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn path_completer_hidden_files() {
+        let dir = test_dir("path-hidden");
+        let mut completer = PathCompleter::new(&dir);
+
+        assert!(!replacements(&completer, "open ").contains(&String::from(".hidden")));
+        assert_eq!(replacements(&completer, "open ."), vec![".hidden"]);
+        completer.show_hidden = true;
+        assert!(replacements(&completer, "open ").contains(&String::from(".hidden")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn path_completer_expands_home() {
+        let dir = test_dir("path-home");
+        let completer = PathCompleter {
+            base_dir: PathBuf::from("/nonexistent"),
+            home_dir: Some(dir.clone()),
+            show_hidden: false,
+        };
+
+        assert_eq!(replacements(&completer, "open ~"), vec!["~/"]);
+        assert_eq!(
+            replacements(&completer, "open ~/al"),
+            vec!["~/alpha.txt", "~/alps/"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
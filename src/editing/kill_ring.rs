@@ -0,0 +1,151 @@
+///
+/// KillRing stores text deleted by the readline kill actions
+///
+use std::mem;
+
+/// Which side of the cursor text was killed from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KillDirection {
+    /// Text before the cursor, like Ctrl-W and Ctrl-U
+    Backward,
+    /// Text after the cursor, like Alt-D and Ctrl-K
+    Forward,
+}
+
+/// KillRing is a list of killed text, oldest first
+///
+/// Consecutive kills are joined into one entry like they are in readline,
+/// so Ctrl-W pressed twice yanks both words back.
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::editing::kill_ring::{KillDirection, KillRing};
+///
+/// let mut kill_ring = KillRing::default();
+/// kill_ring.start_key();
+/// kill_ring.kill("world", KillDirection::Backward);
+/// kill_ring.start_key();
+/// kill_ring.kill("hello ", KillDirection::Backward);
+/// assert_eq!(kill_ring.yank(), Some("hello world"));
+///
+/// kill_ring.start_key();
+/// kill_ring.start_key();
+/// kill_ring.kill("again", KillDirection::Forward);
+/// assert_eq!(kill_ring.entries, vec!["hello world", "again"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KillRing {
+    /// The killed text, oldest first
+    pub entries: Vec<String>,
+    /// The maximum number of entries to keep, older entries are dropped
+    pub max_len: usize,
+    /// Whether the key being handled continues the kill of the last key
+    appending: bool,
+    /// Whether the key being handled killed text
+    killed: bool,
+}
+
+impl Default for KillRing {
+    fn default() -> KillRing {
+        KillRing {
+            entries: Vec::new(),
+            max_len: 60,
+            appending: false,
+            killed: false,
+        }
+    }
+}
+
+impl KillRing {
+    /// Start handling a key
+    /// Text killed for the key is added to the last entry if the previous
+    /// key killed text too, otherwise it starts a new entry.
+    pub fn start_key(&mut self) {
+        self.appending = mem::take(&mut self.killed);
+    }
+
+    /// Record text killed from direction of the cursor
+    pub fn kill(&mut self, text: &str, direction: KillDirection) {
+        self.killed = true;
+        if text.is_empty() {
+            return;
+        }
+        match self.entries.last_mut() {
+            Some(last) if self.appending => match direction {
+                KillDirection::Backward => last.insert_str(0, text),
+                KillDirection::Forward => last.push_str(text),
+            },
+            _ => {
+                self.push(text);
+                // Later kills for the same key join this entry
+                self.appending = true;
+            }
+        }
+    }
+
+    /// Add text as a new entry, even if the last key killed text
+    /// This is how vi deletes and copies text.
+    pub fn push(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.entries.push(String::from(text));
+        if self.entries.len() > self.max_len {
+            let excess = self.entries.len() - self.max_len;
+            self.entries.drain(..excess);
+        }
+    }
+
+    /// The most recently killed text
+    pub fn yank(&self) -> Option<&str> {
+        self.get(0)
+    }
+
+    /// The text killed back kills ago, where 0 is the most recent
+    pub fn get(&self, back: usize) -> Option<&str> {
+        let index = self.entries.len().checked_sub(back + 1)?;
+        Some(self.entries[index].as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editing::kill_ring::{KillDirection, KillRing};
+
+    #[test]
+    fn kill_ring_joins_consecutive_kills() {
+        let mut kill_ring = KillRing::default();
+        assert_eq!(kill_ring.yank(), None);
+
+        kill_ring.start_key();
+        kill_ring.kill("open ", KillDirection::Forward);
+        kill_ring.start_key();
+        kill_ring.kill("", KillDirection::Forward);
+        kill_ring.start_key();
+        kill_ring.kill("file", KillDirection::Forward);
+        assert_eq!(kill_ring.entries, vec!["open file"]);
+
+        // A key that doesn't kill ends the entry
+        kill_ring.start_key();
+        kill_ring.start_key();
+        kill_ring.kill("quit", KillDirection::Backward);
+        assert_eq!(kill_ring.yank(), Some("quit"));
+        assert_eq!(kill_ring.get(1), Some("open file"));
+        assert_eq!(kill_ring.get(2), None);
+    }
+
+    #[test]
+    fn kill_ring_drops_the_oldest_entries() {
+        let mut kill_ring = KillRing {
+            max_len: 2,
+            ..Default::default()
+        };
+        for text in ["a", "b", "c"] {
+            kill_ring.start_key();
+            kill_ring.start_key();
+            kill_ring.kill(text, KillDirection::Backward);
+        }
+        assert_eq!(kill_ring.entries, vec!["b", "c"]);
+    }
+}
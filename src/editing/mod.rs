@@ -0,0 +1,19 @@
+///
+/// editing is a module for the text editing state of the CommandBar
+/// Text deleted by the kill actions is kept so it can be yanked back,
+/// changes can be undone, and the command can be edited with vi keys
+/// instead of readline keys.
+///
+pub mod kill_ring;
+
+/// The kill ring and named registers, which can be shared by CommandBars
+pub mod registers;
+
+/// Vi editing, with insert and normal sub-modes
+pub mod vi;
+
+/// Undoing and redoing changes to the input
+pub mod undo;
+
+/// Newline policies for pasted text
+pub mod paste;
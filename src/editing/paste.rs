@@ -0,0 +1,146 @@
+///
+/// Text pasted into the command, which arrives as one event when the
+/// terminal has bracketed paste enabled
+///
+use std::fmt;
+use std::str::FromStr;
+
+/// What to do with newlines in pasted text, since the command is one line
+///
+/// Newlines at the end of the text are always dropped, so a copied line
+/// can be pasted with any policy.
+/// Other control characters are never pasted, see strip_controls.
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::editing::paste::PastePolicy;
+///
+/// let text = "open one\r\nopen two\n";
+/// assert_eq!(PastePolicy::Strip.apply(text), Some(String::from("open oneopen two")));
+/// assert_eq!(PastePolicy::Join.apply(text), Some(String::from("open one open two")));
+/// assert_eq!(PastePolicy::Reject.apply(text), None);
+/// assert_eq!(PastePolicy::Reject.apply("open one\n"), Some(String::from("open one")));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PastePolicy {
+    /// Remove the newlines
+    Strip,
+    /// Replace each line break with a space
+    #[default]
+    Join,
+    /// Don't paste text with more than one line
+    Reject,
+}
+
+/// Error parsing a paste policy that isn't "strip", "join" or "reject"
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownPastePolicy(pub String);
+
+impl fmt::Display for UnknownPastePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown paste policy: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownPastePolicy {}
+
+impl FromStr for PastePolicy {
+    type Err = UnknownPastePolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strip" => Ok(PastePolicy::Strip),
+            "join" => Ok(PastePolicy::Join),
+            "reject" => Ok(PastePolicy::Reject),
+            _ => Err(UnknownPastePolicy(String::from(s))),
+        }
+    }
+}
+
+impl PastePolicy {
+    /// The text to insert for pasted text, or None if it's rejected
+    /// Line breaks can be \n, \r\n or \r.
+    /// The control characters left after the line breaks are handled are
+    /// removed with strip_controls.
+    pub fn apply(&self, text: &str) -> Option<String> {
+        let text = text.trim_end_matches(['\r', '\n']);
+        let text = match self {
+            PastePolicy::Strip => text.replace(['\r', '\n'], ""),
+            PastePolicy::Join => text.replace("\r\n", " ").replace(['\r', '\n'], " "),
+            PastePolicy::Reject if text.contains(['\r', '\n']) => return None,
+            PastePolicy::Reject => String::from(text),
+        };
+        Some(strip_controls(&text))
+    }
+}
+
+/// Remove the control characters from text, since the input is drawn as
+/// it is and they would be written to the terminal
+///
+/// Tabs become spaces, and escape sequences like ESC [ 3 1 m are removed
+/// whole so their parameters aren't left behind.
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::editing::paste::strip_controls;
+///
+/// assert_eq!(strip_controls("\x1b[1mopen\x1b[0m\tfile\x07"), "open file");
+/// ```
+pub fn strip_controls(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\t' => stripped.push(' '),
+            // A CSI sequence ends with a character from @ to ~
+            '\x1b' if chars.as_str().starts_with('[') => {
+                chars.by_ref().skip(1).find(|c| ('@'..='~').contains(c));
+            }
+            c if c.is_control() => (),
+            c => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editing::paste::{strip_controls, PastePolicy, UnknownPastePolicy};
+
+    #[test]
+    fn paste_policy_handles_line_breaks() {
+        let text = "a\rb\r\n\nc\r\n\r\n";
+        assert_eq!(PastePolicy::Strip.apply(text), Some(String::from("abc")));
+        assert_eq!(PastePolicy::Join.apply(text), Some(String::from("a b  c")));
+        assert_eq!(PastePolicy::Reject.apply(text), None);
+        assert_eq!(PastePolicy::Reject.apply("\n"), Some(String::new()));
+    }
+
+    #[test]
+    fn paste_policy_drops_control_characters() {
+        let text = "\x1b[31mred\x1b[0m\tfile\x07\x7f\x1bx\r\n";
+        assert_eq!(
+            PastePolicy::Join.apply(text),
+            Some(String::from("red filex"))
+        );
+        assert_eq!(
+            PastePolicy::Reject.apply(text),
+            Some(String::from("red filex"))
+        );
+        // An escape sequence cut off at the end is dropped too
+        assert_eq!(strip_controls("a\x1b[3"), "a");
+    }
+
+    #[test]
+    fn paste_policy_parses() {
+        assert_eq!("strip".parse(), Ok(PastePolicy::Strip));
+        assert_eq!("join".parse(), Ok(PastePolicy::Join));
+        assert_eq!("reject".parse(), Ok(PastePolicy::Reject));
+        assert_eq!(
+            "keep".parse::<PastePolicy>(),
+            Err(UnknownPastePolicy(String::from("keep")))
+        );
+    }
+}
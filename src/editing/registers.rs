@@ -0,0 +1,178 @@
+///
+/// Registers hold killed and copied text, and can be shared by CommandBars
+///
+use std::collections::HashMap;
+
+use crate::editing::kill_ring::{KillDirection, KillRing};
+
+/// A clipboard outside the CommandBar, like the system clipboard or an
+/// OSC 52 terminal sequence
+///
+/// The Registers copy each kill to the clipboard, and yank from it when it
+/// has text that isn't in the kill ring.
+pub trait Clipboard {
+    /// Copy text to the clipboard
+    fn copy(&mut self, text: &str);
+
+    /// The text on the clipboard, if any
+    fn paste(&mut self) -> Option<String>;
+}
+
+/// Text yanked into the input, which yank-pop can replace with an older kill
+#[derive(Clone, Debug, PartialEq)]
+pub struct Yanked {
+    /// Where the text starts in the input, counted in graphemes
+    pub start: usize,
+    /// The text that was inserted
+    pub text: String,
+    /// How far back in the kill ring the text is, 0 for the newest kill
+    pub index: usize,
+}
+
+/// Registers store the kill ring, and named registers for vi
+///
+/// CommandBars share registers through an Rc, so text killed in one can be
+/// yanked in another.
+///
+/// # Example
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use tui_command_bar_widget::editing::registers::Registers;
+/// use tui_command_bar_widget::widgets::command_bar::CommandBar;
+///
+/// let registers = Rc::new(RefCell::new(Registers::default()));
+/// let mut first = CommandBar {
+///     registers: Rc::clone(&registers),
+///     ..Default::default()
+/// };
+/// let mut second = CommandBar {
+///     registers: Rc::clone(&registers),
+///     ..Default::default()
+/// };
+///
+/// first.input = String::from("open file");
+/// first.cursor = 9;
+/// first.delete_word_backward();
+/// second.yank();
+/// assert_eq!(second.input, "file");
+/// ```
+#[derive(Default)]
+pub struct Registers {
+    /// Text killed with the readline keys or deleted with vi keys
+    pub kill_ring: KillRing,
+    /// Text copied into the vi registers named a to z
+    pub named: HashMap<char, String>,
+    /// A clipboard kills are copied to and yanks paste from, if any
+    pub clipboard: Option<Box<dyn Clipboard>>,
+}
+
+impl Registers {
+    /// Copy the newest kill to the clipboard
+    fn copy_to_clipboard(&mut self) {
+        if let (Some(clipboard), Some(text)) = (&mut self.clipboard, self.kill_ring.yank()) {
+            clipboard.copy(text);
+        }
+    }
+
+    /// Record text killed from direction of the cursor
+    /// Consecutive kills are joined, see KillRing::kill.
+    pub fn kill(&mut self, text: &str, direction: KillDirection) {
+        self.kill_ring.kill(text, direction);
+        if !text.is_empty() {
+            self.copy_to_clipboard();
+        }
+    }
+
+    /// Add text to the kill ring as a new entry
+    pub fn push(&mut self, text: &str) {
+        self.kill_ring.push(text);
+        if !text.is_empty() {
+            self.copy_to_clipboard();
+        }
+    }
+
+    /// The text to yank, which is the text on the clipboard if it's
+    /// changed since the last kill, otherwise the newest kill
+    pub fn yank(&mut self) -> Option<String> {
+        let pasted = self.clipboard.as_mut().and_then(|c| c.paste());
+        if let Some(text) = pasted {
+            if self.kill_ring.yank() != Some(text.as_str()) {
+                self.kill_ring.push(&text);
+            }
+        }
+        self.kill_ring.yank().map(String::from)
+    }
+
+    /// Set a named register
+    /// An uppercase name appends to the lowercase register, like vi.
+    pub fn set(&mut self, name: char, text: &str) {
+        let register = self.named.entry(name.to_ascii_lowercase()).or_default();
+        if !name.is_uppercase() {
+            register.clear();
+        }
+        register.push_str(text);
+    }
+
+    /// The text in a named register
+    pub fn get(&self, name: char) -> Option<&str> {
+        self.named
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::editing::kill_ring::KillDirection;
+    use crate::editing::registers::{Clipboard, Registers};
+
+    /// A clipboard that records what's copied to it
+    struct TestClipboard(Rc<RefCell<Vec<String>>>);
+
+    impl Clipboard for TestClipboard {
+        fn copy(&mut self, text: &str) {
+            self.0.borrow_mut().push(String::from(text));
+        }
+
+        fn paste(&mut self) -> Option<String> {
+            self.0.borrow().last().cloned()
+        }
+    }
+
+    #[test]
+    fn registers_bridge_a_clipboard() {
+        let copied = Rc::new(RefCell::new(Vec::new()));
+        let mut registers = Registers {
+            clipboard: Some(Box::new(TestClipboard(Rc::clone(&copied)))),
+            ..Default::default()
+        };
+        registers.kill_ring.start_key();
+        registers.kill("one", KillDirection::Forward);
+        registers.kill(" two", KillDirection::Forward);
+        assert_eq!(*copied.borrow(), vec!["one", "one two"]);
+        assert_eq!(registers.yank(), Some(String::from("one two")));
+        assert_eq!(registers.kill_ring.entries.len(), 1);
+
+        // Text copied by another application is yanked and kept
+        copied.borrow_mut().push(String::from("outside"));
+        assert_eq!(registers.yank(), Some(String::from("outside")));
+        assert_eq!(registers.kill_ring.entries, vec!["one two", "outside"]);
+    }
+
+    #[test]
+    fn registers_name_registers() {
+        let mut registers = Registers::default();
+        registers.set('a', "open");
+        registers.set('A', " file");
+        assert_eq!(registers.get('a'), Some("open file"));
+        registers.set('a', "quit");
+        assert_eq!(registers.get('A'), Some("quit"));
+        assert_eq!(registers.get('b'), None);
+        assert_eq!(registers.yank(), None);
+    }
+}
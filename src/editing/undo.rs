@@ -0,0 +1,170 @@
+///
+/// UndoHistory records the input before each change, so changes can be
+/// undone and redone
+///
+use std::mem;
+
+/// The input and cursor at some point while editing
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    /// The text of the input
+    pub input: String,
+    /// The cursor position, counted in graphemes
+    pub cursor: usize,
+}
+
+/// UndoHistory is a stack of the states before each change, and a stack of
+/// the states that were undone
+///
+/// Characters typed one after another are one change, so undo removes the
+/// whole run of them.
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::editing::undo::{Snapshot, UndoHistory};
+///
+/// let snapshot = |input: &str| Snapshot {
+///     input: String::from(input),
+///     cursor: input.len(),
+/// };
+/// let mut undo_history = UndoHistory::default();
+/// // Type w and q, then delete the q
+/// undo_history.record(snapshot(""), true);
+/// undo_history.record(snapshot("w"), true);
+/// undo_history.record(snapshot("wq"), false);
+///
+/// assert_eq!(undo_history.undo(snapshot("w")), Some(snapshot("wq")));
+/// assert_eq!(undo_history.undo(snapshot("wq")), Some(snapshot("")));
+/// assert_eq!(undo_history.undo(snapshot("")), None);
+/// assert_eq!(undo_history.redo(snapshot("")), Some(snapshot("wq")));
+/// ```
+#[derive(Clone, Debug)]
+pub struct UndoHistory {
+    /// The states before each change, oldest first
+    pub undo: Vec<Snapshot>,
+    /// The states that were undone, most recently undone last
+    pub redo: Vec<Snapshot>,
+    /// The maximum number of changes to keep, older changes are dropped
+    pub max_len: usize,
+    /// Whether the last change was typing, so more typing joins it
+    typing: bool,
+    /// Whether the key being handled undid or redid a change
+    restoring: bool,
+}
+
+impl Default for UndoHistory {
+    fn default() -> UndoHistory {
+        UndoHistory {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            max_len: 100,
+            typing: false,
+            restoring: false,
+        }
+    }
+}
+
+impl UndoHistory {
+    /// Start handling a key
+    pub fn start_key(&mut self) {
+        self.restoring = false;
+    }
+
+    /// Record the state before a change, and forget the undone changes
+    /// typed is true for a typed character, which joins the change before
+    /// it if that was typed too.
+    /// Changes made by undo and redo aren't recorded.
+    pub fn record(&mut self, before: Snapshot, typed: bool) {
+        if self.restoring {
+            return;
+        }
+        let was_typing = mem::replace(&mut self.typing, typed);
+        if !(typed && was_typing) {
+            self.undo.push(before);
+            if self.undo.len() > self.max_len {
+                let excess = self.undo.len() - self.max_len;
+                self.undo.drain(..excess);
+            }
+        }
+        self.redo.clear();
+    }
+
+    /// End the current run of typing, for example when the cursor moves
+    pub fn break_typing(&mut self) {
+        self.typing = false;
+    }
+
+    /// Undo the last change, returning the state to restore
+    /// current is kept so the change can be redone.
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.undo.pop()?;
+        self.redo.push(current);
+        self.typing = false;
+        self.restoring = true;
+        Some(snapshot)
+    }
+
+    /// Redo the last undone change, returning the state to restore
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.redo.pop()?;
+        self.undo.push(current);
+        self.typing = false;
+        self.restoring = true;
+        Some(snapshot)
+    }
+
+    /// Forget every change
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.typing = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editing::undo::{Snapshot, UndoHistory};
+
+    fn snapshot(input: &str) -> Snapshot {
+        Snapshot {
+            input: String::from(input),
+            cursor: input.chars().count(),
+        }
+    }
+
+    #[test]
+    fn undo_history_groups_typing() {
+        let mut undo_history = UndoHistory::default();
+        for input in ["", "o", "op"] {
+            undo_history.record(snapshot(input), true);
+        }
+        undo_history.break_typing();
+        undo_history.record(snapshot("ope"), true);
+        assert_eq!(undo_history.undo, vec![snapshot(""), snapshot("ope")]);
+
+        // Changes made while restoring aren't recorded
+        undo_history.undo(snapshot("open"));
+        undo_history.record(snapshot("open"), false);
+        assert_eq!(undo_history.undo.len(), 1);
+
+        undo_history.start_key();
+        undo_history.record(snapshot("ope"), false);
+        assert!(undo_history.redo.is_empty());
+        assert_eq!(undo_history.undo.len(), 2);
+    }
+
+    #[test]
+    fn undo_history_drops_the_oldest_changes() {
+        let mut undo_history = UndoHistory {
+            max_len: 2,
+            ..Default::default()
+        };
+        for input in ["a", "b", "c"] {
+            undo_history.record(snapshot(input), false);
+        }
+        assert_eq!(undo_history.undo, vec![snapshot("b"), snapshot("c")]);
+        undo_history.clear();
+        assert_eq!(undo_history.undo(snapshot("d")), None);
+    }
+}
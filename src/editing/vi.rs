@@ -0,0 +1,440 @@
+///
+/// Vi keys for editing the command, with insert and normal sub-modes
+///
+use std::fmt;
+use std::iter::Peekable;
+use std::str::FromStr;
+
+/// The keys used to edit the command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditingKeys {
+    /// Readline keys, like Ctrl-A and Alt-F
+    Readline,
+    /// Vi keys, with insert and normal sub-modes
+    Vi,
+}
+
+/// Error parsing editing keys that aren't "readline" or "vi"
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownEditingKeys(pub String);
+
+impl fmt::Display for UnknownEditingKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown editing keys: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownEditingKeys {}
+
+impl FromStr for EditingKeys {
+    type Err = UnknownEditingKeys;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "readline" | "emacs" => Ok(EditingKeys::Readline),
+            "vi" => Ok(EditingKeys::Vi),
+            _ => Err(UnknownEditingKeys(String::from(s))),
+        }
+    }
+}
+
+/// The sub-modes of vi editing
+/// These are only used while the CommandBar is in InputMode::Editing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViMode {
+    /// Keys type into the input
+    Insert,
+    /// Keys are motions and operators
+    Normal,
+}
+
+/// Where the cursor moves to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Motion {
+    /// h, one grapheme left
+    Left,
+    /// l, one grapheme right
+    Right,
+    /// w, the start of the next word
+    WordForward,
+    /// b, the start of the previous word
+    WordBackward,
+    /// e, the end of the word
+    WordEnd,
+    /// 0, the start of the input
+    LineStart,
+    /// $, the end of the input
+    LineEnd,
+    /// f, t, F and T, a character in the input
+    Find {
+        /// The character to find
+        c: char,
+        /// Whether to search after the cursor
+        forward: bool,
+        /// Whether to stop next to the character, for t and T
+        till: bool,
+    },
+    /// The whole input, for dd, cc and yy
+    WholeLine,
+}
+
+impl Motion {
+    /// Whether an operator includes the grapheme the motion stops on
+    pub fn is_inclusive(&self) -> bool {
+        match self {
+            Motion::WordEnd | Motion::LineEnd => true,
+            Motion::Find { forward, .. } => *forward,
+            _ => false,
+        }
+    }
+}
+
+/// What an operator does with the text a motion moves over
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    /// d, delete the text
+    Delete,
+    /// c, delete the text and start inserting
+    Change,
+    /// y, copy the text
+    Yank,
+}
+
+/// Where i, a, I and A start inserting
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InsertAt {
+    /// i, before the cursor
+    Cursor,
+    /// a, after the cursor
+    After,
+    /// I, at the start of the input
+    Start,
+    /// A, at the end of the input
+    End,
+}
+
+/// A complete vi normal mode command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViCommand {
+    /// Move the cursor
+    Move(Motion),
+    /// Apply an operator to the text the motion moves over
+    Operate(Operator, Motion),
+    /// Start inserting
+    Insert(InsertAt),
+    /// p and P, paste the last deleted or copied text after or before the
+    /// cursor
+    Paste {
+        /// Whether to paste after the cursor
+        after: bool,
+    },
+    /// ., repeat the last change
+    Repeat,
+    /// u, undo the last change
+    Undo,
+}
+
+impl ViCommand {
+    /// Whether the command changes the input, so . repeats it
+    pub fn is_change(&self) -> bool {
+        match self {
+            ViCommand::Move(_) | ViCommand::Repeat | ViCommand::Undo => false,
+            ViCommand::Operate(operator, _) => *operator != Operator::Yank,
+            ViCommand::Insert(_) | ViCommand::Paste { .. } => true,
+        }
+    }
+}
+
+/// The result of parsing the keys typed in normal mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Parsed {
+    /// A command and the count typed before it, if any
+    Command(Option<usize>, ViCommand),
+    /// The keys start a command
+    Pending,
+    /// The keys aren't a command
+    Invalid,
+}
+
+/// The largest count, bigger ones are clamped to it so p can't build a
+/// huge string
+pub const MAX_COUNT: usize = 9999;
+
+/// Read a count, which can't start with 0 since that's a motion
+fn count<I: Iterator<Item = char>>(keys: &mut Peekable<I>) -> Option<usize> {
+    let mut count: Option<usize> = None;
+    while let Some(digit) = keys.peek().and_then(|c| c.to_digit(10)) {
+        if count.is_none() && digit == 0 {
+            break;
+        }
+        count = Some(
+            count
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit as usize)
+                .min(MAX_COUNT),
+        );
+        keys.next();
+    }
+    count
+}
+
+/// Parse the motion starting with c
+fn motion<I: Iterator<Item = char>>(c: char, keys: &mut I) -> Result<Motion, Parsed> {
+    let motion = match c {
+        'h' => Motion::Left,
+        'l' | ' ' => Motion::Right,
+        'w' => Motion::WordForward,
+        'b' => Motion::WordBackward,
+        'e' => Motion::WordEnd,
+        '0' | '^' => Motion::LineStart,
+        '$' => Motion::LineEnd,
+        'f' | 't' | 'F' | 'T' => Motion::Find {
+            c: keys.next().ok_or(Parsed::Pending)?,
+            forward: c.is_lowercase(),
+            till: c.eq_ignore_ascii_case(&'t'),
+        },
+        _ => return Err(Parsed::Invalid),
+    };
+    Ok(motion)
+}
+
+/// Parse the keys typed in vi normal mode
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::editing::vi::{parse, Motion, Operator, Parsed, ViCommand};
+///
+/// assert_eq!(
+///     parse(&['2', 'd', '3', 'w']),
+///     Parsed::Command(Some(6), ViCommand::Operate(Operator::Delete, Motion::WordForward))
+/// );
+/// assert_eq!(parse(&['c', 'f']), Parsed::Pending);
+/// assert_eq!(parse(&['d', 'q']), Parsed::Invalid);
+/// ```
+pub fn parse(keys: &[char]) -> Parsed {
+    let mut keys = keys.iter().copied().peekable();
+    let mut times = count(&mut keys);
+    let Some(c) = keys.next() else {
+        return Parsed::Pending;
+    };
+    let command = match c {
+        'i' => ViCommand::Insert(InsertAt::Cursor),
+        'a' => ViCommand::Insert(InsertAt::After),
+        'I' => ViCommand::Insert(InsertAt::Start),
+        'A' => ViCommand::Insert(InsertAt::End),
+        'p' => ViCommand::Paste { after: true },
+        'P' => ViCommand::Paste { after: false },
+        '.' => ViCommand::Repeat,
+        'u' => ViCommand::Undo,
+        'x' => ViCommand::Operate(Operator::Delete, Motion::Right),
+        'X' => ViCommand::Operate(Operator::Delete, Motion::Left),
+        'D' => ViCommand::Operate(Operator::Delete, Motion::LineEnd),
+        'C' => ViCommand::Operate(Operator::Change, Motion::LineEnd),
+        's' => ViCommand::Operate(Operator::Change, Motion::Right),
+        'S' => ViCommand::Operate(Operator::Change, Motion::WholeLine),
+        'd' | 'c' | 'y' => {
+            let operator = match c {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+            // A count after the operator multiplies the one before it
+            if let Some(motion_times) = count(&mut keys) {
+                times = Some(
+                    times
+                        .unwrap_or(1)
+                        .saturating_mul(motion_times)
+                        .min(MAX_COUNT),
+                );
+            }
+            let motion = match keys.next() {
+                None => return Parsed::Pending,
+                // dd, cc and yy work on the whole input
+                Some(m) if m == c => Motion::WholeLine,
+                Some(m) => match motion(m, &mut keys) {
+                    Ok(motion) => motion,
+                    Err(parsed) => return parsed,
+                },
+            };
+            ViCommand::Operate(operator, motion)
+        }
+        _ => match motion(c, &mut keys) {
+            Ok(motion) => ViCommand::Move(motion),
+            Err(parsed) => return parsed,
+        },
+    };
+    if keys.next().is_some() {
+        return Parsed::Invalid;
+    }
+    Parsed::Command(times, command)
+}
+
+/// The last change, repeated by .
+#[derive(Clone, Debug, PartialEq)]
+pub struct LastChange {
+    /// The count typed before the command, if any
+    pub count: Option<usize>,
+    /// The command that made the change
+    pub command: ViCommand,
+    /// Text typed in insert mode after the command
+    pub inserted: String,
+}
+
+/// The vi editing state of a CommandBar
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViState {
+    /// The current sub-mode
+    pub mode: ViMode,
+    /// Keys typed in normal mode that don't make a command yet
+    pub keys: Vec<char>,
+    /// The register named with " before the command being run, if any
+    pub register: Option<char>,
+    /// The last change, repeated by .
+    pub last_change: Option<LastChange>,
+    /// Where the cursor was when insert mode started, so the typed text can
+    /// be recorded for .
+    pub insert_start: usize,
+    /// Whether insert mode was started by the last change, so the typed text
+    /// is part of it
+    pub recording: bool,
+}
+
+impl Default for ViState {
+    fn default() -> ViState {
+        ViState {
+            mode: ViMode::Insert,
+            keys: Vec::new(),
+            register: None,
+            last_change: None,
+            insert_start: 0,
+            recording: false,
+        }
+    }
+}
+
+impl ViState {
+    /// Add a key typed in normal mode, returning the command it completes
+    /// Keys that can't make a command are dropped.
+    /// A command can start with " and a register name, like "ayw, which
+    /// sets register.
+    pub fn push(&mut self, c: char) -> Option<(Option<usize>, ViCommand)> {
+        self.keys.push(c);
+        let (register, keys) = match self.keys.as_slice() {
+            ['"'] => return None,
+            ['"', name, keys @ ..] if name.is_ascii_alphabetic() => (Some(*name), keys),
+            ['"', ..] => {
+                self.keys.clear();
+                return None;
+            }
+            keys => (None, keys),
+        };
+        match parse(keys) {
+            Parsed::Command(count, command) => {
+                self.register = register;
+                self.keys.clear();
+                Some((count, command))
+            }
+            Parsed::Pending => None,
+            Parsed::Invalid => {
+                self.keys.clear();
+                None
+            }
+        }
+    }
+
+    /// Change the sub-mode, with the cursor at cursor
+    pub fn set_mode(&mut self, mode: ViMode, cursor: usize) {
+        self.mode = mode;
+        self.keys.clear();
+        self.insert_start = cursor;
+        self.recording = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editing::vi::{
+        parse, InsertAt, Motion, Operator, Parsed, ViCommand, ViState, MAX_COUNT,
+    };
+
+    fn parse_str(keys: &str) -> Parsed {
+        parse(&keys.chars().collect::<Vec<char>>())
+    }
+
+    #[test]
+    fn vi_parses_motions_and_counts() {
+        assert_eq!(
+            parse_str("0"),
+            Parsed::Command(None, ViCommand::Move(Motion::LineStart))
+        );
+        assert_eq!(
+            parse_str("10l"),
+            Parsed::Command(Some(10), ViCommand::Move(Motion::Right))
+        );
+        assert_eq!(
+            parse_str("3T,"),
+            Parsed::Command(
+                Some(3),
+                ViCommand::Move(Motion::Find {
+                    c: ',',
+                    forward: false,
+                    till: true
+                })
+            )
+        );
+        assert_eq!(parse_str("2"), Parsed::Pending);
+        assert_eq!(parse_str("f"), Parsed::Pending);
+        assert_eq!(parse_str("z"), Parsed::Invalid);
+    }
+
+    #[test]
+    fn vi_parses_operators() {
+        assert_eq!(
+            parse_str("cc"),
+            Parsed::Command(
+                None,
+                ViCommand::Operate(Operator::Change, Motion::WholeLine)
+            )
+        );
+        assert_eq!(
+            parse_str("y$"),
+            Parsed::Command(None, ViCommand::Operate(Operator::Yank, Motion::LineEnd))
+        );
+        assert_eq!(
+            parse_str("3x"),
+            Parsed::Command(Some(3), ViCommand::Operate(Operator::Delete, Motion::Right))
+        );
+        assert_eq!(parse_str("d2"), Parsed::Pending);
+        assert_eq!(parse_str("dy"), Parsed::Invalid);
+        assert_eq!(parse_str("2d3d"), parse_str("6dd"));
+        assert_eq!(
+            parse_str("99999999999999999999p"),
+            Parsed::Command(Some(MAX_COUNT), ViCommand::Paste { after: true })
+        );
+        assert_eq!(parse_str("999d99d"), parse_str("9999dd"));
+    }
+
+    #[test]
+    fn vi_state_collects_keys() {
+        let mut vi = ViState::default();
+        assert_eq!(vi.push('d'), None);
+        assert_eq!(vi.push('z'), None);
+        assert!(vi.keys.is_empty());
+        assert_eq!(vi.push('A'), Some((None, ViCommand::Insert(InsertAt::End))));
+        assert_eq!(vi.register, None);
+
+        for c in ['"', 'b', 'y'] {
+            assert_eq!(vi.push(c), None);
+        }
+        assert_eq!(
+            vi.push('y'),
+            Some((None, ViCommand::Operate(Operator::Yank, Motion::WholeLine)))
+        );
+        assert_eq!(vi.register, Some('b'));
+        assert_eq!(vi.push('"'), None);
+        assert_eq!(vi.push('1'), None);
+        assert!(vi.keys.is_empty());
+    }
+}
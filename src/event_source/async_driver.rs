@@ -0,0 +1,408 @@
+///
+/// Drive a CommandBar from an asynchronous stream of events
+///
+/// This is enabled by the async feature.
+/// The driver works with any executor, including tokio.
+///
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crossterm::event::{Event, EventStream};
+use futures::future::{self, Either};
+use futures::stream::{self, Stream, StreamExt};
+use futures_timer::Delay;
+use log::error;
+
+use crate::widgets::command_bar::{CommandBar, EventHandlerResult};
+use crate::widgets::popup::Popup;
+
+/// What happened when the AsyncDriver fed an event to a CommandBar or Popup
+#[derive(Clone, Debug, PartialEq)]
+pub enum DriverEvent {
+    /// The CommandBar handled the event and should be redrawn
+    Handled,
+    /// The CommandBar submitted a command
+    Submitted(String),
+    /// The CommandBar didn't handle the event, so the application can
+    Unhandled(Event),
+}
+
+/// A widget the AsyncDriver can feed events to
+///
+/// This is implemented for CommandBar, and for Popup so the popup is shown
+/// and hidden as its CommandBar is edited.
+pub trait DrivenWidget: AsMut<CommandBar> {
+    /// Time left to press the next key of a pending key sequence
+    fn key_timeout(&self) -> Option<Duration>;
+
+    /// Finish a pending key sequence if its timeout has run out
+    /// Returns whether the widget changed and should be redrawn
+    fn handle_timeout(&mut self) -> bool;
+
+    /// Handle an event read from the stream
+    fn handle_key_event(&mut self, event: Event) -> EventHandlerResult;
+}
+
+impl DrivenWidget for CommandBar {
+    fn key_timeout(&self) -> Option<Duration> {
+        CommandBar::key_timeout(self)
+    }
+
+    fn handle_timeout(&mut self) -> bool {
+        CommandBar::handle_timeout(self)
+    }
+
+    fn handle_key_event(&mut self, event: Event) -> EventHandlerResult {
+        CommandBar::handle_key_event(self, event)
+    }
+}
+
+impl DrivenWidget for Popup {
+    fn key_timeout(&self) -> Option<Duration> {
+        self.command_bar.key_timeout()
+    }
+
+    fn handle_timeout(&mut self) -> bool {
+        Popup::handle_timeout(self)
+    }
+
+    fn handle_key_event(&mut self, event: Event) -> EventHandlerResult {
+        Popup::handle_key_event(self, event)
+    }
+}
+
+/// What the driver got while waiting for an event
+enum Wait {
+    /// The next event from the stream
+    Event(io::Result<Event>),
+    /// The key sequence timeout ran out first
+    TimedOut,
+}
+
+/// AsyncDriver reads events from a Stream and feeds them to a CommandBar
+/// or a Popup
+///
+/// While a key sequence is pending the driver only waits
+/// CommandBar::key_timeout for the next event, then finishes the sequence
+/// with CommandBar::handle_timeout.
+///
+/// # Example
+///
+/// ```
+/// use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+/// use futures::{executor::block_on, stream};
+/// use tui_command_bar_widget::event_source::async_driver::{AsyncDriver, DriverEvent};
+/// use tui_command_bar_widget::widgets::command_bar::{CommandBar, InputMode};
+///
+/// let key = |code| Ok(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+/// let events = stream::iter(vec![key(KeyCode::Char('q')), key(KeyCode::Enter)]);
+/// let mut driver = AsyncDriver::new(events);
+/// let mut command_bar_widget = CommandBar {
+///     input_mode: InputMode::Editing,
+///     ..Default::default()
+/// };
+///
+/// block_on(async {
+///     // An application would redraw after each event
+///     while let Some(event) = driver.next(&mut command_bar_widget).await {
+///         if let DriverEvent::Submitted(command) = event.unwrap() {
+///             assert_eq!(command, "q");
+///         }
+///     }
+/// });
+/// ```
+pub struct AsyncDriver<S> {
+    /// The stream events are read from
+    pub events: S,
+}
+
+impl AsyncDriver<EventStream> {
+    /// Build a driver that reads from crossterm's EventStream
+    pub fn crossterm() -> Self {
+        AsyncDriver::new(EventStream::new())
+    }
+}
+
+impl<S> AsyncDriver<S>
+where
+    S: Stream<Item = io::Result<Event>> + Unpin,
+{
+    /// Build a driver that reads from events
+    pub fn new(events: S) -> Self {
+        AsyncDriver { events }
+    }
+
+    /// Wait for the next event, for at most timeout if there is one
+    /// Returns None when the event stream ends
+    async fn wait(&mut self, timeout: Option<Duration>) -> Option<Wait> {
+        let Some(timeout) = timeout else {
+            return self.events.next().await.map(Wait::Event);
+        };
+        match future::select(self.events.next(), Delay::new(timeout)).await {
+            Either::Left((event, _)) => event.map(Wait::Event),
+            Either::Right(_) => Some(Wait::TimedOut),
+        }
+    }
+
+    /// Wait for the next event and feed it to widget
+    /// A pending key sequence that times out is reported as Handled.
+    /// Returns None when the event stream ends
+    pub async fn next<W: DrivenWidget>(
+        &mut self,
+        widget: &mut W,
+    ) -> Option<io::Result<DriverEvent>> {
+        loop {
+            match self.wait(widget.key_timeout()).await? {
+                Wait::Event(event) => return Some(event.map(|event| feed(widget, event))),
+                Wait::TimedOut => {
+                    if widget.handle_timeout() {
+                        return Some(Ok(DriverEvent::Handled));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Turn the driver into a stream of the commands submitted in widget
+    ///
+    /// This takes the place of the tx_channel.
+    /// Events the widget doesn't handle are passed to unhandled, so the
+    /// application can still react to keys like q.
+    /// The widget is only borrowed while an event is handled, so the
+    /// application can render it between commands, and unhandled can use it.
+    /// The stream ends when the event stream ends or fails.
+    pub fn commands<W, F>(self, widget: Rc<RefCell<W>>, unhandled: F) -> impl Stream<Item = String>
+    where
+        W: DrivenWidget,
+        F: FnMut(Event),
+    {
+        stream::unfold((self, unhandled), move |(mut driver, mut unhandled)| {
+            let widget = Rc::clone(&widget);
+            async move {
+                loop {
+                    let timeout = widget.borrow().key_timeout();
+                    let event = match driver.wait(timeout).await? {
+                        Wait::Event(Ok(event)) => event,
+                        Wait::Event(Err(e)) => {
+                            error!("Event read error: {}", e);
+                            return None;
+                        }
+                        Wait::TimedOut => {
+                            widget.borrow_mut().handle_timeout();
+                            continue;
+                        }
+                    };
+                    let result = feed(&mut *widget.borrow_mut(), event);
+                    match result {
+                        DriverEvent::Submitted(command) => {
+                            return Some((command, (driver, unhandled)))
+                        }
+                        DriverEvent::Unhandled(event) => unhandled(event),
+                        DriverEvent::Handled => (),
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Handle an event in widget and report what happened
+fn feed<W: DrivenWidget>(widget: &mut W, event: Event) -> DriverEvent {
+    // Catch submitted commands on a channel of our own, then pass them on
+    // to the application's channel
+    let (tx, rx) = mpsc::channel();
+    let app_tx = widget.as_mut().tx_channel.replace(tx);
+    let result = widget.handle_key_event(event);
+    let command_bar = widget.as_mut();
+    command_bar.tx_channel = app_tx;
+    if let Ok(command) = rx.try_recv() {
+        if let Some(tx) = &command_bar.tx_channel {
+            if let Err(e) = tx.send(command.clone()) {
+                error!("Error sending command: {}", e);
+            }
+        }
+        return DriverEvent::Submitted(command);
+    }
+
+    match result {
+        EventHandlerResult::Unhandled(event) => DriverEvent::Unhandled(event),
+        EventHandlerResult::Ok | EventHandlerResult::Err => DriverEvent::Handled,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+    use futures::{executor::block_on, stream, StreamExt};
+    use futures_timer::Delay;
+
+    use crate::event_source::async_driver::{AsyncDriver, DriverEvent};
+    use crate::key_hook::key_hook::{HandlerOutcome, KeyHook};
+    use crate::key_hook::key_sequence::KeySequence;
+    use crate::widgets::command_bar::CommandBar;
+    use crate::widgets::popup::Popup;
+
+    fn key(c: char) -> io::Result<Event> {
+        Ok(Event::Key(KeyEvent::new(
+            KeyCode::Char(c),
+            KeyModifiers::NONE,
+        )))
+    }
+
+    fn enter() -> io::Result<Event> {
+        Ok(Event::Key(KeyEvent::new(
+            KeyCode::Enter,
+            KeyModifiers::NONE,
+        )))
+    }
+
+    #[test]
+    fn async_driver_reports_each_event() {
+        let mut driver =
+            AsyncDriver::new(stream::iter(vec![key('x'), key(':'), key('w'), enter()]));
+        let (tx, rx) = mpsc::channel();
+        let mut command_bar_widget = CommandBar::default_with_tx_channel(tx);
+        command_bar_widget.register_key(':', CommandBar::command_key_handler);
+
+        let events: Vec<DriverEvent> = block_on(async {
+            let mut events = Vec::new();
+            while let Some(event) = driver.next(&mut command_bar_widget).await {
+                events.push(event.unwrap());
+            }
+            events
+        });
+        assert_eq!(
+            events,
+            vec![
+                DriverEvent::Unhandled(key('x').unwrap()),
+                DriverEvent::Handled,
+                DriverEvent::Handled,
+                DriverEvent::Submitted(String::from("w")),
+            ]
+        );
+        // The application's channel still gets the command
+        assert_eq!(rx.try_recv(), Ok(String::from("w")));
+    }
+
+    #[test]
+    fn async_driver_streams_commands() {
+        let events = vec![
+            key(':'),
+            key('a'),
+            enter(),
+            key('x'),
+            key(':'),
+            key('b'),
+            enter(),
+            Err(io::Error::other("closed")),
+            key(':'),
+        ];
+        let command_bar_widget = Rc::new(RefCell::new(CommandBar::default()));
+        command_bar_widget
+            .borrow_mut()
+            .register_key(':', CommandBar::command_key_handler);
+
+        let unhandled = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&unhandled);
+        let mut commands = Box::pin(
+            AsyncDriver::new(stream::iter(events))
+                .commands(Rc::clone(&command_bar_widget), move |event| {
+                    seen.borrow_mut().push(event)
+                }),
+        );
+        block_on(async {
+            assert_eq!(commands.next().await, Some(String::from("a")));
+            // The application still has the CommandBar between commands
+            assert_eq!(command_bar_widget.borrow().messages, vec!["a"]);
+            assert_eq!(commands.next().await, Some(String::from("b")));
+            assert_eq!(*unhandled.borrow(), vec![key('x').unwrap()]);
+            assert_eq!(commands.next().await, None);
+        });
+    }
+
+    #[test]
+    fn async_driver_times_out_key_sequences() {
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        let mut command_bar_widget = CommandBar::default();
+        for sequence in ["g", "g g"] {
+            let ran = Rc::clone(&ran);
+            command_bar_widget.register_key(
+                sequence.parse::<KeySequence>().unwrap(),
+                move |_: &mut CommandBar, _| {
+                    ran.borrow_mut().push(sequence);
+                    HandlerOutcome::Handled
+                },
+            );
+        }
+        command_bar_widget.key_database.timeout = Some(Duration::from_millis(20));
+
+        // No event follows g, so it runs once the timeout is over
+        let events = stream::iter(vec![key('g')]).chain(stream::pending());
+        let mut driver = AsyncDriver::new(events);
+        block_on(async {
+            let event = driver.next(&mut command_bar_widget).await;
+            assert_eq!(event.unwrap().unwrap(), DriverEvent::Handled);
+            assert!(ran.borrow().is_empty());
+            let event = driver.next(&mut command_bar_widget).await;
+            assert_eq!(event.unwrap().unwrap(), DriverEvent::Handled);
+        });
+        assert_eq!(*ran.borrow(), vec!["g"]);
+    }
+
+    #[test]
+    fn async_driver_shows_and_hides_a_popup() {
+        let mut popup = Popup::default();
+        popup
+            .command_bar
+            .set_command_key("g g".parse::<KeySequence>().unwrap());
+        popup.register_key('g', CommandBar::command_key_handler);
+        popup.command_bar.key_database.timeout = Some(Duration::from_millis(20));
+
+        // g opens the popup once the timeout is over
+        let events = stream::iter(vec![key('g')])
+            .chain(stream::pending().take_until(Delay::new(Duration::from_millis(100))))
+            .chain(stream::iter(vec![key('w'), enter()]));
+        let mut driver = AsyncDriver::new(events);
+        block_on(async {
+            let event = driver.next(&mut popup).await;
+            assert_eq!(event.unwrap().unwrap(), DriverEvent::Handled);
+            assert!(!popup.show_popup);
+            let event = driver.next(&mut popup).await;
+            assert_eq!(event.unwrap().unwrap(), DriverEvent::Handled);
+            assert!(popup.show_popup);
+            let event = driver.next(&mut popup).await;
+            assert_eq!(event.unwrap().unwrap(), DriverEvent::Handled);
+            assert!(popup.show_popup);
+            let event = driver.next(&mut popup).await;
+            assert_eq!(
+                event.unwrap().unwrap(),
+                DriverEvent::Submitted(String::from("w"))
+            );
+        });
+        assert!(!popup.show_popup);
+    }
+
+    #[test]
+    fn async_driver_streams_popup_commands() {
+        let popup = Rc::new(RefCell::new(Popup::default()));
+        popup.borrow_mut().command_bar.set_command_key(':');
+
+        let events = stream::iter(vec![key(':'), key('a'), enter()]);
+        let seen = Rc::clone(&popup);
+        let mut commands = Box::pin(AsyncDriver::new(events).commands(Rc::clone(&popup), |_| ()));
+        block_on(async {
+            assert_eq!(commands.next().await, Some(String::from("a")));
+            assert!(!seen.borrow().show_popup);
+            assert_eq!(commands.next().await, None);
+        });
+    }
+}
@@ -0,0 +1,24 @@
+///
+/// Read events from the terminal with crossterm
+///
+use std::io;
+use std::time::Duration;
+
+use crossterm::event::{self, Event};
+
+use crate::event_source::event_source::EventSource;
+
+/// CrosstermEventSource reads events with crossterm::event::read
+/// This is the source CommandBar::handle_event uses.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CrosstermEventSource;
+
+impl EventSource for CrosstermEventSource {
+    fn read(&mut self) -> io::Result<Event> {
+        event::read()
+    }
+
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        event::poll(timeout)
+    }
+}
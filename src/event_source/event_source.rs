@@ -0,0 +1,43 @@
+///
+/// EventSource is a trait for reading terminal events
+///
+use std::io;
+use std::time::Duration;
+
+use crossterm::event::Event;
+
+/// An EventSource produces the events the CommandBar handles
+///
+/// Events use the crossterm event types, sources for other terminal
+/// libraries convert their events to them.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+/// use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+/// use tui_command_bar_widget::event_source::event_source::EventSource;
+///
+/// /// A source that always presses Esc
+/// struct Escape;
+///
+/// impl EventSource for Escape {
+///     fn read(&mut self) -> io::Result<Event> {
+///         Ok(Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)))
+///     }
+/// }
+///
+/// let event = Escape.read().unwrap();
+/// assert_eq!(event, Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+/// ```
+pub trait EventSource {
+    /// Read the next event, blocking until one is available
+    fn read(&mut self) -> io::Result<Event>;
+
+    /// Wait up to timeout for an event to be ready to read
+    /// Returns false if the timeout ran out first.
+    /// Sources that can't wait return true, so the next read blocks.
+    fn poll(&mut self, _timeout: Duration) -> io::Result<bool> {
+        Ok(true)
+    }
+}
@@ -0,0 +1,20 @@
+///
+/// event_source is a module for reading terminal events
+/// The CommandBar reads events from an EventSource, so it can be driven by
+/// crossterm, termion or a scripted list of events in tests.
+///
+#[allow(clippy::module_inception)]
+pub mod event_source;
+
+/// Events read with crossterm
+pub mod crossterm_source;
+
+/// Events read with termion, converted to crossterm events
+pub mod termion_source;
+
+/// Events from an in-memory queue, for tests and demos
+pub mod scripted;
+
+/// Feed a CommandBar from an asynchronous stream of events
+#[cfg(feature = "async")]
+pub mod async_driver;
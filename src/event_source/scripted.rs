@@ -0,0 +1,123 @@
+///
+/// A scripted queue of events
+///
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::time::Duration;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+use crate::event_source::event_source::EventSource;
+
+/// ScriptedEventSource returns queued events in order
+///
+/// Reading after the last event returns an UnexpectedEof error, and polling
+/// after the last event times out straight away.
+///
+/// # Example
+///
+/// ```
+/// use crossterm::event::KeyCode;
+/// use tui_command_bar_widget::event_source::scripted::ScriptedEventSource;
+/// use tui_command_bar_widget::widgets::command_bar::{CommandBar, EventHandlerResult, InputMode};
+///
+/// let mut command_bar_widget = CommandBar {
+///     input_mode: InputMode::Editing,
+///     ..Default::default()
+/// };
+/// let mut events = ScriptedEventSource::default();
+/// events.push_str("hi");
+/// events.push_keys(&[KeyCode::Left, KeyCode::Char('!')]);
+///
+/// while !events.is_empty() {
+///     assert_eq!(command_bar_widget.handle_event_from(&mut events), EventHandlerResult::Ok);
+/// }
+/// assert_eq!(command_bar_widget.input, "h!i");
+/// ```
+#[derive(Debug, Default)]
+pub struct ScriptedEventSource {
+    /// The events and read errors still to be returned
+    pub events: VecDeque<io::Result<Event>>,
+}
+
+impl ScriptedEventSource {
+    /// Build a source that returns events in order
+    pub fn new<I: IntoIterator<Item = Event>>(events: I) -> Self {
+        ScriptedEventSource {
+            events: events.into_iter().map(Ok).collect(),
+        }
+    }
+
+    /// Queue an event
+    pub fn push(&mut self, event: Event) {
+        self.events.push_back(Ok(event));
+    }
+
+    /// Queue a read error
+    pub fn push_error(&mut self, error: io::Error) {
+        self.events.push_back(Err(error));
+    }
+
+    /// Queue a key press for each code, without modifiers
+    pub fn push_keys(&mut self, codes: &[KeyCode]) {
+        for code in codes {
+            self.push(Event::Key(KeyEvent::new(*code, KeyModifiers::NONE)));
+        }
+    }
+
+    /// Queue a key press for each character of text
+    pub fn push_str(&mut self, text: &str) {
+        for c in text.chars() {
+            self.push(Event::Key(KeyEvent::new(
+                KeyCode::Char(c),
+                KeyModifiers::NONE,
+            )));
+        }
+    }
+
+    /// Whether all the events have been read
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl EventSource for ScriptedEventSource {
+    fn read(&mut self) -> io::Result<Event> {
+        self.events.pop_front().unwrap_or_else(|| {
+            Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "no more scripted events",
+            ))
+        })
+    }
+
+    fn poll(&mut self, _timeout: Duration) -> io::Result<bool> {
+        Ok(!self.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, ErrorKind};
+
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::event_source::event_source::EventSource;
+    use crate::event_source::scripted::ScriptedEventSource;
+
+    #[test]
+    fn scripted_events_are_read_in_order() {
+        let mut source = ScriptedEventSource::new([Event::Resize(10, 5)]);
+        source.push_keys(&[KeyCode::Enter]);
+        source.push_error(io::Error::other("broken"));
+
+        assert_eq!(source.read().unwrap(), Event::Resize(10, 5));
+        assert_eq!(
+            source.read().unwrap(),
+            Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+        );
+        assert_eq!(source.read().unwrap_err().to_string(), "broken");
+        assert!(source.is_empty());
+        assert_eq!(source.read().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+}
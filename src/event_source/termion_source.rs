@@ -0,0 +1,424 @@
+///
+/// Read events from the terminal with termion
+///
+use std::io::{self, ErrorKind, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use log::debug;
+use termion::event as termion_event;
+use termion::input::TermReadEventsAndRaw;
+
+use crate::event_source::event_source::EventSource;
+
+/// TermionEventSource reads termion events and converts them to crossterm
+/// events
+///
+/// termion can only block on a read, so events are read on a thread of
+/// their own and poll waits for the next one with a timeout.
+/// Clones of a source share the thread and its events, so an event is
+/// read by one of them and isn't lost when another is dropped.
+/// The thread ends when the input ends, or once it reads an event after
+/// every clone is dropped.
+///
+/// With bracketed paste enabled, the text between the paste markers is
+/// read as one Paste event, like crossterm does.
+///
+/// Termion doesn't report resize events, applications should check the
+/// terminal size when they draw.
+#[derive(Clone)]
+pub struct TermionEventSource {
+    /// The events, shared with the clones of the source
+    input: Arc<Mutex<Input>>,
+}
+
+/// The events read by a reader thread
+struct Input {
+    /// Events converted by the reader thread
+    events: Receiver<io::Result<Event>>,
+    /// An event poll received that hasn't been read yet
+    pending: Option<io::Result<Event>>,
+}
+
+/// The source reading standard input, which the sources returned by stdin
+/// are clones of
+static STDIN: OnceLock<TermionEventSource> = OnceLock::new();
+
+impl TermionEventSource {
+    /// Read events from standard input
+    /// The terminal should be in raw mode.
+    ///
+    /// There's one thread reading standard input, shared by every source
+    /// stdin returns, so sources don't race each other for the bytes of an
+    /// event, and a dropped source doesn't take the next key with it.
+    /// The thread keeps reading until the process exits, so an application
+    /// that reads standard input some other way shouldn't call stdin.
+    pub fn stdin() -> Self {
+        STDIN
+            .get_or_init(|| TermionEventSource::new(io::stdin()))
+            .clone()
+    }
+
+    /// Read events from reader
+    pub fn new<R: Read + Send + 'static>(reader: R) -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut converter = EventConverter::default();
+            for event in reader.events_and_raw() {
+                let event = match event {
+                    Ok((event, raw)) => match converter.convert(&event, &raw) {
+                        Some(event) => Ok(event),
+                        None => continue,
+                    },
+                    Err(e) => Err(e),
+                };
+                // The source was dropped
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+        TermionEventSource {
+            input: Arc::new(Mutex::new(Input {
+                events: rx,
+                pending: None,
+            })),
+        }
+    }
+
+    /// Lock the shared input
+    /// A clone that panicked while holding the lock can't have left the
+    /// input half changed, so a poisoned lock is still used.
+    fn lock(&self) -> MutexGuard<'_, Input> {
+        self.input.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The sequences a terminal with bracketed paste enabled sends before and
+/// after pasted text
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// EventConverter converts the events read by the reader thread, joining
+/// the keys between the paste markers into one Paste event
+#[derive(Default)]
+struct EventConverter {
+    /// The bytes pasted so far, while reading a paste
+    paste: Option<Vec<u8>>,
+}
+
+impl EventConverter {
+    /// Convert an event and the bytes it was read from
+    /// Returns None for unsupported events and for pasted keys
+    fn convert(&mut self, event: &termion_event::Event, raw: &[u8]) -> Option<Event> {
+        if let termion_event::Event::Unsupported(bytes) = event {
+            if bytes == PASTE_START {
+                self.paste = Some(Vec::new());
+                return None;
+            }
+            if bytes == PASTE_END {
+                let text = self.paste.take()?;
+                return Some(Event::Paste(String::from_utf8_lossy(&text).into_owned()));
+            }
+        }
+        // The raw bytes keep the pasted text as it was, with its line breaks
+        if let Some(text) = &mut self.paste {
+            text.extend_from_slice(raw);
+            return None;
+        }
+        let converted = convert_event(event);
+        if converted.is_none() {
+            debug!("Ignoring unsupported termion event: {:?}", event);
+        }
+        converted
+    }
+}
+
+/// The error read and poll return once the reader thread has stopped
+fn end_of_input() -> io::Error {
+    io::Error::new(ErrorKind::UnexpectedEof, "end of input")
+}
+
+impl EventSource for TermionEventSource {
+    fn read(&mut self) -> io::Result<Event> {
+        let mut input = self.lock();
+        match input.pending.take() {
+            Some(event) => event,
+            None => input.events.recv().unwrap_or_else(|_| Err(end_of_input())),
+        }
+    }
+
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        let mut input = self.lock();
+        if input.pending.is_some() {
+            return Ok(true);
+        }
+        match input.events.recv_timeout(timeout) {
+            Ok(event) => {
+                input.pending = Some(event);
+                Ok(true)
+            }
+            Err(RecvTimeoutError::Timeout) => Ok(false),
+            // Let read report the end of the input
+            Err(RecvTimeoutError::Disconnected) => Ok(true),
+        }
+    }
+}
+
+/// Convert a termion key to a crossterm key event
+fn convert_key(key: &termion_event::Key) -> Option<KeyEvent> {
+    use termion_event::Key;
+
+    let (code, modifiers) = match *key {
+        Key::Backspace => (KeyCode::Backspace, KeyModifiers::NONE),
+        Key::Left => (KeyCode::Left, KeyModifiers::NONE),
+        Key::Right => (KeyCode::Right, KeyModifiers::NONE),
+        Key::Up => (KeyCode::Up, KeyModifiers::NONE),
+        Key::Down => (KeyCode::Down, KeyModifiers::NONE),
+        Key::Home => (KeyCode::Home, KeyModifiers::NONE),
+        Key::End => (KeyCode::End, KeyModifiers::NONE),
+        Key::PageUp => (KeyCode::PageUp, KeyModifiers::NONE),
+        Key::PageDown => (KeyCode::PageDown, KeyModifiers::NONE),
+        // crossterm reports Shift-Tab as BackTab with the shift modifier
+        Key::BackTab => (KeyCode::BackTab, KeyModifiers::SHIFT),
+        Key::Delete => (KeyCode::Delete, KeyModifiers::NONE),
+        Key::Insert => (KeyCode::Insert, KeyModifiers::NONE),
+        Key::F(n) => (KeyCode::F(n), KeyModifiers::NONE),
+        Key::Char('\n') | Key::Char('\r') => (KeyCode::Enter, KeyModifiers::NONE),
+        Key::Char('\t') => (KeyCode::Tab, KeyModifiers::NONE),
+        // crossterm reports uppercase letters with the shift modifier
+        Key::Char(c) if c.is_uppercase() => (KeyCode::Char(c), KeyModifiers::SHIFT),
+        Key::Char(c) => (KeyCode::Char(c), KeyModifiers::NONE),
+        Key::Alt(c) => (KeyCode::Char(c), KeyModifiers::ALT),
+        Key::Ctrl(c) => (KeyCode::Char(c), KeyModifiers::CONTROL),
+        Key::Null => (KeyCode::Null, KeyModifiers::NONE),
+        Key::Esc => (KeyCode::Esc, KeyModifiers::NONE),
+        _ => return None,
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+/// Convert a termion mouse event to a crossterm mouse event
+/// termion coordinates start at 1, crossterm coordinates start at 0.
+fn convert_mouse(mouse: &termion_event::MouseEvent) -> MouseEvent {
+    use termion_event::MouseEvent as TermionMouse;
+
+    let (kind, x, y) = match *mouse {
+        TermionMouse::Press(button, x, y) => {
+            let kind = match button {
+                termion_event::MouseButton::Left => MouseEventKind::Down(MouseButton::Left),
+                termion_event::MouseButton::Right => MouseEventKind::Down(MouseButton::Right),
+                termion_event::MouseButton::Middle => MouseEventKind::Down(MouseButton::Middle),
+                termion_event::MouseButton::WheelUp => MouseEventKind::ScrollUp,
+                termion_event::MouseButton::WheelDown => MouseEventKind::ScrollDown,
+            };
+            (kind, x, y)
+        }
+        // termion doesn't say which button was released or held
+        TermionMouse::Release(x, y) => (MouseEventKind::Up(MouseButton::Left), x, y),
+        TermionMouse::Hold(x, y) => (MouseEventKind::Drag(MouseButton::Left), x, y),
+    };
+    MouseEvent {
+        kind,
+        column: x.saturating_sub(1),
+        row: y.saturating_sub(1),
+        modifiers: KeyModifiers::NONE,
+    }
+}
+
+/// Convert a termion event to a crossterm event
+/// Returns None for events crossterm has no equivalent for
+pub fn convert_event(event: &termion_event::Event) -> Option<Event> {
+    match event {
+        termion_event::Event::Key(key) => convert_key(key).map(Event::Key),
+        termion_event::Event::Mouse(mouse) => Some(Event::Mouse(convert_mouse(mouse))),
+        termion_event::Event::Unsupported(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{self, Read};
+    use std::rc::Rc;
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::time::Duration;
+
+    use crossterm::event::{
+        Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    };
+
+    use crate::event_source::event_source::EventSource;
+    use crate::event_source::termion_source::TermionEventSource;
+    use crate::key_hook::key_hook::{HandlerOutcome, KeyHook};
+    use crate::key_hook::key_sequence::KeySequence;
+    use crate::widgets::command_bar::{CommandBar, EventHandlerResult, InputMode};
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    /// Input that blocks until bytes are sent, like a terminal
+    struct ChannelReader(Receiver<u8>);
+
+    impl Read for ChannelReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.recv() {
+                Ok(byte) if !buf.is_empty() => {
+                    buf[0] = byte;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn channel_source() -> (Sender<u8>, TermionEventSource) {
+        let (tx, rx) = mpsc::channel();
+        (tx, TermionEventSource::new(ChannelReader(rx)))
+    }
+
+    #[test]
+    fn termion_source_converts_keys() {
+        // a, Enter, Ctrl-R, Alt-x, Up then Esc
+        let input: &[u8] = b"a\r\x12\x1bx\x1b[A\x1b";
+        let mut source = TermionEventSource::new(input);
+
+        assert_eq!(
+            source.read().unwrap(),
+            key(KeyCode::Char('a'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            source.read().unwrap(),
+            key(KeyCode::Enter, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            source.read().unwrap(),
+            key(KeyCode::Char('r'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            source.read().unwrap(),
+            key(KeyCode::Char('x'), KeyModifiers::ALT)
+        );
+        assert_eq!(source.read().unwrap(), key(KeyCode::Up, KeyModifiers::NONE));
+        assert_eq!(
+            source.read().unwrap(),
+            key(KeyCode::Esc, KeyModifiers::NONE)
+        );
+        assert!(source.read().is_err());
+    }
+
+    #[test]
+    fn termion_source_polls_with_timeout() {
+        let (tx, mut source) = channel_source();
+        assert!(!source.poll(Duration::from_millis(10)).unwrap());
+
+        tx.send(b'g').unwrap();
+        assert!(source.poll(Duration::from_secs(5)).unwrap());
+        // The polled event is kept for read
+        assert!(source.poll(Duration::ZERO).unwrap());
+        assert_eq!(
+            source.read().unwrap(),
+            key(KeyCode::Char('g'), KeyModifiers::NONE)
+        );
+
+        drop(tx);
+        assert!(source.poll(Duration::from_secs(5)).unwrap());
+        assert!(source.read().is_err());
+    }
+
+    #[test]
+    fn termion_source_clones_share_events() {
+        let (tx, mut source) = channel_source();
+        let mut clone = source.clone();
+
+        // The event polled by the dropped source is read by its clone
+        tx.send(b'a').unwrap();
+        assert!(source.poll(Duration::from_secs(5)).unwrap());
+        drop(source);
+        assert_eq!(
+            clone.read().unwrap(),
+            key(KeyCode::Char('a'), KeyModifiers::NONE)
+        );
+        tx.send(b'b').unwrap();
+        assert_eq!(
+            clone.read().unwrap(),
+            key(KeyCode::Char('b'), KeyModifiers::NONE)
+        );
+    }
+
+    #[test]
+    fn termion_source_times_out_key_sequences() {
+        let mut command_bar_widget = CommandBar::default();
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        for sequence in ["g", "g g"] {
+            let ran = Rc::clone(&ran);
+            command_bar_widget.register_key(
+                sequence.parse::<KeySequence>().unwrap(),
+                move |_: &mut CommandBar, _| {
+                    ran.borrow_mut().push(sequence);
+                    HandlerOutcome::Handled
+                },
+            );
+        }
+        command_bar_widget.key_database.timeout = Some(Duration::from_millis(20));
+
+        // g waits for the next key, and runs when none comes in time
+        let (tx, mut source) = channel_source();
+        tx.send(b'g').unwrap();
+        command_bar_widget.handle_event_from(&mut source);
+        assert!(ran.borrow().is_empty());
+        assert_eq!(
+            command_bar_widget.handle_event_from(&mut source),
+            EventHandlerResult::Ok
+        );
+        assert_eq!(*ran.borrow(), vec!["g"]);
+    }
+
+    #[test]
+    fn termion_source_reads_bracketed_paste() {
+        let input: &[u8] = b"\x1b[200~open \xc3\xa9t\xc3\xa9\r\n\x1b[Cfile\x1b[201~x";
+        let mut source = TermionEventSource::new(input);
+
+        // The pasted text is one event, and the escape sequence in it is
+        // left out when the CommandBar pastes it
+        let mut command_bar_widget = CommandBar {
+            input_mode: InputMode::Editing,
+            ..Default::default()
+        };
+        let event = source.read().unwrap();
+        assert!(matches!(event, Event::Paste(_)));
+        assert_eq!(
+            command_bar_widget.handle_key_event(event),
+            EventHandlerResult::Ok
+        );
+        assert_eq!(command_bar_widget.input, "open \u{e9}t\u{e9} file");
+        assert_eq!(
+            source.read().unwrap(),
+            key(KeyCode::Char('x'), KeyModifiers::NONE)
+        );
+        assert!(source.read().is_err());
+    }
+
+    #[test]
+    fn termion_source_converts_mouse() {
+        // Left button press at column 3, row 2 in X10 mouse encoding
+        let input: &[u8] = b"\x1b[M #\"";
+        let mut source = TermionEventSource::new(input);
+
+        assert_eq!(
+            source.read().unwrap(),
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column: 2,
+                row: 1,
+                modifiers: KeyModifiers::NONE,
+            })
+        );
+    }
+}
@@ -0,0 +1,514 @@
+///
+/// History stores submitted commands so they can be recalled and browsed
+///
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::completion::fuzzy::{fuzzy_match, is_fuzzy_match};
+
+/// How the History treats an entry that was already submitted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Keep every entry, even repeats
+    Keep,
+    /// Don't record an entry that matches the most recent one
+    IgnoreConsecutive,
+    /// Remove older copies of an entry when it's submitted again
+    EraseOld,
+}
+
+/// History is a list of submitted commands, oldest first
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::history::history::History;
+///
+/// let mut history = History::default();
+/// history.push("first");
+/// history.push("second");
+///
+/// assert_eq!(history.back("draft"), Some("second"));
+/// assert_eq!(history.back("second"), Some("first"));
+/// assert_eq!(history.forward(), Some("second"));
+/// assert_eq!(history.forward(), Some("draft"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct History {
+    /// The recorded entries, oldest first
+    pub entries: Vec<String>,
+    /// The maximum number of entries to keep, older entries are dropped
+    pub max_len: usize,
+    /// How repeated entries are recorded
+    pub duplicates: DuplicatePolicy,
+    /// File the history is saved to, if any
+    pub file: Option<PathBuf>,
+    /// Number of lines in the history file, which is compacted once
+    /// appending makes it longer than max_len
+    file_lines: usize,
+    /// Index of the entry currently being browsed
+    position: Option<usize>,
+    /// The input that was being edited when browsing started
+    draft: String,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History {
+            entries: Vec::new(),
+            max_len: 1000,
+            duplicates: DuplicatePolicy::IgnoreConsecutive,
+            file: None,
+            file_lines: 0,
+            position: None,
+            draft: String::new(),
+        }
+    }
+}
+
+impl History {
+    /// Record an entry and stop browsing
+    /// Blank entries are not recorded
+    /// Returns false if the entry wasn't recorded.
+    pub fn push(&mut self, entry: &str) -> bool {
+        self.reset();
+        if entry.trim().is_empty() {
+            return false;
+        }
+
+        match self.duplicates {
+            DuplicatePolicy::Keep => (),
+            DuplicatePolicy::IgnoreConsecutive => {
+                if self.entries.last().map(String::as_str) == Some(entry) {
+                    return false;
+                }
+            }
+            DuplicatePolicy::EraseOld => self.entries.retain(|e| e != entry),
+        }
+
+        self.entries.push(String::from(entry));
+        self.truncate();
+        true
+    }
+
+    /// Drop the oldest entries past max_len
+    fn truncate(&mut self) {
+        if self.entries.len() > self.max_len {
+            let excess = self.entries.len() - self.max_len;
+            self.entries.drain(..excess);
+        }
+    }
+
+    /// Stop browsing, the next call to back starts at the newest entry
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+
+    /// Whether an entry is currently being browsed
+    pub fn is_browsing(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// The input that was being edited when browsing started
+    /// Returns None if no entry is being browsed.
+    pub fn draft(&self) -> Option<&str> {
+        self.position.map(|_| self.draft.as_str())
+    }
+
+    /// Step back to the previous entry
+    /// current is the input being edited, it's kept as the draft when
+    /// browsing starts.
+    /// Returns None if there are no older entries.
+    pub fn back(&mut self, current: &str) -> Option<&str> {
+        let index = match self.position {
+            None => {
+                if self.entries.is_empty() {
+                    return None;
+                }
+                self.draft = String::from(current);
+                self.entries.len() - 1
+            }
+            Some(0) => return None,
+            Some(i) => i - 1,
+        };
+        self.position = Some(index);
+        Some(&self.entries[index])
+    }
+
+    /// Step forward to the next entry
+    /// Moving past the newest entry returns the draft and stops browsing.
+    /// Returns None if no entry is being browsed.
+    pub fn forward(&mut self) -> Option<&str> {
+        let index = self.position? + 1;
+        if index < self.entries.len() {
+            self.position = Some(index);
+            Some(&self.entries[index])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    /// Load entries from a file and save to it from now on
+    /// A file that doesn't exist yet is treated as empty.
+    /// Entries are appended to the file as they're submitted, so it's
+    /// rewritten without the dropped and duplicate entries once it's loaded.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        self.reset();
+        self.entries.clear();
+        for line in contents.lines() {
+            self.push(line);
+        }
+        self.file = Some(path.to_path_buf());
+        self.file_lines = contents.lines().count();
+
+        if self.file_lines > self.entries.len() {
+            self.save()?;
+            self.file_lines = self.entries.len();
+        }
+        Ok(())
+    }
+
+    /// Add an entry that was just pushed to the end of the history file
+    /// Once the file would be longer than max_len it's rewritten with the
+    /// entries instead, so it doesn't grow without limit.
+    /// Does nothing if there is no history file.
+    pub fn append(&mut self, entry: &str) -> io::Result<()> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if self.file_lines >= self.max_len {
+            self.save()?;
+            self.file_lines = self.entries.len();
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", entry)?;
+        self.file_lines += 1;
+        Ok(())
+    }
+
+    /// Save the entries to the history file, one per line
+    /// Does nothing if there is no history file.
+    pub fn save(&self) -> io::Result<()> {
+        match &self.file {
+            Some(path) => {
+                let mut contents = self.entries.join("\n");
+                contents.push('\n');
+                fs::write(path, contents)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// State of a reverse incremental search through the History
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::history::history::{History, ReverseSearch};
+///
+/// let mut history = History::default();
+/// history.push("open one");
+/// history.push("write");
+/// history.push("open two");
+///
+/// let mut search = ReverseSearch::default();
+/// search.push('o', &history);
+/// assert_eq!(search.matched(&history), Some("open two"));
+/// search.step_back(&history);
+/// assert_eq!(search.matched(&history), Some("open one"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReverseSearch {
+    /// The text being searched for
+    pub query: String,
+    /// Index of the matching entry
+    /// A failed search keeps the last match.
+    pub found: Option<usize>,
+    /// Whether the last search failed to find a match
+    pub failed: bool,
+    /// Character indexes in the matching entry of the query characters
+    pub positions: Vec<usize>,
+    /// Match entries containing the query characters in order instead of
+    /// the exact query
+    pub fuzzy: bool,
+    /// The input to restore when the search is cancelled, if browsing the
+    /// history had replaced it
+    pub draft: Option<String>,
+}
+
+impl ReverseSearch {
+    /// Whether entry matches the query
+    fn matches(&self, entry: &str) -> bool {
+        if self.fuzzy {
+            is_fuzzy_match(&self.query, entry)
+        } else {
+            entry.contains(&self.query)
+        }
+    }
+
+    /// Character indexes in entry of the query characters
+    fn match_positions(&self, entry: &str) -> Vec<usize> {
+        if self.fuzzy {
+            fuzzy_match(&self.query, entry).map_or_else(Vec::new, |m| m.positions)
+        } else {
+            match entry.find(&self.query) {
+                Some(offset) => {
+                    let start = entry[..offset].chars().count();
+                    (start..start + self.query.chars().count()).collect()
+                }
+                None => Vec::new(),
+            }
+        }
+    }
+
+    /// Byte offset in the matching entry of the start of the query match
+    pub fn match_start(&self, history: &History) -> usize {
+        match (self.matched(history), self.positions.first()) {
+            (Some(entry), Some(&start)) => entry
+                .char_indices()
+                .nth(start)
+                .map_or(0, |(offset, _)| offset),
+            _ => 0,
+        }
+    }
+
+    /// Search backwards from the entry before end for the query
+    fn search(&mut self, history: &History, end: usize) {
+        if self.query.is_empty() {
+            self.found = None;
+            self.failed = false;
+            self.positions.clear();
+            return;
+        }
+        let end = end.min(history.entries.len());
+        match history.entries[..end]
+            .iter()
+            .rposition(|entry| self.matches(entry))
+        {
+            Some(i) => {
+                self.found = Some(i);
+                self.failed = false;
+                // Only the match that's shown is scored
+                self.positions = self.match_positions(&history.entries[i]);
+            }
+            None => self.failed = true,
+        }
+    }
+
+    /// Add a character to the query
+    /// The current match is kept if it still matches.
+    pub fn push(&mut self, c: char, history: &History) {
+        self.query.push(c);
+        let end = self.found.map_or(history.entries.len(), |i| i + 1);
+        self.search(history, end);
+    }
+
+    /// Remove the last character from the query and search again from the
+    /// newest entry
+    pub fn pop(&mut self, history: &History) {
+        self.query.pop();
+        self.search(history, history.entries.len());
+    }
+
+    /// Find the next older entry that matches the query
+    pub fn step_back(&mut self, history: &History) {
+        let end = self.found.unwrap_or(history.entries.len());
+        self.search(history, end);
+    }
+
+    /// The entry that matches the query, if any
+    pub fn matched<'h>(&self, history: &'h History) -> Option<&'h str> {
+        self.found
+            .and_then(|i| history.entries.get(i))
+            .map(String::as_str)
+    }
+
+    /// The prompt shown in front of the match
+    pub fn prompt(&self) -> String {
+        let failed = if self.failed { "failed " } else { "" };
+        format!("({}reverse-i-search)`{}': ", failed, self.query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::history::history::{DuplicatePolicy, History, ReverseSearch};
+
+    fn history_with(entries: &[&str], duplicates: DuplicatePolicy) -> History {
+        let mut history = History {
+            duplicates,
+            ..Default::default()
+        };
+        for entry in entries {
+            history.push(entry);
+        }
+        history
+    }
+
+    #[test]
+    fn history_browses_and_restores_draft() {
+        let mut history = history_with(&["a", "b"], DuplicatePolicy::Keep);
+
+        assert_eq!(history.forward(), None);
+        assert_eq!(history.draft(), None);
+        assert_eq!(history.back("draft"), Some("b"));
+        assert_eq!(history.back("b"), Some("a"));
+        assert_eq!(history.draft(), Some("draft"));
+        assert_eq!(history.back("a"), None);
+        assert_eq!(history.forward(), Some("b"));
+        assert_eq!(history.forward(), Some("draft"));
+        assert!(!history.is_browsing());
+    }
+
+    #[test]
+    fn history_ignores_blank_entries() {
+        let history = history_with(&["a", "", "  "], DuplicatePolicy::Keep);
+        assert_eq!(history.entries, vec!["a"]);
+    }
+
+    #[test]
+    fn history_duplicate_policies() {
+        let entries = ["a", "b", "b", "a"];
+
+        let history = history_with(&entries, DuplicatePolicy::Keep);
+        assert_eq!(history.entries, vec!["a", "b", "b", "a"]);
+
+        let history = history_with(&entries, DuplicatePolicy::IgnoreConsecutive);
+        assert_eq!(history.entries, vec!["a", "b", "a"]);
+
+        let history = history_with(&entries, DuplicatePolicy::EraseOld);
+        assert_eq!(history.entries, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn history_keeps_max_len_entries() {
+        let mut history = History {
+            max_len: 2,
+            ..Default::default()
+        };
+        history.push("a");
+        history.push("b");
+        history.push("c");
+        assert_eq!(history.entries, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn history_saves_and_loads_file() {
+        let path = std::env::temp_dir().join(format!(
+            "tui-command-bar-widget-history-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let mut history = History::default();
+        history.load(&path).unwrap();
+        assert!(history.entries.is_empty());
+        history.push("open file");
+        history.push("write");
+        history.save().unwrap();
+        history.append("open file").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "open file\nwrite\nopen file\n"
+        );
+
+        // Loading drops the entries the history wouldn't keep, and compacts
+        // the file to match
+        let mut loaded = History {
+            max_len: 2,
+            duplicates: DuplicatePolicy::EraseOld,
+            ..Default::default()
+        };
+        loaded.load(&path).unwrap();
+        assert_eq!(loaded.entries, vec!["write", "open file"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "write\nopen file\n");
+
+        // Appending past max_len compacts the file too
+        for entry in ["quit", "write"] {
+            loaded.push(entry);
+            loaded.append(entry).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "quit\nwrite\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reverse_search_narrows_and_steps_back() {
+        let history = history_with(
+            &["git commit", "ls", "git push", "grep"],
+            DuplicatePolicy::Keep,
+        );
+        let mut search = ReverseSearch::default();
+        assert_eq!(search.matched(&history), None);
+
+        search.push('g', &history);
+        assert_eq!(search.matched(&history), Some("grep"));
+        search.push('i', &history);
+        assert_eq!(search.matched(&history), Some("git push"));
+        search.step_back(&history);
+        assert_eq!(search.matched(&history), Some("git commit"));
+        assert!(!search.failed);
+
+        // There are no older matches, so the search fails but keeps the match
+        search.step_back(&history);
+        assert_eq!(search.matched(&history), Some("git commit"));
+        assert!(search.failed);
+        assert_eq!(search.prompt(), "(failed reverse-i-search)`gi': ");
+
+        search.pop(&history);
+        assert_eq!(search.matched(&history), Some("grep"));
+        assert_eq!(search.prompt(), "(reverse-i-search)`g': ");
+    }
+
+    #[test]
+    fn reverse_search_fuzzy() {
+        let history = history_with(&["write-all", "wq", "ls"], DuplicatePolicy::Keep);
+        let mut search = ReverseSearch {
+            fuzzy: true,
+            ..Default::default()
+        };
+        search.push('w', &history);
+        search.push('a', &history);
+        assert_eq!(search.matched(&history), Some("write-all"));
+        assert_eq!(search.positions, vec![0, 6]);
+        assert_eq!(search.match_start(&history), 0);
+
+        search.pop(&history);
+        search.pop(&history);
+        search.push('a', &history);
+        search.push('l', &history);
+        assert_eq!(search.match_start(&history), 6);
+    }
+
+    #[test]
+    fn reverse_search_fuzzy_long_entry() {
+        let entry = format!("{}write-all", "x-".repeat(5000));
+        let history = history_with(&[&entry, "ls"], DuplicatePolicy::Keep);
+        let mut search = ReverseSearch {
+            fuzzy: true,
+            ..Default::default()
+        };
+        for c in "writeall".chars() {
+            search.push(c, &history);
+        }
+        assert_eq!(search.matched(&history), Some(entry.as_str()));
+        assert_eq!(search.match_start(&history), 10000);
+        search.push('q', &history);
+        assert!(search.failed);
+    }
+}
@@ -0,0 +1,7 @@
+///
+/// history is a module for recalling previously submitted commands
+/// The history can be browsed from the CommandBar and optionally kept
+/// in a file between sessions.
+///
+#[allow(clippy::module_inception)]
+pub mod history;
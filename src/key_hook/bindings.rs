@@ -0,0 +1,117 @@
+///
+/// Descriptions of key bindings, for help screens and checking a keymap
+///
+use std::fmt;
+
+use crate::key_hook::key_sequence::KeySequence;
+use crate::key_hook::keymap::Action;
+
+/// What a key sequence is bound to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BindingTarget {
+    /// A handler registered in a KeyDatabase
+    Handler,
+    /// An action in a Keymap
+    Action(Action),
+}
+
+/// A key sequence and what it does
+#[derive(Clone, Debug, PartialEq)]
+pub struct Binding {
+    /// The keys that are pressed
+    pub keys: KeySequence,
+    /// What the keys are bound to
+    pub target: BindingTarget,
+    /// What the binding does, for help screens
+    pub description: String,
+}
+
+/// Bindings that get in each other's way
+#[derive(Clone, Debug, PartialEq)]
+pub enum Conflict {
+    /// Keys are bound to a handler and to an action, only the handler runs
+    Duplicate {
+        /// The keys bound twice
+        keys: KeySequence,
+        /// The action that never runs
+        action: Action,
+    },
+    /// Keys are bound on their own and start a longer sequence, so they
+    /// only run after the sequence times out or is broken
+    Shadowed {
+        /// The shorter binding
+        keys: KeySequence,
+        /// The longer sequence they start
+        by: KeySequence,
+    },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Duplicate { keys, action } => {
+                write!(
+                    f,
+                    "{} is bound to a handler, so {} never runs",
+                    keys, action
+                )
+            }
+            Conflict::Shadowed { keys, by } => {
+                write!(f, "{} starts {}, so it waits for the next key", keys, by)
+            }
+        }
+    }
+}
+
+impl Conflict {
+    /// The keys the conflict is about
+    pub fn keys(&self) -> &KeySequence {
+        match self {
+            Conflict::Duplicate { keys, .. } | Conflict::Shadowed { keys, .. } => keys,
+        }
+    }
+}
+
+/// Sort conflicts by their keys, then by what they conflict with
+pub(crate) fn sort_conflicts(conflicts: &mut [Conflict]) {
+    conflicts.sort_by_cached_key(|conflict| match conflict {
+        Conflict::Duplicate { keys, action } => (keys.to_string(), action.to_string()),
+        Conflict::Shadowed { keys, by } => (keys.to_string(), by.to_string()),
+    });
+}
+
+/// A binding that's different from the default keymap
+#[derive(Clone, Debug, PartialEq)]
+pub struct Override {
+    /// The keys that are bound differently
+    pub keys: KeySequence,
+    /// The action bound by default, if any
+    pub default: Option<Action>,
+    /// The action bound now, None if the default binding was removed
+    pub action: Option<Action>,
+}
+
+/// Sort bindings and overrides by their keys as they're written
+pub(crate) fn sort_by_keys<T, F: Fn(&T) -> &KeySequence>(items: &mut [T], keys: F) {
+    items.sort_by_cached_key(|item| keys(item).to_string());
+}
+
+/// The Shadowed conflicts between bound sequences, in no particular order
+pub(crate) fn shadowed<'a, I>(sequences: I) -> Vec<Conflict>
+where
+    I: IntoIterator<Item = &'a KeySequence>,
+{
+    let sequences: Vec<&KeySequence> = sequences.into_iter().collect();
+    let mut conflicts = Vec::new();
+    for keys in &sequences {
+        for by in &sequences {
+            if by.extends(&keys.keys) {
+                conflicts.push(Conflict::Shadowed {
+                    keys: (*keys).clone(),
+                    by: (*by).clone(),
+                });
+            }
+        }
+    }
+    conflicts
+}
@@ -0,0 +1,261 @@
+///
+/// KeyCombination is a key code with modifiers, such as Ctrl-P or F2
+///
+use std::fmt;
+use std::str::FromStr;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A key and the modifiers held with it
+///
+/// Characters already include the shift key, so Shift is dropped from
+/// character keys: 'A' is stored as Char('A') without modifiers.
+///
+/// # Example
+///
+/// ```
+/// use crossterm::event::{KeyCode, KeyModifiers};
+/// use tui_command_bar_widget::key_hook::key_combination::KeyCombination;
+///
+/// let key: KeyCombination = "C-p".parse().unwrap();
+/// assert_eq!(key, KeyCombination::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+/// assert_eq!(key.to_string(), "C-p");
+///
+/// assert_eq!("M-x".parse::<KeyCombination>().unwrap().modifiers, KeyModifiers::ALT);
+/// assert_eq!("F2".parse::<KeyCombination>().unwrap().code, KeyCode::F(2));
+/// assert_eq!(KeyCombination::from(':'), ":".parse().unwrap());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyCombination {
+    /// The key that was pressed
+    pub code: KeyCode,
+    /// The modifiers held while it was pressed
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyCombination {
+    /// Build a key combination, normalizing Shift on character keys
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                // Terminals report Shift-a as 'A' with or without the modifier
+                let c = c.to_uppercase().next().unwrap_or(c);
+                KeyCombination {
+                    code: KeyCode::Char(c),
+                    modifiers: modifiers - KeyModifiers::SHIFT,
+                }
+            }
+            _ => KeyCombination { code, modifiers },
+        }
+    }
+}
+
+impl From<char> for KeyCombination {
+    fn from(c: char) -> Self {
+        KeyCombination::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+}
+
+impl From<KeyCode> for KeyCombination {
+    fn from(code: KeyCode) -> Self {
+        KeyCombination::new(code, KeyModifiers::NONE)
+    }
+}
+
+impl From<KeyEvent> for KeyCombination {
+    fn from(key: KeyEvent) -> Self {
+        KeyCombination::new(key.code, key.modifiers)
+    }
+}
+
+/// Errors parsing a key combination
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyParseError {
+    /// The string was empty
+    Empty,
+    /// A modifier prefix wasn't one of C, M, A or S
+    UnknownModifier(String),
+    /// The key name wasn't recognized
+    UnknownKey(String),
+}
+
+impl fmt::Display for KeyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyParseError::Empty => write!(f, "empty key"),
+            KeyParseError::UnknownModifier(m) => write!(f, "unknown key modifier: {}", m),
+            KeyParseError::UnknownKey(k) => write!(f, "unknown key: {}", k),
+        }
+    }
+}
+
+impl std::error::Error for KeyParseError {}
+
+/// Key names and their codes, the first name for a code is used when
+/// displaying it
+const KEY_NAMES: [(&str, KeyCode); 20] = [
+    ("Esc", KeyCode::Esc),
+    ("Escape", KeyCode::Esc),
+    ("Enter", KeyCode::Enter),
+    ("Ret", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("BackTab", KeyCode::BackTab),
+    ("Backspace", KeyCode::Backspace),
+    ("BS", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Del", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Ins", KeyCode::Insert),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+];
+
+/// Parse a key name without modifiers
+fn parse_code(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    if name.eq_ignore_ascii_case("Space") || name.eq_ignore_ascii_case("SPC") {
+        return Some(KeyCode::Char(' '));
+    }
+    if let Some(n) = name.strip_prefix(['F', 'f']) {
+        if let Ok(n) = n.parse::<u8>() {
+            return Some(KeyCode::F(n));
+        }
+    }
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|(_, code)| *code)
+}
+
+impl FromStr for KeyCombination {
+    type Err = KeyParseError;
+
+    /// Parse keys written like C-p, M-x, C-M-Left, F2, Esc or :
+    ///
+    /// C is Control, M and A are Alt and S is Shift.
+    /// A - on its own, or at the end after a modifier, is the minus key.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(KeyParseError::Empty);
+        }
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        while let Some((prefix, key)) = rest.split_once('-') {
+            if key.is_empty() {
+                // A trailing - is the key itself, like C--
+                break;
+            }
+            modifiers |= match prefix {
+                "C" => KeyModifiers::CONTROL,
+                "M" | "A" => KeyModifiers::ALT,
+                "S" => KeyModifiers::SHIFT,
+                _ => return Err(KeyParseError::UnknownModifier(String::from(prefix))),
+            };
+            rest = key;
+        }
+        let code = parse_code(rest).ok_or_else(|| KeyParseError::UnknownKey(String::from(rest)))?;
+        Ok(KeyCombination::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyCombination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, prefix) in [
+            (KeyModifiers::CONTROL, "C-"),
+            (KeyModifiers::ALT, "M-"),
+            (KeyModifiers::SHIFT, "S-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}", prefix)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => match KEY_NAMES.iter().find(|(_, c)| *c == code) {
+                Some((name, _)) => write!(f, "{}", name),
+                None => write!(f, "{:?}", code),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::key_hook::key_combination::{KeyCombination, KeyParseError};
+
+    fn parse(s: &str) -> Result<KeyCombination, KeyParseError> {
+        s.parse()
+    }
+
+    #[test]
+    fn key_combination_parses_modifiers() {
+        assert_eq!(
+            parse("C-M-x").unwrap(),
+            KeyCombination::new(
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+        assert_eq!(parse("A-x"), parse("M-x"));
+        assert_eq!(parse("S-a").unwrap(), KeyCombination::from('A'));
+        assert_eq!(
+            parse("S-Left").unwrap(),
+            KeyCombination::new(KeyCode::Left, KeyModifiers::SHIFT)
+        );
+        assert_eq!(
+            parse("C--").unwrap(),
+            KeyCombination::new(KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(parse("-").unwrap(), KeyCombination::from('-'));
+    }
+
+    #[test]
+    fn key_combination_parses_names() {
+        assert_eq!(parse("F12").unwrap(), KeyCombination::from(KeyCode::F(12)));
+        assert_eq!(parse("esc").unwrap(), KeyCombination::from(KeyCode::Esc));
+        assert_eq!(parse("RET").unwrap(), KeyCombination::from(KeyCode::Enter));
+        assert_eq!(parse("SPC").unwrap(), KeyCombination::from(' '));
+        assert_eq!(parse("F").unwrap(), KeyCombination::from('F'));
+    }
+
+    #[test]
+    fn key_combination_parse_errors() {
+        assert_eq!(parse(""), Err(KeyParseError::Empty));
+        assert_eq!(
+            parse("X-a"),
+            Err(KeyParseError::UnknownModifier(String::from("X")))
+        );
+        assert_eq!(
+            parse("C-Nope"),
+            Err(KeyParseError::UnknownKey(String::from("Nope")))
+        );
+    }
+
+    #[test]
+    fn key_combination_display_round_trips() {
+        for s in ["C-p", "M-x", "C-M-Left", "F2", "Esc", ":", "Space", "S-Tab"] {
+            assert_eq!(parse(s).unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn key_combination_from_key_event_drops_shift_on_chars() {
+        let event = KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT);
+        assert_eq!(KeyCombination::from(event), KeyCombination::from('A'));
+        let event = KeyEvent::new(KeyCode::Char(':'), KeyModifiers::SHIFT);
+        assert_eq!(KeyCombination::from(event), KeyCombination::from(':'));
+    }
+}
@@ -3,25 +3,207 @@
 /// elements.
 ///
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crossterm::event::KeyEvent;
+
+use crate::key_hook::bindings::{
+    shadowed, sort_by_keys, sort_conflicts, Binding, BindingTarget, Conflict,
+};
+use crate::key_hook::key_combination::KeyCombination;
+use crate::key_hook::key_sequence::KeySequence;
+use crate::key_hook::keymap::Keymap;
+
+/// What a key handler did with the key it was given
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HandlerOutcome {
+    /// The handler used the key
+    Handled,
+    /// The handler ignored the key, so it's passed to the parent
+    Unhandled,
+}
+
+/// A key handler, called with the object it's registered on and the key
+/// event that was pressed
+/// Handlers are owned by the KeyDatabase, so they can capture and update
+/// application state.
+pub type KeyHandler<T> = Box<dyn FnMut(&mut T, KeyEvent) -> HandlerOutcome>;
+
+/// How long to wait for the next key of a sequence by default
+pub const DEFAULT_KEY_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// The description of handlers that weren't given one
+pub const DEFAULT_HANDLER_DESCRIPTION: &str = "run a key handler";
 
 /// The KeyDatabase stores command keys and the functions they invoke
-#[derive(Clone)]
-#[allow(clippy::type_complexity)]
-pub struct KeyDatabase<'a, T> {
+///
+/// Keys can be sequences like g g, so the keys pressed so far are kept in
+/// pending until they match a sequence or can't match any.
+pub struct KeyDatabase<T> {
     /// keys is the actual key database, implemented as a HashMap
-    /// mappings characters to functions that accept a generic object
-    /// and a character
-    pub keys: HashMap<char, &'a dyn Fn(&mut T, char)>,
+    /// mapping key sequences to the handlers they invoke
+    pub keys: HashMap<KeySequence, KeyHandler<T>>,
+    /// Descriptions of what the handlers do, for help screens
+    pub descriptions: HashMap<KeySequence, String>,
+    /// Keys pressed so far that start a longer sequence
+    pub pending: Vec<KeyEvent>,
+    /// When the last pending key was pressed
+    pub pending_since: Option<Instant>,
+    /// How long to wait for the next key of a sequence
+    /// When it runs out the pending keys run their own handler if they have
+    /// one, otherwise they're dropped.
+    /// None waits forever.
+    pub timeout: Option<Duration>,
 }
 
-impl<'a, T> Default for KeyDatabase<'a, T> {
+impl<T> Default for KeyDatabase<T> {
     fn default() -> Self {
         Self {
             keys: HashMap::new(),
+            descriptions: HashMap::new(),
+            pending: Vec::new(),
+            pending_since: None,
+            timeout: Some(DEFAULT_KEY_TIMEOUT),
         }
     }
 }
 
+impl<T> KeyDatabase<T> {
+    /// Whether a registered sequence starts with keys and is longer
+    pub fn is_prefix(&self, keys: &[KeyCombination]) -> bool {
+        self.keys.keys().any(|sequence| sequence.extends(keys))
+    }
+
+    /// The keys pressed so far in a sequence
+    pub fn pending_keys(&self) -> KeySequence {
+        KeySequence::new(
+            self.pending
+                .iter()
+                .map(|k| KeyCombination::from(*k))
+                .collect(),
+        )
+    }
+
+    /// Time left at now to press the next key of a sequence
+    /// Returns None if no keys are pending or there's no timeout.
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        let deadline = self.pending_since? + self.timeout?;
+        Some(deadline.saturating_duration_since(now))
+    }
+
+    /// Whether the pending keys have waited longer than the timeout at now
+    pub fn timed_out(&self, now: Instant) -> bool {
+        self.remaining(now) == Some(Duration::ZERO)
+    }
+
+    /// Take the pending keys, leaving none pending
+    pub fn take_pending(&mut self) -> Vec<KeyEvent> {
+        self.pending_since = None;
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Describe what the handler for keys does
+    pub fn describe<K: Into<KeySequence>>(&mut self, keys: K, description: &str) {
+        self.descriptions
+            .insert(keys.into(), String::from(description));
+    }
+
+    /// Every handler and the actions in keymap, sorted by keys
+    ///
+    /// Actions bound to the same keys as a handler are left out, since the
+    /// handler runs instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tui_command_bar_widget::key_hook::bindings::BindingTarget;
+    /// use tui_command_bar_widget::key_hook::key_hook::KeyHook;
+    /// use tui_command_bar_widget::key_hook::keymap::{Action, Keymap};
+    /// use tui_command_bar_widget::widgets::command_bar::CommandBar;
+    ///
+    /// let mut command_bar_widget = CommandBar::default();
+    /// command_bar_widget.register_key(':', CommandBar::command_key_handler);
+    /// command_bar_widget
+    ///     .key_database
+    ///     .describe(':', Action::Edit.description());
+    ///
+    /// let mut keymap = Keymap::default();
+    /// keymap.bind('q', Action::Cancel);
+    /// let bindings = command_bar_widget.key_database.bindings(&keymap);
+    /// assert_eq!(bindings[0].keys, ':'.into());
+    /// assert_eq!(bindings[0].target, BindingTarget::Handler);
+    /// assert_eq!(bindings[0].description, "start editing the command");
+    /// assert_eq!(bindings[1].target, BindingTarget::Action(Action::Cancel));
+    /// ```
+    pub fn bindings(&self, keymap: &Keymap) -> Vec<Binding> {
+        let handlers = self.keys.keys().map(|keys| Binding {
+            keys: keys.clone(),
+            target: BindingTarget::Handler,
+            description: self
+                .descriptions
+                .get(keys)
+                .cloned()
+                .unwrap_or_else(|| String::from(DEFAULT_HANDLER_DESCRIPTION)),
+        });
+        let actions = keymap
+            .bindings()
+            .into_iter()
+            .filter(|binding| !self.keys.contains_key(&binding.keys));
+        let mut bindings: Vec<Binding> = handlers.chain(actions).collect();
+        sort_by_keys(&mut bindings, |binding| &binding.keys);
+        bindings
+    }
+
+    /// Handlers and actions in keymap that get in each other's way
+    ///
+    /// Keys bound to a handler and an action only run the handler, and keys
+    /// that start a longer sequence wait for the next key before they run.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tui_command_bar_widget::key_hook::bindings::Conflict;
+    /// use tui_command_bar_widget::key_hook::key_hook::KeyHook;
+    /// use tui_command_bar_widget::key_hook::key_sequence::KeySequence;
+    /// use tui_command_bar_widget::key_hook::keymap::{Action, Keymap};
+    /// use tui_command_bar_widget::widgets::command_bar::CommandBar;
+    ///
+    /// let mut command_bar_widget = CommandBar::default();
+    /// command_bar_widget.register_key('g', CommandBar::command_key_handler);
+    ///
+    /// let mut keymap = Keymap::default();
+    /// keymap.bind("g g".parse::<KeySequence>().unwrap(), Action::HistoryPrevious);
+    /// assert_eq!(
+    ///     command_bar_widget.key_database.conflicts(&keymap),
+    ///     vec![Conflict::Shadowed {
+    ///         keys: 'g'.into(),
+    ///         by: "g g".parse().unwrap(),
+    ///     }]
+    /// );
+    /// ```
+    pub fn conflicts(&self, keymap: &Keymap) -> Vec<Conflict> {
+        let mut conflicts: Vec<Conflict> = keymap
+            .bindings
+            .iter()
+            .filter(|(keys, _)| self.keys.contains_key(*keys))
+            .map(|(keys, action)| Conflict::Duplicate {
+                keys: keys.clone(),
+                action: *action,
+            })
+            .collect();
+        let mut sequences: Vec<&KeySequence> = self.keys.keys().collect();
+        sequences.extend(
+            keymap
+                .bindings
+                .keys()
+                .filter(|keys| !self.keys.contains_key(*keys)),
+        );
+        conflicts.extend(shadowed(sequences));
+        sort_conflicts(&mut conflicts);
+        conflicts
+    }
+}
+
 /// Register keys to listen for
 /// Each View can register to listen for certain key presses in the main
 /// app event loop
@@ -35,10 +217,17 @@ impl<'a, T> Default for KeyDatabase<'a, T> {
 ///
 /// The command view may not be focused or even visible, so handling is done
 /// on the global hook.
-pub trait KeyHook<'a, T> {
-    /// Register a key listener
-    fn register_key(&mut self, key: char, f: &'a dyn Fn(&mut T, char));
+///
+/// Keys can be given as a char, a KeyCode, a KeyCombination parsed from
+/// a string like "C-p" or a KeySequence parsed from a string like "C-x C-s".
+/// Handlers can be closures or methods like CommandBar::command_key_handler.
+pub trait KeyHook<T> {
+    /// Register a key listener, replacing any listener for the same key
+    fn register_key<K, F>(&mut self, key: K, f: F)
+    where
+        K: Into<KeySequence>,
+        F: FnMut(&mut T, KeyEvent) -> HandlerOutcome + 'static;
 
     /// Unregister a key listener
-    fn unregister_key(&mut self, key: char);
+    fn unregister_key<K: Into<KeySequence>>(&mut self, key: K);
 }
@@ -0,0 +1,127 @@
+///
+/// KeySequence is a chord of one or more key combinations, such as g g or
+/// C-x C-s
+///
+use std::fmt;
+use std::str::FromStr;
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::key_hook::key_combination::{KeyCombination, KeyParseError};
+
+/// Key combinations that are pressed one after the other
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::key_hook::key_combination::KeyCombination;
+/// use tui_command_bar_widget::key_hook::key_sequence::KeySequence;
+///
+/// let save: KeySequence = "C-x C-s".parse().unwrap();
+/// assert_eq!(save.keys.len(), 2);
+/// assert_eq!(save.to_string(), "C-x C-s");
+///
+/// // Space is written by name, since spaces separate the keys
+/// let find: KeySequence = "Space f f".parse().unwrap();
+/// assert_eq!(find.keys[0], KeyCombination::from(' '));
+///
+/// assert_eq!(KeySequence::from(':'), ":".parse().unwrap());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KeySequence {
+    /// The keys in the order they're pressed
+    pub keys: Vec<KeyCombination>,
+}
+
+impl KeySequence {
+    /// Build a sequence from keys
+    pub fn new(keys: Vec<KeyCombination>) -> Self {
+        KeySequence { keys }
+    }
+
+    /// Whether the sequence starts with prefix and has more keys after it
+    pub fn extends(&self, prefix: &[KeyCombination]) -> bool {
+        self.keys.len() > prefix.len() && self.keys.starts_with(prefix)
+    }
+}
+
+impl From<KeyCombination> for KeySequence {
+    fn from(key: KeyCombination) -> Self {
+        KeySequence::new(vec![key])
+    }
+}
+
+impl From<char> for KeySequence {
+    fn from(c: char) -> Self {
+        KeySequence::from(KeyCombination::from(c))
+    }
+}
+
+impl From<KeyCode> for KeySequence {
+    fn from(code: KeyCode) -> Self {
+        KeySequence::from(KeyCombination::from(code))
+    }
+}
+
+impl From<KeyEvent> for KeySequence {
+    fn from(key: KeyEvent) -> Self {
+        KeySequence::from(KeyCombination::from(key))
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = KeyParseError;
+
+    /// Parse keys separated by whitespace, like g g or C-x C-s
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keys = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<KeyCombination>, _>>()?;
+        if keys.is_empty() {
+            return Err(KeyParseError::Empty);
+        }
+        Ok(KeySequence::new(keys))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, key) in self.keys.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", key)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key_hook::key_combination::{KeyCombination, KeyParseError};
+    use crate::key_hook::key_sequence::KeySequence;
+
+    #[test]
+    fn key_sequence_parses_keys() {
+        let sequence: KeySequence = "  g   g ".parse().unwrap();
+        assert_eq!(
+            sequence.keys,
+            vec![KeyCombination::from('g'), KeyCombination::from('g')]
+        );
+        assert_eq!("".parse::<KeySequence>(), Err(KeyParseError::Empty));
+        assert_eq!(
+            "C-x Nope".parse::<KeySequence>(),
+            Err(KeyParseError::UnknownKey(String::from("Nope")))
+        );
+    }
+
+    #[test]
+    fn key_sequence_extends_prefix() {
+        let sequence: KeySequence = "Space f f".parse().unwrap();
+        assert!(sequence.extends(&[]));
+        assert!(sequence.extends(&sequence.keys[..2]));
+        assert!(!sequence.extends(&sequence.keys));
+        assert!(!sequence.extends(&[KeyCombination::from('f')]));
+    }
+}
@@ -0,0 +1,480 @@
+///
+/// Keymap binds key sequences to the editing actions of a CommandBar
+///
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::key_hook::bindings::{
+    shadowed, sort_by_keys, sort_conflicts, Binding, BindingTarget, Conflict, Override,
+};
+use crate::key_hook::key_combination::KeyCombination;
+use crate::key_hook::key_sequence::KeySequence;
+
+/// An action of the CommandBar that keys can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Start editing the command
+    Edit,
+    /// Submit the command and stop editing
+    Submit,
+    /// Stop editing without submitting
+    Cancel,
+    /// Delete the grapheme before the cursor
+    DeleteBackward,
+    /// Delete the grapheme under the cursor
+    DeleteForward,
+    /// Kill the word before the cursor
+    DeleteWordBackward,
+    /// Kill the word after the cursor
+    DeleteWordForward,
+    /// Kill the input before the cursor
+    KillToStart,
+    /// Kill the input after the cursor
+    KillToEnd,
+    /// Move the cursor one grapheme left
+    MoveLeft,
+    /// Move the cursor one grapheme right
+    MoveRight,
+    /// Move the cursor to the start of the input
+    MoveHome,
+    /// Move the cursor to the end of the input
+    MoveEnd,
+    /// Move the cursor to the start of the word before it
+    MoveWordLeft,
+    /// Move the cursor to the end of the word after it
+    MoveWordRight,
+    /// Swap the graphemes before and under the cursor
+    TransposeChars,
+    /// Insert the most recently killed text
+    Yank,
+    /// Replace the yanked text with an older kill
+    YankPop,
+    /// Undo the last change to the input
+    Undo,
+    /// Redo the last change that was undone
+    Redo,
+    /// Complete the argument at the cursor
+    Complete,
+    /// Go back to the previous completion candidate
+    CompletePrevious,
+    /// Recall the previous history entry
+    HistoryPrevious,
+    /// Recall the next history entry
+    HistoryNext,
+    /// Search the history backwards
+    ReverseSearch,
+}
+
+/// Action names, as written in key maps, and descriptions
+/// The entries are in the same order as the Action variants.
+const ACTIONS: [(Action, &str, &str); 25] = [
+    (Action::Edit, "edit", "start editing the command"),
+    (Action::Submit, "submit", "submit the command"),
+    (Action::Cancel, "cancel", "stop editing without submitting"),
+    (
+        Action::DeleteBackward,
+        "delete-backward",
+        "delete the character before the cursor",
+    ),
+    (
+        Action::DeleteForward,
+        "delete-forward",
+        "delete the character under the cursor",
+    ),
+    (
+        Action::DeleteWordBackward,
+        "delete-word-backward",
+        "delete the word before the cursor",
+    ),
+    (
+        Action::DeleteWordForward,
+        "delete-word-forward",
+        "delete the word after the cursor",
+    ),
+    (
+        Action::KillToStart,
+        "kill-to-start",
+        "delete everything before the cursor",
+    ),
+    (
+        Action::KillToEnd,
+        "kill-to-end",
+        "delete everything after the cursor",
+    ),
+    (Action::MoveLeft, "move-left", "move the cursor left"),
+    (Action::MoveRight, "move-right", "move the cursor right"),
+    (
+        Action::MoveHome,
+        "move-home",
+        "move the cursor to the start",
+    ),
+    (Action::MoveEnd, "move-end", "move the cursor to the end"),
+    (
+        Action::MoveWordLeft,
+        "move-word-left",
+        "move the cursor back a word",
+    ),
+    (
+        Action::MoveWordRight,
+        "move-word-right",
+        "move the cursor forward a word",
+    ),
+    (
+        Action::TransposeChars,
+        "transpose-chars",
+        "swap the characters around the cursor",
+    ),
+    (Action::Yank, "yank", "insert the last deleted text"),
+    (
+        Action::YankPop,
+        "yank-pop",
+        "replace the inserted text with older deleted text",
+    ),
+    (Action::Undo, "undo", "undo the last change"),
+    (Action::Redo, "redo", "redo the last undone change"),
+    (Action::Complete, "complete", "complete the argument"),
+    (
+        Action::CompletePrevious,
+        "complete-previous",
+        "go back to the previous completion",
+    ),
+    (
+        Action::HistoryPrevious,
+        "history-previous",
+        "recall the previous command",
+    ),
+    (
+        Action::HistoryNext,
+        "history-next",
+        "recall the next command",
+    ),
+    (
+        Action::ReverseSearch,
+        "reverse-search",
+        "search the command history",
+    ),
+];
+
+impl Action {
+    /// Every action, in the order they're documented
+    pub fn all() -> impl Iterator<Item = Action> {
+        ACTIONS.iter().map(|(action, _, _)| *action)
+    }
+
+    /// The name of the action, as written in key maps
+    pub fn name(&self) -> &'static str {
+        self.entry().1
+    }
+
+    /// A short description of what the action does
+    pub fn description(&self) -> &'static str {
+        self.entry().2
+    }
+
+    fn entry(&self) -> &'static (Action, &'static str, &'static str) {
+        &ACTIONS[*self as usize]
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Error parsing an action name that isn't known
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownAction(pub String);
+
+impl fmt::Display for UnknownAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown action: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownAction {}
+
+impl FromStr for Action {
+    type Err = UnknownAction;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ACTIONS
+            .iter()
+            .find(|(_, name, _)| *name == s)
+            .map(|(action, _, _)| *action)
+            .ok_or_else(|| UnknownAction(String::from(s)))
+    }
+}
+
+/// A Keymap binds key sequences to actions
+///
+/// A CommandBar has a keymap for each InputMode. Keys that aren't bound in
+/// the Editing keymap insert their character.
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::key_hook::key_sequence::KeySequence;
+/// use tui_command_bar_widget::key_hook::keymap::{Action, Keymap};
+///
+/// let mut keymap = Keymap::editing();
+/// let enter: KeySequence = "Enter".parse().unwrap();
+/// assert_eq!(keymap.get(&enter), Some(Action::Submit));
+///
+/// let control_j: KeySequence = "C-j".parse().unwrap();
+/// keymap.bind(control_j.clone(), Action::Submit);
+/// keymap.unbind(enter.clone());
+/// assert_eq!(keymap.get(&control_j), Some(Action::Submit));
+/// assert_eq!(keymap.get(&enter), None);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Keymap {
+    /// The actions bound to each key sequence
+    pub bindings: HashMap<KeySequence, Action>,
+}
+
+impl Keymap {
+    /// The default bindings for editing a command
+    /// They include the readline bindings, like Ctrl-A, Alt-F and Ctrl-K.
+    pub fn editing() -> Self {
+        let mut keymap = Keymap::default();
+        for (code, action) in [
+            (KeyCode::Enter, Action::Submit),
+            (KeyCode::Esc, Action::Cancel),
+            (KeyCode::Backspace, Action::DeleteBackward),
+            (KeyCode::Delete, Action::DeleteForward),
+            (KeyCode::Left, Action::MoveLeft),
+            (KeyCode::Right, Action::MoveRight),
+            (KeyCode::Home, Action::MoveHome),
+            (KeyCode::End, Action::MoveEnd),
+            (KeyCode::Tab, Action::Complete),
+            (KeyCode::Up, Action::HistoryPrevious),
+            (KeyCode::Down, Action::HistoryNext),
+        ] {
+            keymap.bind(code, action);
+        }
+        // crossterm reports Shift-Tab as BackTab with the shift modifier
+        keymap.bind(
+            KeyCombination::new(KeyCode::BackTab, KeyModifiers::SHIFT),
+            Action::CompletePrevious,
+        );
+        keymap.bind(KeyCode::BackTab, Action::CompletePrevious);
+        for (c, modifiers, action) in [
+            ('r', KeyModifiers::CONTROL, Action::ReverseSearch),
+            ('a', KeyModifiers::CONTROL, Action::MoveHome),
+            ('e', KeyModifiers::CONTROL, Action::MoveEnd),
+            ('b', KeyModifiers::CONTROL, Action::MoveLeft),
+            ('f', KeyModifiers::CONTROL, Action::MoveRight),
+            ('b', KeyModifiers::ALT, Action::MoveWordLeft),
+            ('f', KeyModifiers::ALT, Action::MoveWordRight),
+            ('w', KeyModifiers::CONTROL, Action::DeleteWordBackward),
+            ('d', KeyModifiers::ALT, Action::DeleteWordForward),
+            ('u', KeyModifiers::CONTROL, Action::KillToStart),
+            ('k', KeyModifiers::CONTROL, Action::KillToEnd),
+            ('t', KeyModifiers::CONTROL, Action::TransposeChars),
+            ('y', KeyModifiers::CONTROL, Action::Yank),
+            ('y', KeyModifiers::ALT, Action::YankPop),
+            ('_', KeyModifiers::CONTROL, Action::Undo),
+            // Terminals send Ctrl-_ as the same byte as Ctrl-7
+            ('7', KeyModifiers::CONTROL, Action::Undo),
+            ('z', KeyModifiers::CONTROL, Action::Undo),
+            ('_', KeyModifiers::ALT, Action::Redo),
+        ] {
+            keymap.bind(KeyCombination::new(KeyCode::Char(c), modifiers), action);
+        }
+        keymap
+    }
+
+    /// Bind keys to action, returning the action they were bound to before
+    pub fn bind<K: Into<KeySequence>>(&mut self, keys: K, action: Action) -> Option<Action> {
+        self.bindings.insert(keys.into(), action)
+    }
+
+    /// Remove the binding for keys, returning the action they were bound to
+    pub fn unbind<K: Into<KeySequence>>(&mut self, keys: K) -> Option<Action> {
+        self.bindings.remove(&keys.into())
+    }
+
+    /// The action bound to keys
+    pub fn get(&self, keys: &KeySequence) -> Option<Action> {
+        self.bindings.get(keys).copied()
+    }
+
+    /// Whether a bound sequence starts with keys and is longer
+    pub fn is_prefix(&self, keys: &[KeyCombination]) -> bool {
+        self.bindings.keys().any(|sequence| sequence.extends(keys))
+    }
+
+    /// Every binding with the description of its action, sorted by keys
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tui_command_bar_widget::key_hook::keymap::Keymap;
+    ///
+    /// for binding in Keymap::editing().bindings() {
+    ///     println!("{:>10}  {}", binding.keys, binding.description);
+    /// }
+    /// ```
+    pub fn bindings(&self) -> Vec<Binding> {
+        let mut bindings: Vec<Binding> = self
+            .bindings
+            .iter()
+            .map(|(keys, action)| Binding {
+                keys: keys.clone(),
+                target: BindingTarget::Action(*action),
+                description: String::from(action.description()),
+            })
+            .collect();
+        sort_by_keys(&mut bindings, |binding| &binding.keys);
+        bindings
+    }
+
+    /// Bindings that start a longer bound sequence
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = shadowed(self.bindings.keys());
+        sort_conflicts(&mut conflicts);
+        conflicts
+    }
+
+    /// Bindings that are different from defaults, including default
+    /// bindings that were removed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crossterm::event::KeyCode;
+    /// use tui_command_bar_widget::key_hook::bindings::Override;
+    /// use tui_command_bar_widget::key_hook::keymap::{Action, Keymap};
+    ///
+    /// let mut keymap = Keymap::editing();
+    /// keymap.bind(KeyCode::Esc, Action::Submit);
+    /// assert_eq!(
+    ///     keymap.overrides(&Keymap::editing()),
+    ///     vec![Override {
+    ///         keys: KeyCode::Esc.into(),
+    ///         default: Some(Action::Cancel),
+    ///         action: Some(Action::Submit),
+    ///     }]
+    /// );
+    /// ```
+    pub fn overrides(&self, defaults: &Keymap) -> Vec<Override> {
+        let mut overrides: Vec<Override> = self
+            .bindings
+            .keys()
+            .chain(defaults.bindings.keys())
+            .filter(|keys| self.get(keys) != defaults.get(keys))
+            .map(|keys| Override {
+                keys: keys.clone(),
+                default: defaults.get(keys),
+                action: self.get(keys),
+            })
+            .collect();
+        sort_by_keys(&mut overrides, |o| &o.keys);
+        overrides.dedup();
+        overrides
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+
+    use crate::key_hook::bindings::{BindingTarget, Override};
+    use crate::key_hook::key_sequence::KeySequence;
+    use crate::key_hook::keymap::{Action, Keymap, UnknownAction};
+
+    #[test]
+    fn action_names_round_trip() {
+        for action in Action::all() {
+            assert_eq!(action.name().parse::<Action>(), Ok(action));
+            assert!(!action.description().is_empty());
+        }
+        assert_eq!(Action::all().count(), 25);
+        assert_eq!(
+            "explode".parse::<Action>(),
+            Err(UnknownAction(String::from("explode")))
+        );
+    }
+
+    #[test]
+    fn keymap_binds_sequences() {
+        let mut keymap = Keymap::editing();
+        assert_eq!(
+            keymap.bind(KeyCode::Enter, Action::Cancel),
+            Some(Action::Submit)
+        );
+        assert_eq!(keymap.unbind(KeyCode::Esc), Some(Action::Cancel));
+        assert_eq!(keymap.get(&KeyCode::Esc.into()), None);
+
+        let save: KeySequence = "C-x C-s".parse().unwrap();
+        keymap.bind(save, Action::Submit);
+        assert!(keymap.is_prefix(&["C-x".parse().unwrap()]));
+        assert!(!keymap.is_prefix(&["C-s".parse().unwrap()]));
+    }
+
+    #[test]
+    fn keymap_lists_bindings_and_conflicts() {
+        let mut keymap = Keymap::editing();
+        let bindings = keymap.bindings();
+        assert_eq!(bindings.len(), keymap.bindings.len());
+        let enter = bindings
+            .iter()
+            .find(|binding| binding.keys == KeyCode::Enter.into())
+            .unwrap();
+        assert_eq!(enter.target, BindingTarget::Action(Action::Submit));
+        assert_eq!(enter.description, "submit the command");
+        assert!(keymap.conflicts().is_empty());
+
+        let quit: KeySequence = "C-x C-c".parse().unwrap();
+        let exit: KeySequence = "C-x C-c C-c".parse().unwrap();
+        keymap.bind(quit.clone(), Action::Cancel);
+        keymap.bind(exit.clone(), Action::Cancel);
+        keymap.bind(KeyCode::Tab, Action::Submit);
+        keymap.bind("C-x".parse::<KeySequence>().unwrap(), Action::Submit);
+        let conflicts: Vec<String> = keymap.conflicts().iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            conflicts,
+            [
+                "C-x starts C-x C-c, so it waits for the next key",
+                "C-x starts C-x C-c C-c, so it waits for the next key",
+                "C-x C-c starts C-x C-c C-c, so it waits for the next key",
+            ]
+        );
+    }
+
+    #[test]
+    fn keymap_reports_overrides() {
+        let mut keymap = Keymap::editing();
+        assert!(keymap.overrides(&Keymap::editing()).is_empty());
+
+        keymap.unbind(KeyCode::Esc);
+        keymap.bind(KeyCode::Tab, Action::Complete);
+        keymap.bind(KeyCode::Enter, Action::Cancel);
+        keymap.bind('j', Action::HistoryNext);
+        let overrides = keymap.overrides(&Keymap::editing());
+        assert_eq!(
+            overrides,
+            vec![
+                Override {
+                    keys: KeyCode::Enter.into(),
+                    default: Some(Action::Submit),
+                    action: Some(Action::Cancel),
+                },
+                Override {
+                    keys: KeyCode::Esc.into(),
+                    default: Some(Action::Cancel),
+                    action: None,
+                },
+                Override {
+                    keys: 'j'.into(),
+                    default: None,
+                    action: Some(Action::HistoryNext),
+                },
+            ]
+        );
+    }
+}
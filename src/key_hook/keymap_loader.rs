@@ -0,0 +1,353 @@
+///
+/// Load keymaps from the keymap tables of a TOML config file
+///
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::ops::Range;
+use std::path::Path;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::key_hook::bindings::Conflict;
+use crate::key_hook::key_combination::KeyParseError;
+use crate::key_hook::key_sequence::KeySequence;
+use crate::key_hook::keymap::{Action, Keymap};
+use crate::widgets::command_bar::InputMode;
+
+/// The action name that removes a default binding
+pub const UNBIND: &str = "none";
+
+/// The kinds of errors in a keymap config
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeymapErrorKind {
+    /// The config isn't valid TOML, or a table has the wrong type
+    Toml(String),
+    /// A keymap table isn't named after an input mode
+    UnknownMode(String),
+    /// A key couldn't be parsed
+    InvalidKey(String, KeyParseError),
+    /// A key is bound to an action that doesn't exist
+    UnknownAction(String),
+    /// A key is written twice in a table, like "Enter" and "Ret"
+    DuplicateKey(String, String),
+    /// A binding conflicts with another binding in its keymap
+    Conflict(Conflict),
+}
+
+/// An error in a keymap config
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeymapError {
+    /// What was wrong
+    pub kind: KeymapErrorKind,
+    /// The line number of the error, starting at 1
+    pub line: usize,
+    /// The text of the line with the error
+    pub text: String,
+}
+
+impl KeymapError {
+    /// Build an error for the span of text in config
+    fn new(kind: KeymapErrorKind, config: &str, span: Option<Range<usize>>) -> Self {
+        let start = span.map_or(0, |span| span.start.min(config.len()));
+        let line = config[..start].matches('\n').count() + 1;
+        KeymapError {
+            kind,
+            line,
+            text: String::from(config.lines().nth(line - 1).unwrap_or("").trim()),
+        }
+    }
+}
+
+impl fmt::Display for KeymapErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeymapErrorKind::Toml(message) => write!(f, "{}", message),
+            KeymapErrorKind::UnknownMode(mode) => write!(f, "unknown input mode {:?}", mode),
+            KeymapErrorKind::InvalidKey(key, e) => write!(f, "invalid key {:?}: {}", key, e),
+            KeymapErrorKind::UnknownAction(action) => write!(f, "unknown action {:?}", action),
+            KeymapErrorKind::DuplicateKey(key, first) => {
+                write!(f, "key {:?} is the same key as {:?}", key, first)
+            }
+            KeymapErrorKind::Conflict(conflict) => write!(f, "{}", conflict),
+        }
+    }
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}: {}", self.line, self.kind, self.text)
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+/// The parts of the config file the loader reads
+#[derive(Deserialize)]
+struct KeymapConfig {
+    #[serde(default)]
+    keymap: HashMap<Spanned<String>, HashMap<Spanned<String>, Spanned<String>>>,
+}
+
+/// The keymaps a CommandBar starts with
+pub fn default_keymaps() -> HashMap<InputMode, Keymap> {
+    HashMap::from([
+        (InputMode::Normal, Keymap::default()),
+        (InputMode::Editing, Keymap::editing()),
+    ])
+}
+
+/// The input mode a keymap table is named after
+fn input_mode(name: &str) -> Option<InputMode> {
+    match name {
+        "normal" => Some(InputMode::Normal),
+        "editing" => Some(InputMode::Editing),
+        _ => None,
+    }
+}
+
+/// The keys a conflict's keys get in the way of
+fn conflict_with(conflict: &Conflict) -> &KeySequence {
+    match conflict {
+        Conflict::Duplicate { keys, .. } => keys,
+        Conflict::Shadowed { by, .. } => by,
+    }
+}
+
+/// Parse the keymap tables in a TOML config
+///
+/// Bindings in a [keymap.normal] or [keymap.editing] table are added to the
+/// default keymaps, and a binding to "none" removes a default binding.
+/// Other tables and keys in the config are ignored.
+/// Binding the same key twice in a table is an error, and so is a binding
+/// that conflicts with another one when the default keymap didn't.
+///
+/// # Example
+///
+/// ```
+/// use tui_command_bar_widget::key_hook::keymap::Action;
+/// use tui_command_bar_widget::key_hook::keymap_loader::parse_keymaps;
+/// use tui_command_bar_widget::widgets::command_bar::{CommandBar, InputMode};
+///
+/// let config = r#"
+/// command-key = ":"
+///
+/// [keymap.editing]
+/// "C-j" = "submit"
+/// "C-x C-u" = "delete-word-backward"
+/// "Enter" = "none"
+/// "#;
+///
+/// let command_bar_widget = CommandBar {
+///     keymaps: parse_keymaps(config).unwrap(),
+///     ..Default::default()
+/// };
+/// let editing = &command_bar_widget.keymaps[&InputMode::Editing];
+/// assert_eq!(editing.get(&"C-j".parse().unwrap()), Some(Action::Submit));
+/// assert_eq!(editing.get(&"Enter".parse().unwrap()), None);
+///
+/// let error = parse_keymaps("[keymap.editing]\n\"C-j\" = \"explode\"\n").unwrap_err();
+/// assert_eq!(error.to_string(), r#"line 2: unknown action "explode": "C-j" = "explode""#);
+/// ```
+pub fn parse_keymaps(config: &str) -> Result<HashMap<InputMode, Keymap>, KeymapError> {
+    let parsed: KeymapConfig = toml::from_str(config).map_err(|e| {
+        KeymapError::new(
+            KeymapErrorKind::Toml(String::from(e.message())),
+            config,
+            e.span(),
+        )
+    })?;
+
+    let mut keymaps = default_keymaps();
+    // Sort the bindings so the first error in the file is reported
+    let mut tables: Vec<_> = parsed.keymap.into_iter().collect();
+    tables.sort_by_key(|(mode, _)| mode.span().start);
+    for (mode, bindings) in tables {
+        let input_mode = input_mode(mode.get_ref()).ok_or_else(|| {
+            KeymapError::new(
+                KeymapErrorKind::UnknownMode(mode.get_ref().clone()),
+                config,
+                Some(mode.span()),
+            )
+        })?;
+        let keymap = keymaps.entry(input_mode).or_default();
+        let default_conflicts = keymap.conflicts();
+
+        let mut bindings: Vec<_> = bindings.into_iter().collect();
+        bindings.sort_by_key(|(key, _)| key.span().start);
+        // Where each key sequence is written in the table
+        let mut written: HashMap<KeySequence, Spanned<String>> = HashMap::new();
+        for (key, action) in bindings {
+            let keys: KeySequence = key.get_ref().parse().map_err(|e| {
+                KeymapError::new(
+                    KeymapErrorKind::InvalidKey(key.get_ref().clone(), e),
+                    config,
+                    Some(key.span()),
+                )
+            })?;
+            if let Some(first) = written.get(&keys) {
+                return Err(KeymapError::new(
+                    KeymapErrorKind::DuplicateKey(key.get_ref().clone(), first.get_ref().clone()),
+                    config,
+                    Some(key.span()),
+                ));
+            }
+            written.insert(keys.clone(), key);
+            if action.get_ref() == UNBIND {
+                keymap.unbind(keys);
+                continue;
+            }
+            let action = action.get_ref().parse::<Action>().map_err(|e| {
+                KeymapError::new(
+                    KeymapErrorKind::UnknownAction(e.0),
+                    config,
+                    Some(action.span()),
+                )
+            })?;
+            keymap.bind(keys, action);
+        }
+
+        // Report the new conflict whose binding comes first in the table
+        let conflict = keymap
+            .conflicts()
+            .into_iter()
+            .filter(|conflict| !default_conflicts.contains(conflict))
+            .filter_map(|conflict| {
+                let span = [conflict.keys(), conflict_with(&conflict)]
+                    .into_iter()
+                    .filter_map(|keys| written.get(keys))
+                    .map(|key| key.span())
+                    .min_by_key(|span| span.start)?;
+                Some((conflict, span))
+            })
+            .min_by_key(|(_, span)| span.start);
+        if let Some((conflict, span)) = conflict {
+            return Err(KeymapError::new(
+                KeymapErrorKind::Conflict(conflict),
+                config,
+                Some(span),
+            ));
+        }
+    }
+    Ok(keymaps)
+}
+
+/// Load the keymap tables from a TOML config file
+/// Errors in the keymaps are returned as InvalidData errors wrapping a
+/// KeymapError.
+pub fn load_keymaps<P: AsRef<Path>>(path: P) -> io::Result<HashMap<InputMode, Keymap>> {
+    let config = fs::read_to_string(path)?;
+    parse_keymaps(&config).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use crossterm::event::KeyCode;
+
+    use crate::key_hook::bindings::Conflict;
+    use crate::key_hook::key_combination::KeyParseError;
+    use crate::key_hook::keymap::{Action, Keymap};
+    use crate::key_hook::keymap_loader::{
+        default_keymaps, load_keymaps, parse_keymaps, KeymapError, KeymapErrorKind,
+    };
+    use crate::widgets::command_bar::InputMode;
+
+    #[test]
+    fn keymap_loader_adds_to_the_defaults() {
+        let keymaps = parse_keymaps(
+            r#"
+            debug = true
+
+            [keymap.normal]
+            "g g" = "edit"
+
+            [keymap.editing]
+            "Esc" = "none"
+            "C-g" = "cancel"
+            "#,
+        )
+        .unwrap();
+
+        let normal = &keymaps[&InputMode::Normal];
+        assert_eq!(normal.get(&"g g".parse().unwrap()), Some(Action::Edit));
+        let editing = &keymaps[&InputMode::Editing];
+        assert_eq!(editing.get(&KeyCode::Esc.into()), None);
+        assert_eq!(editing.get(&"C-g".parse().unwrap()), Some(Action::Cancel));
+        assert_eq!(editing.bindings.len(), Keymap::editing().bindings.len());
+
+        assert_eq!(parse_keymaps("").unwrap(), default_keymaps());
+    }
+
+    #[test]
+    fn keymap_loader_reports_the_offending_line() {
+        let error = |config: &str| parse_keymaps(config).unwrap_err();
+
+        assert_eq!(
+            error("[keymap.insert]\n\"a\" = \"edit\"\n"),
+            KeymapError {
+                kind: KeymapErrorKind::UnknownMode(String::from("insert")),
+                line: 1,
+                text: String::from("[keymap.insert]"),
+            }
+        );
+        assert_eq!(
+            error("[keymap.editing]\n\"C-a\" = \"edit\"\n  \"X-a\" = \"cancel\"\n"),
+            KeymapError {
+                kind: KeymapErrorKind::InvalidKey(
+                    String::from("X-a"),
+                    KeyParseError::UnknownModifier(String::from("X"))
+                ),
+                line: 3,
+                text: String::from("\"X-a\" = \"cancel\""),
+            }
+        );
+        let toml_error = error("[keymap.editing]\n\"C-a\" = 3\n");
+        assert!(matches!(toml_error.kind, KeymapErrorKind::Toml(_)));
+        assert_eq!(toml_error.line, 2);
+    }
+
+    #[test]
+    fn keymap_loader_rejects_conflicts() {
+        let error = |config: &str| parse_keymaps(config).unwrap_err();
+
+        let duplicate = error("[keymap.editing]\n\"Enter\" = \"submit\"\n\"Ret\" = \"cancel\"\n");
+        assert_eq!(
+            duplicate.kind,
+            KeymapErrorKind::DuplicateKey(String::from("Ret"), String::from("Enter"))
+        );
+        assert_eq!(duplicate.line, 3);
+
+        let shadowed = error("[keymap.normal]\n\"g\" = \"edit\"\n\"g g\" = \"edit\"\n");
+        assert_eq!(
+            shadowed.to_string(),
+            "line 2: g starts g g, so it waits for the next key: \"g\" = \"edit\""
+        );
+        // Escape starts a sequence, so the default binding waits
+        let shadowed = error("[keymap.editing]\n\"C-g\" = \"cancel\"\n\"Esc Esc\" = \"cancel\"\n");
+        assert_eq!(
+            shadowed.kind,
+            KeymapErrorKind::Conflict(Conflict::Shadowed {
+                keys: KeyCode::Esc.into(),
+                by: "Esc Esc".parse().unwrap(),
+            })
+        );
+        assert_eq!(shadowed.line, 3);
+
+        // Removing the default binding first is fine
+        assert!(
+            parse_keymaps("[keymap.editing]\n\"Esc\" = \"none\"\n\"Esc Esc\" = \"cancel\"\n")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn keymap_loader_reads_files() {
+        let error = load_keymaps("config/does-not-exist.toml").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert!(load_keymaps("config/tui-command-bar-widget.toml").is_ok());
+    }
+}
@@ -6,3 +6,18 @@
 ///
 #[allow(clippy::module_inception)]
 pub mod key_hook;
+
+/// Key codes with modifiers, used to look up keys in a KeyDatabase
+pub mod key_combination;
+
+/// Sequences of keys, like g g or C-x C-s
+pub mod key_sequence;
+
+/// Key maps binding keys to CommandBar actions for each InputMode
+pub mod keymap;
+
+/// Loading keymaps from a TOML config file
+pub mod keymap_loader;
+
+/// Listing key bindings and the conflicts between them
+pub mod bindings;
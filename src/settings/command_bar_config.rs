@@ -0,0 +1,429 @@
+///
+/// CommandBarConfig holds the CommandBar settings from a config file
+///
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use config::{Config, ConfigError, Environment, File, FileFormat};
+use serde::Deserialize;
+use tui::style::Style;
+
+use crate::editing::paste::PastePolicy;
+use crate::editing::vi::EditingKeys;
+use crate::key_hook::key_hook::KeyDatabase;
+use crate::key_hook::key_sequence::KeySequence;
+use crate::key_hook::keymap::Keymap;
+use crate::key_hook::keymap_loader::parse_keymaps;
+use crate::settings::style_config::StyleConfig;
+use crate::widgets::command_bar::{CommandBar, InputMode};
+
+/// The styles table of the config file
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct StylesConfig {
+    normal: Option<StyleConfig>,
+    editing: Option<StyleConfig>,
+    error: Option<StyleConfig>,
+}
+
+/// The config file as it's read by the config crate
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct RawConfig {
+    debug: bool,
+    command_key: Option<String>,
+    title: Option<String>,
+    styles: StylesConfig,
+    history_file: Option<PathBuf>,
+    max_length: Option<usize>,
+    editing_keys: Option<String>,
+    paste_newlines: Option<String>,
+    keymap: Option<config::Value>,
+    locale_file: Option<String>,
+}
+
+/// Settings for a CommandBar, read from a TOML config file
+///
+/// Any setting can be overridden with an environment variable starting with
+/// APP_, for example APP_COMMAND_KEY or APP_MAX_LENGTH.
+/// Nested settings use two underscores, like APP_STYLES__EDITING__FG.
+/// The keymap tables are the exception: they're only read from the config
+/// file, since key names like C-g don't fit in a variable name.
+///
+/// # Example
+///
+/// ```
+/// use tui::style::{Color, Style};
+/// use tui_command_bar_widget::settings::command_bar_config::CommandBarConfig;
+/// use tui_command_bar_widget::widgets::command_bar::CommandBar;
+///
+/// let config = CommandBarConfig::parse(
+///     r#"
+///     command-key = "C-x :"
+///     title = "Run"
+///     max-length = 20
+///
+///     [styles.editing]
+///     fg = "light-green"
+///     "#,
+/// )
+/// .unwrap();
+///
+/// let mut command_bar_widget = CommandBar::default();
+/// config.apply(&mut command_bar_widget).unwrap();
+/// assert_eq!(command_bar_widget.command_key, Some("C-x :".parse().unwrap()));
+/// assert_eq!(command_bar_widget.title, "Run");
+/// assert_eq!(command_bar_widget.editing_style, Style::default().fg(Color::LightGreen));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandBarConfig {
+    /// Whether the application should run in debug mode
+    pub debug: bool,
+    /// Key that starts editing the command
+    pub command_key: Option<KeySequence>,
+    /// Title of the CommandBar
+    pub title: Option<String>,
+    /// Style of the CommandBar in Normal mode
+    pub normal_style: Option<Style>,
+    /// Style of the CommandBar in Editing mode
+    pub editing_style: Option<Style>,
+    /// Style of command errors
+    pub error_style: Option<Style>,
+    /// File the command history is loaded from and saved to
+    pub history_file: Option<PathBuf>,
+    /// The most graphemes that can be typed into the input
+    pub max_length: Option<usize>,
+    /// Whether the input is edited with readline or vi keys
+    pub editing_keys: Option<EditingKeys>,
+    /// What to do with newlines in pasted text
+    pub paste_newlines: Option<PastePolicy>,
+    /// Keymaps from the keymap tables, with the default bindings they
+    /// don't change
+    pub keymaps: Option<HashMap<InputMode, Keymap>>,
+    /// File with the translations to use, from locale-file
+    /// This is for the application, the CommandBar doesn't use it.
+    pub locale: Option<String>,
+}
+
+/// The environment variables that override the config file
+///
+/// The config file has dashes where variable names have underscores, so
+/// APP_MAX_LENGTH sets max-length and APP_STYLES__EDITING__FG sets fg in
+/// the styles.editing table.
+fn environment<I: IntoIterator<Item = (String, String)>>(variables: I) -> Environment {
+    let settings = variables
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let name = name.strip_prefix("APP_")?.to_lowercase();
+            let path: Vec<String> = name
+                .split("__")
+                .map(|part| part.replace('_', "-"))
+                .collect();
+            Some((path.join("."), value))
+        })
+        .collect();
+    Environment::default()
+        .try_parsing(true)
+        .source(Some(settings))
+}
+
+/// Convert an error from another crate to a ConfigError
+fn foreign<E: std::error::Error + Send + Sync + 'static>(e: E) -> ConfigError {
+    ConfigError::Foreign(Box::new(e))
+}
+
+/// Convert a style from the config file
+fn style(name: &str, style: Option<StyleConfig>) -> Result<Option<Style>, ConfigError> {
+    style
+        .map(|style| style.to_style())
+        .transpose()
+        .map_err(|e| ConfigError::Message(format!("invalid {} style: {}", name, e)))
+}
+
+impl CommandBarConfig {
+    /// Load the settings from a TOML config file and the environment
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let config = fs::read_to_string(path).map_err(foreign)?;
+        CommandBarConfig::from_sources(&config, environment(env::vars()))
+    }
+
+    /// Parse the settings from TOML config text and the environment
+    pub fn parse(config: &str) -> Result<Self, ConfigError> {
+        CommandBarConfig::from_sources(config, environment(env::vars()))
+    }
+
+    /// Read the settings from config text, overridden by environment
+    fn from_sources(config: &str, environment: Environment) -> Result<Self, ConfigError> {
+        let raw: RawConfig = Config::builder()
+            .add_source(File::from_str(config, FileFormat::Toml))
+            .add_source(environment)
+            .build()?
+            .try_deserialize()?;
+
+        let command_key = raw
+            .command_key
+            .map(|key| {
+                key.parse::<KeySequence>().map_err(|e| {
+                    ConfigError::Message(format!("invalid command-key {:?}: {}", key, e))
+                })
+            })
+            .transpose()?;
+        let editing_keys = raw
+            .editing_keys
+            .map(|keys| {
+                keys.parse::<EditingKeys>()
+                    .map_err(|e| ConfigError::Message(format!("invalid editing-keys: {}", e)))
+            })
+            .transpose()?;
+        let paste_newlines = raw
+            .paste_newlines
+            .map(|policy| {
+                policy
+                    .parse::<PastePolicy>()
+                    .map_err(|e| ConfigError::Message(format!("invalid paste-newlines: {}", e)))
+            })
+            .transpose()?;
+        // The keymap tables are parsed from the text so errors can give
+        // the line they're on
+        let keymaps = match raw.keymap {
+            Some(_) => Some(parse_keymaps(config).map_err(foreign)?),
+            None => None,
+        };
+        if let (Some(key), Some(normal)) = (
+            &command_key,
+            keymaps.as_ref().and_then(|k| k.get(&InputMode::Normal)),
+        ) {
+            let mut key_database = KeyDatabase::<CommandBar>::default();
+            key_database
+                .keys
+                .insert(key.clone(), Box::new(CommandBar::command_key_handler));
+            if let Some(conflict) = key_database.conflicts(normal).first() {
+                return Err(ConfigError::Message(format!(
+                    "command-key conflicts with the normal keymap: {}",
+                    conflict
+                )));
+            }
+        }
+
+        Ok(CommandBarConfig {
+            debug: raw.debug,
+            command_key,
+            title: raw.title,
+            normal_style: style("normal", raw.styles.normal)?,
+            editing_style: style("editing", raw.styles.editing)?,
+            error_style: style("error", raw.styles.error)?,
+            history_file: raw.history_file,
+            max_length: raw.max_length,
+            editing_keys,
+            paste_newlines,
+            keymaps,
+            locale: raw.locale_file,
+        })
+    }
+
+    /// Apply the settings to a CommandBar or a Popup
+    ///
+    /// The command key replaces the CommandBar's command key.
+    /// Settings that aren't in the config are left as they are.
+    /// Returns an error if the history file can't be read.
+    pub fn apply<W: AsMut<CommandBar>>(&self, widget: &mut W) -> io::Result<()> {
+        let command_bar = widget.as_mut();
+        if let Some(key) = &self.command_key {
+            command_bar.set_command_key(key.clone());
+        }
+        if let Some(title) = &self.title {
+            command_bar.title = title.clone();
+        }
+        if let Some(style) = self.normal_style {
+            command_bar.normal_style = style;
+        }
+        if let Some(style) = self.editing_style {
+            command_bar.editing_style = style;
+        }
+        if let Some(style) = self.error_style {
+            command_bar.error_style = style;
+        }
+        if self.max_length.is_some() {
+            command_bar.max_length = self.max_length;
+        }
+        if let Some(editing_keys) = self.editing_keys {
+            command_bar.editing_keys = editing_keys;
+        }
+        if let Some(policy) = self.paste_newlines {
+            command_bar.paste_policy = policy;
+        }
+        if let Some(keymaps) = &self.keymaps {
+            command_bar.keymaps = keymaps.clone();
+        }
+        if let Some(path) = &self.history_file {
+            command_bar.history.load(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use config::{ConfigError, Environment};
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+    use tui::style::{Color, Modifier, Style};
+
+    use crate::editing::paste::PastePolicy;
+    use crate::editing::vi::EditingKeys;
+    use crate::key_hook::key_hook::{HandlerOutcome, KeyHook};
+    use crate::key_hook::keymap::Action;
+    use crate::settings::command_bar_config::CommandBarConfig;
+    use crate::widgets::command_bar::{CommandBar, EventHandlerResult, InputMode};
+    use crate::widgets::popup::Popup;
+
+    /// An environment with only the given variables set
+    fn environment(variables: &[(&str, &str)]) -> Environment {
+        let variables = variables
+            .iter()
+            .map(|(k, v)| (String::from(*k), String::from(*v)));
+        super::environment(variables)
+    }
+
+    #[test]
+    fn command_bar_config_reads_the_config_file() {
+        let config = fs::read_to_string("config/tui-command-bar-widget.toml").unwrap();
+        let config = CommandBarConfig::from_sources(&config, environment(&[])).unwrap();
+        assert!(config.debug);
+        assert_eq!(config.command_key, Some(':'.into()));
+        assert_eq!(config.locale, Some(String::from("i18n.toml")));
+        let keymaps = config.keymaps.unwrap();
+        let cancel = keymaps[&InputMode::Editing].get(&"C-g".parse().unwrap());
+        assert_eq!(cancel, Some(Action::Cancel));
+    }
+
+    #[test]
+    fn command_bar_config_environment_overrides() {
+        let config = CommandBarConfig::from_sources(
+            "command-key = \":\"\nmax-length = 10\n",
+            environment(&[
+                ("APP_COMMAND_KEY", "C-p"),
+                ("APP_MAX_LENGTH", "4"),
+                ("APP_STYLES__ERROR__FG", "magenta"),
+                ("APP_TITLE", "Run"),
+                ("APP_EDITING_KEYS", "vi"),
+                ("APP_PASTE_NEWLINES", "reject"),
+                ("APP_LOCALE_FILE", "fr.toml"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.command_key, Some("C-p".parse().unwrap()));
+        assert_eq!(config.max_length, Some(4));
+        assert_eq!(
+            config.error_style,
+            Some(Style::default().fg(Color::Magenta))
+        );
+        assert_eq!(config.title, Some(String::from("Run")));
+        assert_eq!(config.editing_keys, Some(EditingKeys::Vi));
+        assert_eq!(config.paste_newlines, Some(PastePolicy::Reject));
+        assert_eq!(config.locale, Some(String::from("fr.toml")));
+        assert_eq!(config.keymaps, None);
+    }
+
+    #[test]
+    fn command_bar_config_errors() {
+        let message = |config: &str| match CommandBarConfig::from_sources(config, environment(&[]))
+        {
+            Err(ConfigError::Message(message)) => message,
+            other => panic!("expected a message, got {:?}", other),
+        };
+        assert_eq!(
+            message("command-key = \"X-p\""),
+            "invalid command-key \"X-p\": unknown key modifier: X"
+        );
+        assert_eq!(
+            message("[styles.normal]\nfg = \"mauve\""),
+            "invalid normal style: unknown color: mauve"
+        );
+        assert_eq!(
+            message("paste-newlines = \"keep\""),
+            "invalid paste-newlines: unknown paste policy: keep"
+        );
+
+        let error = CommandBarConfig::from_sources(
+            "[keymap.editing]\n\"C-p\" = \"explode\"",
+            environment(&[]),
+        );
+        assert!(matches!(error, Err(ConfigError::Foreign(_))));
+        assert_eq!(
+            message("command-key = \"g\"\n[keymap.normal]\n\"g g\" = \"edit\""),
+            "command-key conflicts with the normal keymap: g starts g g, so it waits for the next key"
+        );
+    }
+
+    #[test]
+    fn command_bar_config_applies_to_a_popup() {
+        let config = CommandBarConfig::from_sources(
+            r#"
+            command-key = "p"
+            max-length = 2
+
+            [styles.normal]
+            bg = "blue"
+            modifiers = ["bold"]
+            "#,
+            environment(&[]),
+        )
+        .unwrap();
+        let mut popup = Popup::default();
+        popup.command_bar.set_command_key(':');
+        config.apply(&mut popup).unwrap();
+
+        let command_bar = &popup.command_bar;
+        assert_eq!(command_bar.command_key, Some('p'.into()));
+        assert_eq!(command_bar.key_database.keys.len(), 1);
+        assert_eq!(
+            command_bar.bindings(InputMode::Normal)[0].description,
+            "start editing the command"
+        );
+        assert_eq!(
+            command_bar.normal_style,
+            Style::default()
+                .bg(Color::Blue)
+                .add_modifier(Modifier::BOLD)
+        );
+
+        let key = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        assert_eq!(popup.handle_key_event(key('p')), EventHandlerResult::Ok);
+        for c in "abc".chars() {
+            popup.handle_key_event(key(c));
+        }
+        assert_eq!(popup.command_bar.input, "ab");
+    }
+
+    #[test]
+    fn command_bar_config_keeps_other_registered_keys() {
+        let config =
+            CommandBarConfig::from_sources("command-key = \"p\"", environment(&[])).unwrap();
+        let mut command_bar_widget = CommandBar::default();
+        command_bar_widget.set_command_key(':');
+        command_bar_widget.register_key('q', |_: &mut CommandBar, _| HandlerOutcome::Handled);
+        config.apply(&mut command_bar_widget).unwrap();
+
+        assert_eq!(command_bar_widget.command_key, Some('p'.into()));
+        let keys = &command_bar_widget.key_database.keys;
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains_key(&'q'.into()));
+        assert!(!keys.contains_key(&':'.into()));
+
+        let key = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        assert_eq!(
+            command_bar_widget.handle_key_event(key('q')),
+            EventHandlerResult::Ok
+        );
+        assert_eq!(command_bar_widget.input_mode, InputMode::Normal);
+        assert_eq!(
+            command_bar_widget.handle_key_event(key(':')),
+            EventHandlerResult::Unhandled(key(':'))
+        );
+    }
+}
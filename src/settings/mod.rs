@@ -0,0 +1,8 @@
+///
+/// settings loads CommandBar settings from a TOML config file, with
+/// overrides from the environment
+///
+pub mod command_bar_config;
+
+/// Text styles written in the config file
+pub mod style_config;
@@ -0,0 +1,177 @@
+///
+/// Styles written in a config file
+///
+use std::fmt;
+
+use serde::Deserialize;
+use tui::style::{Color, Modifier, Style};
+
+/// A style as it's written in the config file
+///
+/// Colors are names like "red" or "light-blue", "#rrggbb" or a number from
+/// 0 to 255. Modifiers are names like "bold" or "underlined".
+///
+/// # Example
+///
+/// ```
+/// use tui::style::{Color, Modifier, Style};
+/// use tui_command_bar_widget::settings::style_config::StyleConfig;
+///
+/// let config = StyleConfig {
+///     fg: Some(String::from("light-blue")),
+///     bg: Some(String::from("#202020")),
+///     modifiers: vec![String::from("bold")],
+/// };
+/// assert_eq!(
+///     config.to_style().unwrap(),
+///     Style::default()
+///         .fg(Color::LightBlue)
+///         .bg(Color::Rgb(32, 32, 32))
+///         .add_modifier(Modifier::BOLD)
+/// );
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StyleConfig {
+    /// The foreground color
+    pub fg: Option<String>,
+    /// The background color
+    pub bg: Option<String>,
+    /// Modifiers added to the text
+    pub modifiers: Vec<String>,
+}
+
+/// Errors converting a StyleConfig to a Style
+#[derive(Clone, Debug, PartialEq)]
+pub enum StyleError {
+    /// The color isn't a name, #rrggbb or a number from 0 to 255
+    UnknownColor(String),
+    /// The modifier name isn't known
+    UnknownModifier(String),
+}
+
+impl fmt::Display for StyleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StyleError::UnknownColor(c) => write!(f, "unknown color: {}", c),
+            StyleError::UnknownModifier(m) => write!(f, "unknown style modifier: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for StyleError {}
+
+/// Color names and their colors
+const COLOR_NAMES: [(&str, Color); 17] = [
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("dark-gray", Color::DarkGray),
+    ("light-red", Color::LightRed),
+    ("light-green", Color::LightGreen),
+    ("light-yellow", Color::LightYellow),
+    ("light-blue", Color::LightBlue),
+    ("light-magenta", Color::LightMagenta),
+    ("light-cyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+/// Modifier names and their modifiers
+const MODIFIER_NAMES: [(&str, Modifier); 9] = [
+    ("bold", Modifier::BOLD),
+    ("dim", Modifier::DIM),
+    ("italic", Modifier::ITALIC),
+    ("underlined", Modifier::UNDERLINED),
+    ("slow-blink", Modifier::SLOW_BLINK),
+    ("rapid-blink", Modifier::RAPID_BLINK),
+    ("reversed", Modifier::REVERSED),
+    ("hidden", Modifier::HIDDEN),
+    ("crossed-out", Modifier::CROSSED_OUT),
+];
+
+/// Parse a color name, #rrggbb or a number from 0 to 255
+pub fn parse_color(s: &str) -> Result<Color, StyleError> {
+    let unknown = || StyleError::UnknownColor(String::from(s));
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 {
+            return Err(unknown());
+        }
+        let rgb = u32::from_str_radix(hex, 16).map_err(|_| unknown())?;
+        return Ok(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+    }
+    if let Ok(index) = s.parse::<u8>() {
+        return Ok(Color::Indexed(index));
+    }
+    let name = s.to_lowercase().replace('_', "-");
+    COLOR_NAMES
+        .iter()
+        .find(|(color_name, _)| *color_name == name)
+        .map(|(_, color)| *color)
+        .ok_or_else(unknown)
+}
+
+/// Parse a modifier name
+pub fn parse_modifier(s: &str) -> Result<Modifier, StyleError> {
+    let name = s.to_lowercase().replace('_', "-");
+    MODIFIER_NAMES
+        .iter()
+        .find(|(modifier_name, _)| *modifier_name == name)
+        .map(|(_, modifier)| *modifier)
+        .ok_or_else(|| StyleError::UnknownModifier(String::from(s)))
+}
+
+impl StyleConfig {
+    /// Convert to a Style
+    pub fn to_style(&self) -> Result<Style, StyleError> {
+        let mut style = Style::default();
+        if let Some(fg) = &self.fg {
+            style = style.fg(parse_color(fg)?);
+        }
+        if let Some(bg) = &self.bg {
+            style = style.bg(parse_color(bg)?);
+        }
+        for modifier in &self.modifiers {
+            style = style.add_modifier(parse_modifier(modifier)?);
+        }
+        Ok(style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tui::style::{Color, Modifier};
+
+    use crate::settings::style_config::{parse_color, parse_modifier, StyleError};
+
+    #[test]
+    fn style_config_parses_colors() {
+        assert_eq!(parse_color("Yellow"), Ok(Color::Yellow));
+        assert_eq!(parse_color("dark_gray"), Ok(Color::DarkGray));
+        assert_eq!(parse_color("#ff8000"), Ok(Color::Rgb(255, 128, 0)));
+        assert_eq!(parse_color("208"), Ok(Color::Indexed(208)));
+        assert_eq!(
+            parse_color("#ff80"),
+            Err(StyleError::UnknownColor(String::from("#ff80")))
+        );
+        assert_eq!(
+            parse_color("mauve"),
+            Err(StyleError::UnknownColor(String::from("mauve")))
+        );
+    }
+
+    #[test]
+    fn style_config_parses_modifiers() {
+        assert_eq!(parse_modifier("crossed-out"), Ok(Modifier::CROSSED_OUT));
+        assert_eq!(parse_modifier("BOLD"), Ok(Modifier::BOLD));
+        assert_eq!(
+            parse_modifier("sparkly"),
+            Err(StyleError::UnknownModifier(String::from("sparkly")))
+        );
+    }
+}
@@ -7,32 +7,50 @@
 /// when you build the object.
 use log::{debug, error};
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
 use std::sync::{mpsc, mpsc::SendError};
+use std::time::{Duration, Instant};
 
 // This adds a width() method to String
-use ::crossterm::event::{Event, KeyCode};
+use ::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
 use tui::{
     buffer::Buffer,
     layout::Rect,
-    style::{Color, Style},
-    text::Text,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
     widgets::{Block, Borders, Paragraph, Widget},
 };
 
-use crate::key_hook::key_hook::{KeyDatabase, KeyHook};
-
-use mockall_double::double;
-
-#[double]
-pub use crate::crossterm::event;
-//pub use crate::crossterm::event;
-
-//use crate::event;
+use crate::command::registry::{CommandError, CommandOutcome, CommandRegistry};
+use crate::completion::completer::{
+    apply_candidate, common_prefix, is_valid_span, Completer, CompletionState,
+};
+use crate::editing::kill_ring::KillDirection;
+use crate::editing::paste::PastePolicy;
+use crate::editing::registers::{Registers, Yanked};
+use crate::editing::undo::{Snapshot, UndoHistory};
+use crate::editing::vi::{
+    EditingKeys, InsertAt, LastChange, Motion, Operator, ViCommand, ViMode, ViState,
+};
+use crate::event_source::crossterm_source::CrosstermEventSource;
+use crate::event_source::event_source::EventSource;
+use crate::history::history::{History, ReverseSearch};
+use crate::key_hook::bindings::{Binding, Conflict, Override};
+use crate::key_hook::key_combination::KeyCombination;
+use crate::key_hook::key_hook::{HandlerOutcome, KeyDatabase, KeyHook};
+use crate::key_hook::key_sequence::KeySequence;
+use crate::key_hook::keymap::{Action, Keymap};
+use crate::key_hook::keymap_loader::default_keymaps;
+use crate::widgets::completion_menu::CompletionMenu;
 
 /// A CommandBar has an InputMode that indicates it's editing state
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum InputMode {
     /// Normal means the CommandBar is not being edited
     /// Depending on the widget type, it may not be visible or it may be unfocused
@@ -57,46 +75,122 @@ pub enum InputMode {
 /// let chunks = Layout::default();
 ///
 /// let mut command_bar_widget = CommandBar::default();
-/// let mut closure = |cb: &mut CommandBar, key| { cb.command_key_handler(key) };
-/// command_bar_widget.register_key(':', &mut closure);
+/// command_bar_widget.set_command_key(':');
 /// frame.render_widget(command_bar_widget, area);
 ///
 /// ```
-#[derive(Clone)]
-pub struct CommandBar<'a> {
-    /// Command key to activate the CommandBar
-    pub command_key: Option<char>,
+pub struct CommandBar {
+    /// Command key to activate the CommandBar, bound with set_command_key
+    pub command_key: Option<KeySequence>,
+    /// Title shown in the border of the CommandBar
+    pub title: String,
     /// Current value of the input box
     pub input: String,
+    /// The most graphemes that can be typed into the input, if limited
+    pub max_length: Option<usize>,
+    /// Cursor position in the input box, counted in graphemes
+    /// A cursor equal to the number of graphemes is at the end of the input
+    pub cursor: usize,
     /// Current input mode
     pub input_mode: InputMode,
     /// History of recorded messages
+    /// Every submitted line is kept here, history only keeps the entries
+    /// its max_len and duplicate policy allow.
     pub messages: Vec<String>,
+    /// Command history browsed with the Up and Down keys
+    pub history: History,
+    /// The kill ring and vi registers, which can be shared with other
+    /// CommandBars so text killed in one can be yanked in another
+    pub registers: Rc<RefCell<Registers>>,
+    /// The text inserted by the last yank, which Alt-Y replaces with an
+    /// older kill
+    pub yanked: Option<Yanked>,
+    /// Changes to the input, undone with Ctrl-_ or Ctrl-Z
+    pub undo_history: UndoHistory,
+    /// Whether the input is edited with readline or vi keys
+    pub editing_keys: EditingKeys,
+    /// What to do with newlines in pasted text
+    pub paste_policy: PastePolicy,
+    /// The vi sub-mode and pending keys, when editing with vi keys
+    pub vi: ViState,
+    /// Reverse incremental history search, started with Ctrl-R
+    pub search: Option<ReverseSearch>,
+    /// Use fuzzy matching in the history search
+    pub fuzzy_search: bool,
+    /// Completer queried when Tab is pressed
+    pub completer: Option<Rc<dyn Completer>>,
+    /// Candidates being cycled through with Tab and Shift-Tab
+    pub completion: Option<CompletionState>,
+    /// Commands that submitted input is parsed and dispatched to
+    pub commands: Option<CommandRegistry>,
+    /// Result of the last command dispatched to the commands, shown in the
+    /// title of the CommandBar
+    pub command_result: Option<Result<CommandOutcome, CommandError>>,
     /// channel to use for sending messages
     pub tx_channel: Option<mpsc::Sender<String>>,
-    /// Keep track of width to limit the text in the command bar
+    /// Width of the text in the command bar, updated when it's rendered
     pub width: u16,
+    /// Index of the first visible grapheme when the input is wider than
+    /// the command bar
+    pub scroll_offset: usize,
+    /// Show < and > at the edges of the command bar when the input is
+    /// scrolled out of view
+    pub overflow_indicators: bool,
+    /// Style of the CommandBar in Normal mode
+    pub normal_style: Style,
+    /// Style of the CommandBar in Editing mode
+    pub editing_style: Style,
+    /// Style of command errors, and of the input they refer to
+    pub error_style: Style,
     /// The key database to store key actions
-    pub key_database: KeyDatabase<'a, CommandBar<'a>>,
+    pub key_database: KeyDatabase<CommandBar>,
+    /// Actions bound to keys in each InputMode
+    /// Handlers in the key database take priority over actions in Normal
+    /// mode.
+    pub keymaps: HashMap<InputMode, Keymap>,
 }
 
-impl<'a> Default for CommandBar<'a> {
-    fn default() -> CommandBar<'a> {
+impl Default for CommandBar {
+    fn default() -> CommandBar {
         CommandBar {
             command_key: None,
+            title: String::from("Command"),
             input: String::new(),
+            max_length: None,
+            cursor: 0,
             input_mode: InputMode::Normal,
             messages: Vec::new(),
+            history: History::default(),
+            registers: Rc::new(RefCell::new(Registers::default())),
+            yanked: None,
+            undo_history: UndoHistory::default(),
+            editing_keys: EditingKeys::Readline,
+            paste_policy: PastePolicy::default(),
+            vi: ViState::default(),
+            search: None,
+            fuzzy_search: false,
+            completer: None,
+            completion: None,
+            commands: None,
+            command_result: None,
             tx_channel: None,
             width: 0,
+            scroll_offset: 0,
+            overflow_indicators: false,
+            normal_style: Style::default(),
+            editing_style: Style::default().fg(Color::Yellow),
+            error_style: Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::UNDERLINED),
             key_database: KeyDatabase::default(),
+            keymaps: default_keymaps(),
         }
     }
 }
 
 /// The CommandBar event handler handles UI events and returns a result
 /// depending on how the event was processed
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum EventHandlerResult {
     /// A result of Ok indicates the event was processed by the CommandBar
     Ok,
@@ -108,25 +202,56 @@ pub enum EventHandlerResult {
     Unhandled(Event),
 }
 
-impl<'a> KeyHook<'a, CommandBar<'a>> for CommandBar<'a> {
-    fn register_key(&mut self, key: char, f: &'a dyn Fn(&mut Self, char)) {
-        self.command_key = Some(key);
-        self.key_database.keys.insert(key, f);
+impl KeyHook<CommandBar> for CommandBar {
+    fn register_key<K, F>(&mut self, key: K, f: F)
+    where
+        K: Into<KeySequence>,
+        F: FnMut(&mut CommandBar, KeyEvent) -> HandlerOutcome + 'static,
+    {
+        let key = key.into();
+        self.key_database.descriptions.remove(&key);
+        self.key_database.keys.insert(key, Box::new(f));
     }
 
-    fn unregister_key(&mut self, key: char) {
+    fn unregister_key<K: Into<KeySequence>>(&mut self, key: K) {
+        let key = key.into();
         self.key_database.keys.remove(&key);
+        self.key_database.descriptions.remove(&key);
 
         // Unset the command key if it matches
-        if let Some(command_key) = self.command_key {
-            if command_key == key {
-                self.command_key = None;
-            }
+        if self.command_key.as_ref() == Some(&key) {
+            self.command_key = None;
+        }
+    }
+}
+
+/// Whether key types its character, which chords with Ctrl or Alt don't
+fn is_typed(key: KeyEvent) -> bool {
+    !key.modifiers
+        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+}
+
+/// The kinds of graphemes vi word motions move over
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GraphemeClass {
+    Space,
+    Word,
+    Punctuation,
+}
+
+impl GraphemeClass {
+    fn of(grapheme: &str) -> GraphemeClass {
+        if grapheme.chars().all(char::is_whitespace) {
+            GraphemeClass::Space
+        } else if grapheme.chars().any(|c| c.is_alphanumeric() || c == '_') {
+            GraphemeClass::Word
+        } else {
+            GraphemeClass::Punctuation
         }
     }
 }
 
-impl<'a> CommandBar<'a> {
+impl CommandBar {
     /// Build a default CommandBar with a send channel
     ///
     /// # Example
@@ -154,9 +279,25 @@ impl<'a> CommandBar<'a> {
     }
 
     /// Commit changes in the command bar and close the command bar
+    ///
+    /// If commands are registered the input is dispatched to them, and the
+    /// result is kept in command_result.
     pub fn submit(&mut self) -> Result<(), SendError<String>> {
         let msg: String = self.input.drain(..).collect();
+        self.cursor = 0;
+        self.scroll_offset = 0;
+        self.completion = None;
+        self.undo_history.clear();
+        self.yanked = None;
         self.messages.push(msg.clone());
+        if self.history.push(&msg) {
+            if let Err(e) = self.history.append(&msg) {
+                error!("Error saving history: {}", e);
+            }
+        }
+        if !msg.trim().is_empty() {
+            self.command_result = self.commands.as_ref().map(|c| c.dispatch(&msg));
+        }
         match &self.tx_channel {
             Some(tx) => tx.send(msg),
             None => Ok(()),
@@ -168,343 +309,1673 @@ impl<'a> CommandBar<'a> {
     pub fn normal(&mut self) {
         debug!("Exiting editing mode");
         self.input_mode = InputMode::Normal;
+        self.history.reset();
+        self.undo_history.clear();
+        self.yanked = None;
+        self.search = None;
+        self.completion = None;
     }
 
-    /// Handle the special command key
-    pub fn command_key_handler(&mut self, key: char) {
-        debug!("Command key pressed: {:?}", key);
+    /// Change the input mode to Editing
+    pub fn edit(&mut self) {
         if let InputMode::Normal = self.input_mode {
+            debug!("Entering editing mode");
             self.input_mode = InputMode::Editing;
+            self.command_result = None;
+            // Vi editing starts out inserting, like a shell in vi mode
+            self.vi.set_mode(ViMode::Insert, self.cursor);
         }
     }
 
-    /// Handle an event
-    /// If the widget is not registered to handle the event, pass it to the parent
-    pub fn handle_event(&mut self) -> EventHandlerResult {
-        #[allow(unused_assignments)]
-        let mut handled = false;
+    /// Handle the special command key
+    pub fn command_key_handler(&mut self, key: KeyEvent) -> HandlerOutcome {
+        debug!("Command key pressed: {}", KeyCombination::from(key));
+        self.edit();
+        HandlerOutcome::Handled
+    }
 
-        let res = event::read();
-        let event = match res {
-            Ok(e) => e,
-            Err(e) => {
-                error!("Event read error: {}", e);
-                return EventHandlerResult::Err;
-            }
-        };
-        match event {
-            Event::Key(key) => {
-                // TODO: Match against KeyDatabase
-                //       Maybe only match against KeyDatabase
-                match self.input_mode {
-                    InputMode::Normal => match key.code {
-                        KeyCode::Char(k) => {
-                            // TODO: This needs to be refactored, there are a lot
-                            // of issues around clean API design here that need to
-                            // be better thought out
-                            if self.key_database.keys.contains_key(&k) {
-                                let value_option = self.key_database.keys.get(&k);
-                                if let Some(f) = value_option {
-                                    (*f)(self, k);
-                                }
-                            }
-                            match self.command_key {
-                                // No command key is registered, do nothing
-                                None => {
-                                    handled = false;
-                                }
-                                // A command key is registered, see if it matches
-                                Some(c) => {
-                                    if c == k {
-                                        //self.input_mode = InputMode::Editing;
-                                        handled = true;
-                                    } else {
-                                        handled = false;
-                                    }
-                                }
-                            }
-                        }
-                        _ => {
-                            handled = false;
-                        }
-                    },
-                    InputMode::Editing => match key.code {
-                        KeyCode::Enter => {
-                            // Entering leaves edit mode and commits the text
-                            match self.submit() {
-                                Ok(_) => (),
-                                Err(e) => {
-                                    error!("Send error on message: {}", e);
-                                }
-                            }
-                            self.normal();
-                            handled = true;
-                        }
-                        KeyCode::Char(c) => {
-                            if self.input.width() < self.width.into() {
-                                self.input.push(c);
-                            } else {
-                                debug!(
-                                    "Didn't input data, input too small: {}, {}",
-                                    self.input.width(),
-                                    self.width
-                                );
-                            }
-                            handled = true;
-                        }
-                        KeyCode::Backspace => {
-                            self.input.pop();
-                            handled = true;
-                        }
-                        KeyCode::Esc => {
-                            self.normal();
-                            handled = true;
-                        }
-                        _ => {
-                            handled = false;
-                        }
-                    },
-                }
-            }
-            Event::Resize(w, h) => {
-                debug!("Resize event: {:?}, {:?}", w, h);
-                handled = false;
-            }
-            Event::Mouse(e) => {
-                debug!("Mouse event: {:?}", e);
-                handled = false;
-            }
-        };
-        if handled {
-            EventHandlerResult::Ok
-        } else {
-            EventHandlerResult::Unhandled(event)
+    /// Bind key to command_key_handler and make it the command key
+    /// The old command key is unbound, other registered keys are kept.
+    pub fn set_command_key<K: Into<KeySequence>>(&mut self, key: K) {
+        let key = key.into();
+        if let Some(old_key) = self.command_key.take() {
+            self.unregister_key(old_key);
         }
+        self.register_key(key.clone(), CommandBar::command_key_handler);
+        self.key_database
+            .describe(key.clone(), Action::Edit.description());
+        self.command_key = Some(key);
     }
-}
 
-impl<'a> Widget for CommandBar<'a> {
-    fn render(self, _area: Rect, _buf: &mut Buffer) {}
-}
+    /// The keymap for mode, which is created if it doesn't exist yet
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crossterm::event::{KeyCode, KeyModifiers};
+    /// use tui_command_bar_widget::key_hook::key_combination::KeyCombination;
+    /// use tui_command_bar_widget::key_hook::keymap::Action;
+    /// use tui_command_bar_widget::widgets::command_bar::{CommandBar, InputMode};
+    ///
+    /// let mut command_bar_widget = CommandBar::default();
+    /// let keymap = command_bar_widget.keymap_mut(InputMode::Editing);
+    /// keymap.bind(KeyCombination::new(KeyCode::Char('g'), KeyModifiers::CONTROL), Action::Cancel);
+    /// keymap.unbind(KeyCode::Esc);
+    /// ```
+    pub fn keymap_mut(&mut self, mode: InputMode) -> &mut Keymap {
+        self.keymaps.entry(mode).or_default()
+    }
 
-impl<'a> Widget for &'a CommandBar<'a> {
-    fn render(self, _area: Rect, _buf: &mut Buffer) {}
-}
+    /// The keys bound in mode and what they do, for a help screen
+    /// Handlers in the KeyDatabase are only listed in Normal mode, since
+    /// that's the only mode they run in.
+    pub fn bindings(&self, mode: InputMode) -> Vec<Binding> {
+        let empty = Keymap::default();
+        let keymap = self.keymaps.get(&mode).unwrap_or(&empty);
+        match mode {
+            InputMode::Normal => self.key_database.bindings(keymap),
+            InputMode::Editing => keymap.bindings(),
+        }
+    }
 
-impl<'a> Widget for &mut CommandBar<'a> {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        debug!("area width: {:?}, height: {:?}", area.width, area.height);
-        debug!(
-            "buf width: {:?}, height: {:?}",
-            buf.area.width, buf.area.height
-        );
+    /// The bindings in mode that get in each other's way
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tui_command_bar_widget::key_hook::key_sequence::KeySequence;
+    /// use tui_command_bar_widget::key_hook::keymap::Action;
+    /// use tui_command_bar_widget::widgets::command_bar::{CommandBar, InputMode};
+    ///
+    /// let mut command_bar_widget = CommandBar::default();
+    /// assert!(command_bar_widget.conflicts(InputMode::Editing).is_empty());
+    ///
+    /// let keys: KeySequence = "Esc Esc".parse().unwrap();
+    /// command_bar_widget
+    ///     .keymap_mut(InputMode::Editing)
+    ///     .bind(keys, Action::Submit);
+    /// let conflicts = command_bar_widget.conflicts(InputMode::Editing);
+    /// assert_eq!(conflicts[0].to_string(), "Esc starts Esc Esc, so it waits for the next key");
+    /// ```
+    pub fn conflicts(&self, mode: InputMode) -> Vec<Conflict> {
+        let empty = Keymap::default();
+        let keymap = self.keymaps.get(&mode).unwrap_or(&empty);
+        match mode {
+            InputMode::Normal => self.key_database.conflicts(keymap),
+            InputMode::Editing => keymap.conflicts(),
+        }
+    }
 
-        // The render code in Paragraph selects the buffer cells based on the
-        // area rectangle.
-        // We set this to limit what the user can type in instead of relying on
-        // the LineTruncator code.
-        // Future versions could maybe scroll the text left
-        self.width = area.width - 2;
+    /// The bindings in mode that are different from the default keymap
+    pub fn overrides(&self, mode: InputMode) -> Vec<Override> {
+        let empty = Keymap::default();
+        let keymap = self.keymaps.get(&mode).unwrap_or(&empty);
+        let defaults = default_keymaps().remove(&mode).unwrap_or_default();
+        keymap.overrides(&defaults)
+    }
 
-        let input = Paragraph::new(Text::from(self.input.as_ref()))
-            .style(match self.input_mode {
-                InputMode::Normal => Style::default(),
-                InputMode::Editing => Style::default().fg(Color::Yellow),
-            })
-            .block(Block::default().borders(Borders::ALL).title("Command"));
+    /// Number of graphemes in the input
+    fn grapheme_count(&self) -> usize {
+        self.input.graphemes(true).count()
+    }
 
-        input.render(area, buf);
+    /// Byte offset in the input of the grapheme at index
+    /// Indexes past the last grapheme return the length of the input
+    fn byte_offset(&self, index: usize) -> usize {
+        self.input
+            .grapheme_indices(true)
+            .nth(index)
+            .map_or(self.input.len(), |(i, _)| i)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use log::debug;
+    /// The input may be changed directly, so keep the cursor inside it
+    fn clamp_cursor(&mut self) {
+        self.cursor = self.cursor.min(self.grapheme_count());
+    }
 
-    use tui::{
-        backend::TestBackend,
-        buffer::Buffer,
-        layout::Rect,
-        style::{Color, Style},
-        Terminal,
-    };
+    /// Move the cursor one grapheme to the left
+    pub fn move_cursor_left(&mut self) {
+        self.clamp_cursor();
+        self.cursor = self.cursor.saturating_sub(1);
+    }
 
-    use ::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
-    use ::crossterm::event::{MouseEvent, MouseEventKind};
+    /// Move the cursor one grapheme to the right
+    pub fn move_cursor_right(&mut self) {
+        self.clamp_cursor();
+        if self.cursor < self.grapheme_count() {
+            self.cursor += 1;
+        }
+    }
 
-    use crate::key_hook::key_hook::KeyHook;
-    use crate::widgets::command_bar::{CommandBar, EventHandlerResult, InputMode};
+    /// Move the cursor to the start of the input
+    pub fn move_cursor_home(&mut self) {
+        self.cursor = 0;
+    }
 
-    use std::sync::Mutex;
+    /// Move the cursor to the end of the input
+    pub fn move_cursor_end(&mut self) {
+        self.cursor = self.grapheme_count();
+    }
 
-    use mockall::*;
-    use mockall_double::double;
+    /// Insert a character at the cursor and move the cursor past it
+    /// Nothing is inserted once the input is max_length graphemes long.
+    pub fn insert_char(&mut self, c: char) {
+        self.clamp_cursor();
+        let before = self.grapheme_count();
+        if self.max_length.is_some_and(|max| before >= max) {
+            debug!("Input is at the maximum length");
+            return;
+        }
+        let offset = self.byte_offset(self.cursor);
+        self.input.insert(offset, c);
+        // A combining character joins the previous grapheme instead of
+        // adding a new one
+        self.cursor = (self.cursor + self.grapheme_count()).saturating_sub(before);
+    }
 
-    use std::sync::mpsc;
+    /// Delete the grapheme before the cursor
+    pub fn delete_backward(&mut self) {
+        self.clamp_cursor();
+        if self.cursor == 0 {
+            return;
+        }
+        let start = self.byte_offset(self.cursor - 1);
+        let end = self.byte_offset(self.cursor);
+        self.input.replace_range(start..end, "");
+        self.cursor -= 1;
+    }
 
-    #[double]
-    pub use crate::crossterm::event;
+    /// Delete the grapheme under the cursor
+    pub fn delete_forward(&mut self) {
+        self.clamp_cursor();
+        let start = self.byte_offset(self.cursor);
+        let end = self.byte_offset(self.cursor + 1);
+        self.input.replace_range(start..end, "");
+    }
 
-    lazy_static! {
-        /// The context object is vulnerable to race conditions
-        /// Use a mutex so only one test at a time has access to update it
-        /// The lock will last for the length of the let block in the tests
-        /// Based on the mock_struct_with_static_method.rs tests
-        static ref EVENT_READ_MUTEX: Mutex<()> = Mutex::new(());
+    /// Insert text at the cursor and move the cursor past it
+    /// Graphemes past max_length are left out.
+    pub fn insert_str(&mut self, text: &str) {
+        self.clamp_cursor();
+        let before = self.grapheme_count();
+        let text: String = match self.max_length {
+            Some(max) => text
+                .graphemes(true)
+                .take(max.saturating_sub(before))
+                .collect(),
+            None => String::from(text),
+        };
+        let offset = self.byte_offset(self.cursor);
+        self.input.insert_str(offset, &text);
+        self.cursor = (self.cursor + self.grapheme_count()).saturating_sub(before);
     }
 
-    fn handle_generic_event(
-        command_bar_widget: &mut CommandBar,
-        event: Event,
-    ) -> EventHandlerResult {
-        let _m = EVENT_READ_MUTEX.lock().unwrap();
+    /// Remove the graphemes from start to end, add them to the kill ring
+    /// and move the cursor to start
+    fn kill(&mut self, start: usize, end: usize, direction: KillDirection) {
+        let range = self.byte_offset(start)..self.byte_offset(end);
+        self.registers
+            .borrow_mut()
+            .kill(&self.input[range.clone()], direction);
+        self.input.replace_range(range, "");
+        self.cursor = start;
+    }
 
-        let context = event::read_context();
+    /// Whether each grapheme of the input is part of a word, for the
+    /// readline word motions
+    fn word_graphemes(&self) -> Vec<bool> {
+        self.input
+            .graphemes(true)
+            .map(|g| g.chars().any(char::is_alphanumeric))
+            .collect()
+    }
 
-        context.expect().with().returning(move || {
-            return ::crossterm::Result::Ok(event);
-        });
+    /// Index of the start of the word before the cursor
+    fn word_start_before_cursor(&self) -> usize {
+        let words = self.word_graphemes();
+        let before = &words[..self.cursor];
+        let gap = before.iter().rev().take_while(|word| !**word).count();
+        let word = before
+            .iter()
+            .rev()
+            .skip(gap)
+            .take_while(|word| **word)
+            .count();
+        self.cursor - gap - word
+    }
 
-        command_bar_widget.handle_event()
+    /// Index of the end of the word after the cursor
+    fn word_end_after_cursor(&self) -> usize {
+        let words = self.word_graphemes();
+        let after = &words[self.cursor..];
+        let gap = after.iter().take_while(|word| !**word).count();
+        let word = after.iter().skip(gap).take_while(|word| **word).count();
+        self.cursor + gap + word
     }
 
-    fn handle_error_event(command_bar_widget: &mut CommandBar) -> EventHandlerResult {
-        let _m = EVENT_READ_MUTEX.lock().unwrap();
+    /// Kill the word before the cursor, and any whitespace after it
+    pub fn delete_word_backward(&mut self) {
+        self.clamp_cursor();
+        let spaces: Vec<bool> = self
+            .input
+            .graphemes(true)
+            .take(self.cursor)
+            .map(|g| g.chars().all(char::is_whitespace))
+            .collect();
+        let trailing = spaces.iter().rev().take_while(|space| **space).count();
+        let word = spaces
+            .iter()
+            .rev()
+            .skip(trailing)
+            .take_while(|space| !**space)
+            .count();
+        self.kill(
+            self.cursor - trailing - word,
+            self.cursor,
+            KillDirection::Backward,
+        );
+    }
 
-        let context = event::read_context();
+    /// Kill from the cursor to the end of the next word
+    pub fn delete_word_forward(&mut self) {
+        self.clamp_cursor();
+        let cursor = self.cursor;
+        self.kill(cursor, self.word_end_after_cursor(), KillDirection::Forward);
+    }
 
-        context.expect().with().returning(move || {
-            return ::crossterm::Result::Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                String::from("read error"),
-            ));
-        });
+    /// Kill the input before the cursor
+    pub fn kill_to_start(&mut self) {
+        self.clamp_cursor();
+        self.kill(0, self.cursor, KillDirection::Backward);
+    }
 
-        command_bar_widget.handle_event()
+    /// Kill the input after the cursor
+    pub fn kill_to_end(&mut self) {
+        self.clamp_cursor();
+        let cursor = self.cursor;
+        self.kill(cursor, self.grapheme_count(), KillDirection::Forward);
     }
 
-    /// Run an event test with various parameters
-    /// register_key is a key to register as a command key, or None if no key
-    /// should be registered.
-    /// start_mode is the editing mode to run the test in
-    /// input_event is the event to queue for the event handler
-    /// expected_event_result_option is the expected result from the event handler
-    /// expected_input_mode_option is the expected input mode state after the
-    /// event is processed.
-    /// other_tests is a closure of any other tests to run against the CommandBar
-    /// object.
-    fn run_event_test<'a>(
-        register_key: Option<char>,
-        start_mode: Option<InputMode>,
-        input_event: Option<Event>,
-        expected_event_result_option: Option<EventHandlerResult>,
-        expected_input_mode_option: Option<InputMode>,
-        other_tests: Option<&'a dyn Fn(CommandBar)>,
-    ) {
-        let mut command_bar_widget = CommandBar::default();
-        // create the closure here so it lives for as long as the CommandBar
-        // TODO: Maybe we could annotate this so it's not needed
-        let mut closure = |cb: &mut CommandBar, key| cb.command_key_handler(key);
+    /// Move the cursor to the start of the word before it
+    pub fn move_word_left(&mut self) {
+        self.clamp_cursor();
+        self.cursor = self.word_start_before_cursor();
+    }
 
-        if let Some(k) = register_key {
-            command_bar_widget.register_key(k, &mut closure);
+    /// Move the cursor to the end of the word after it
+    pub fn move_word_right(&mut self) {
+        self.clamp_cursor();
+        self.cursor = self.word_end_after_cursor();
+    }
+
+    /// Swap the grapheme before the cursor with the one under it, and move
+    /// the cursor right
+    /// At the end of the input the last two graphemes are swapped.
+    pub fn transpose_chars(&mut self) {
+        self.clamp_cursor();
+        let count = self.grapheme_count();
+        if self.cursor == 0 || count < 2 {
+            return;
         }
+        let index = self.cursor.min(count - 1);
+        let start = self.byte_offset(index - 1);
+        let middle = self.byte_offset(index);
+        let end = self.byte_offset(index + 1);
+        let swapped = format!("{}{}", &self.input[middle..end], &self.input[start..middle]);
+        self.input.replace_range(start..end, &swapped);
+        self.cursor = index + 1;
+    }
 
-        if let Some(start_mode) = start_mode {
-            command_bar_widget.input_mode = start_mode;
+    /// Insert the most recently killed text at the cursor
+    pub fn yank(&mut self) {
+        let text = self.registers.borrow_mut().yank();
+        if let Some(text) = text {
+            self.insert_yanked(&text, 0);
         }
+    }
 
-        // If there is an input event, handle it and run any tests
-        if let Some(event) = input_event {
-            let event_result = handle_generic_event(&mut command_bar_widget, event);
+    /// Insert text from index kills back in the kill ring at the cursor
+    fn insert_yanked(&mut self, text: &str, index: usize) {
+        self.clamp_cursor();
+        let start = self.cursor;
+        self.insert_str(text);
+        let bytes = self.byte_offset(start)..self.byte_offset(self.cursor);
+        self.yanked = Some(Yanked {
+            start,
+            text: String::from(&self.input[bytes]),
+            index,
+        });
+    }
 
-            if let Some(expected_event_result) = expected_event_result_option {
-                assert_eq!(event_result, expected_event_result);
-            }
+    /// Replace the text inserted by the last yank with the kill before it
+    /// in the kill ring, going back to the newest after the oldest
+    /// Nothing happens unless the cursor is still after the yanked text.
+    pub fn yank_pop(&mut self) {
+        let Some(yanked) = self.yanked.take() else {
+            return;
+        };
+        let end = yanked.start + yanked.text.graphemes(true).count();
+        let bytes = self.byte_offset(yanked.start)..self.byte_offset(end);
+        if self.cursor != end || self.input.get(bytes.clone()) != Some(yanked.text.as_str()) {
+            return;
+        }
+        let (index, text) = {
+            let kill_ring = &self.registers.borrow().kill_ring;
+            let index = (yanked.index + 1) % kill_ring.entries.len().max(1);
+            (index, kill_ring.get(index).map(String::from))
+        };
+        let Some(text) = text else {
+            return;
+        };
+        self.input.replace_range(bytes, "");
+        self.cursor = yanked.start;
+        self.insert_yanked(&text, index);
+    }
 
-            if let Some(expected_input_mode) = expected_input_mode_option {
-                assert_eq!(command_bar_widget.input_mode, expected_input_mode);
+    /// Insert pasted text at the cursor as one change, with its newlines
+    /// handled by the paste_policy
+    /// While searching the history, the text is added to the search.
+    /// Returns false if the paste_policy rejected the text.
+    pub fn paste(&mut self, text: &str) -> bool {
+        let Some(text) = self.paste_policy.apply(text) else {
+            debug!("Rejected pasted text with newlines");
+            return false;
+        };
+        if let Some(search) = self.search.as_mut() {
+            for c in text.chars() {
+                search.push(c, &self.history);
+            }
+            return true;
+        }
+        if self.completion.is_some() {
+            self.accept_completion();
+        }
+        self.undo_history.start_key();
+        let before = self.snapshot();
+        self.insert_str(&text);
+        if self.input != before.input {
+            self.undo_history.record(before, false);
+            if matches!(self.command_result, Some(Err(_))) {
+                self.command_result = None;
             }
         }
+        true
+    }
 
-        // Run extra tests that were passed in
-        if let Some(f) = other_tests {
-            f(command_bar_widget);
+    /// The input and cursor, for the undo history
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            input: self.input.clone(),
+            cursor: self.cursor,
         }
     }
 
-    #[test]
-    fn command_bar_registers_command_key() {
-        run_event_test(
-            Some(':'),
-            None,
-            None,
-            None,
-            None,
-            Some(&|command_bar_widget: CommandBar| {
-                assert!(command_bar_widget.command_key.is_some());
-                assert_eq!(command_bar_widget.command_key.unwrap(), ':');
-            }),
-        );
+    /// Restore the input and cursor from the undo history
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.input = snapshot.input;
+        self.cursor = snapshot.cursor;
+        self.completion = None;
     }
 
-    #[test]
-    fn command_bar_handles_command_key() {
-        run_event_test(
-            Some(':'),
-            None,
-            Some(Event::Key(KeyEvent::new(
-                KeyCode::Char(':'),
-                KeyModifiers::NONE,
-            ))),
-            Some(EventHandlerResult::Ok),
-            Some(InputMode::Editing),
-            None,
-        );
+    /// Undo the last change to the input
+    pub fn undo(&mut self) {
+        if let Some(snapshot) = self.undo_history.undo(self.snapshot()) {
+            self.restore(snapshot);
+        }
     }
 
-    #[test]
-    fn command_bar_handles_escape_key() {
-        run_event_test(
-            Some(':'),
-            Some(InputMode::Editing),
-            Some(Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))),
-            Some(EventHandlerResult::Ok),
-            Some(InputMode::Normal),
-            None,
-        );
+    /// Redo the last change that was undone
+    pub fn redo(&mut self) {
+        if let Some(snapshot) = self.undo_history.redo(self.snapshot()) {
+            self.restore(snapshot);
+        }
     }
 
-    #[test]
-    fn command_bar_handles_non_command_key_in_normal_mode() {
-        run_event_test(
-            Some(':'),
-            None,
-            Some(Event::Key(KeyEvent::new(
-                KeyCode::Char('a'),
-                KeyModifiers::NONE,
-            ))),
-            Some(EventHandlerResult::Unhandled(Event::Key(KeyEvent::new(
-                KeyCode::Char('a'),
-                KeyModifiers::NONE,
-            )))),
-            Some(InputMode::Normal),
-            None,
-        );
+    /// Where a vi motion repeated count times moves the cursor to
+    /// Returns None if f, t, F or T don't find their character.
+    fn vi_target(&self, motion: Motion, count: usize) -> Option<usize> {
+        let classes: Vec<GraphemeClass> =
+            self.input.graphemes(true).map(GraphemeClass::of).collect();
+        let len = classes.len();
+        let mut target = self.cursor;
+        match motion {
+            Motion::Left => target = target.saturating_sub(count),
+            Motion::Right => target = target.saturating_add(count).min(len),
+            Motion::WordForward => {
+                for _ in 0..count {
+                    let before = target;
+                    let class = classes.get(target).copied();
+                    if class != Some(GraphemeClass::Space) {
+                        target += classes[target..]
+                            .iter()
+                            .take_while(|c| Some(**c) == class)
+                            .count();
+                    }
+                    target += classes[target..]
+                        .iter()
+                        .take_while(|c| **c == GraphemeClass::Space)
+                        .count();
+                    // At the end of the input
+                    if target == before {
+                        break;
+                    }
+                }
+            }
+            Motion::WordBackward => {
+                for _ in 0..count {
+                    let before = target;
+                    target -= classes[..target]
+                        .iter()
+                        .rev()
+                        .take_while(|c| **c == GraphemeClass::Space)
+                        .count();
+                    if let Some(class) = target.checked_sub(1).map(|i| classes[i]) {
+                        target -= classes[..target]
+                            .iter()
+                            .rev()
+                            .take_while(|c| **c == class)
+                            .count();
+                    }
+                    // At the start of the input
+                    if target == before {
+                        break;
+                    }
+                }
+            }
+            Motion::WordEnd => {
+                for _ in 0..count {
+                    let start = (target + 1).min(len);
+                    let next = start
+                        + classes[start..]
+                            .iter()
+                            .take_while(|c| **c == GraphemeClass::Space)
+                            .count();
+                    let Some(class) = classes.get(next).copied() else {
+                        target = len.saturating_sub(1);
+                        break;
+                    };
+                    target = next
+                        + classes[next + 1..]
+                            .iter()
+                            .take_while(|c| **c == class)
+                            .count();
+                }
+            }
+            Motion::LineStart => target = 0,
+            Motion::LineEnd => target = len.saturating_sub(1),
+            Motion::Find { c, forward, till } => {
+                let c = c.to_string();
+                let graphemes: Vec<&str> = self.input.graphemes(true).collect();
+                let found = if forward {
+                    (target + 1..len)
+                        .filter(|i| graphemes[*i] == c)
+                        .nth(count - 1)?
+                } else {
+                    (0..target)
+                        .rev()
+                        .filter(|i| graphemes[*i] == c)
+                        .nth(count - 1)?
+                };
+                target = match (till, forward) {
+                    (false, _) => found,
+                    (true, true) => found - 1,
+                    (true, false) => found + 1,
+                };
+            }
+            Motion::WholeLine => target = 0,
+        }
+        Some(target)
     }
 
-    #[test]
-    fn command_bar_unregistered_handles_command_key_in_normal_mode() {
-        run_event_test(
-            None,
-            None,
-            Some(Event::Key(KeyEvent::new(
-                KeyCode::Char(':'),
-                KeyModifiers::NONE,
-            ))),
-            Some(EventHandlerResult::Unhandled(Event::Key(KeyEvent::new(
-                KeyCode::Char(':'),
-                KeyModifiers::NONE,
+    /// The graphemes an operator with motion works on
+    fn vi_range(&self, operator: Operator, motion: Motion, count: usize) -> Option<Range<usize>> {
+        let len = self.grapheme_count();
+        if motion == Motion::WholeLine {
+            return Some(0..len);
+        }
+        // cw changes to the end of the word, like ce, but on the last
+        // grapheme of a word it only changes that grapheme, where ce would
+        // go on to the end of the next word
+        let mut classes = self
+            .input
+            .graphemes(true)
+            .skip(self.cursor)
+            .map(GraphemeClass::of);
+        let (class, next) = (classes.next(), classes.next());
+        let (motion, target) = match (operator, motion, class) {
+            (Operator::Change, Motion::WordForward, Some(class))
+                if class != GraphemeClass::Space =>
+            {
+                let count = if next == Some(class) {
+                    count
+                } else {
+                    count - 1
+                };
+                (Motion::WordEnd, self.vi_target(Motion::WordEnd, count)?)
+            }
+            _ => (motion, self.vi_target(motion, count)?),
+        };
+        let start = self.cursor.min(target);
+        let end = self.cursor.max(target);
+        if motion.is_inclusive() {
+            Some(start..(end + 1).min(len))
+        } else {
+            Some(start..end)
+        }
+    }
+
+    /// Remove the graphemes in range and return them
+    fn remove_graphemes(&mut self, range: Range<usize>) -> String {
+        let bytes = self.byte_offset(range.start)..self.byte_offset(range.end);
+        let removed = String::from(&self.input[bytes.clone()]);
+        self.input.replace_range(bytes, "");
+        removed
+    }
+
+    /// Start vi insert mode at the grapheme index cursor
+    fn vi_insert(&mut self, cursor: usize) {
+        self.cursor = cursor;
+        self.vi.set_mode(ViMode::Insert, cursor);
+    }
+
+    /// Leave vi insert mode, recording the typed text for .
+    fn vi_normal(&mut self) {
+        if self.vi.recording && self.cursor >= self.vi.insert_start {
+            let start = self.byte_offset(self.vi.insert_start);
+            let end = self.byte_offset(self.cursor);
+            let inserted = String::from(&self.input[start..end]);
+            if let Some(change) = &mut self.vi.last_change {
+                change.inserted = inserted;
+            }
+        }
+        self.vi.set_mode(ViMode::Normal, self.cursor);
+        self.move_cursor_left();
+    }
+
+    /// Store text deleted or copied by vi in a named register, or in the
+    /// kill ring if there's no register
+    fn vi_store(&mut self, register: Option<char>, text: &str) {
+        let mut registers = self.registers.borrow_mut();
+        match register {
+            Some(name) => registers.set(name, text),
+            None => registers.push(text),
+        }
+    }
+
+    /// Run a vi normal mode command, with the count typed before it
+    /// The register named before the command is in vi.register.
+    /// A count of 0 runs the command once, like no count.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tui_command_bar_widget::editing::vi::{Motion, Operator, ViCommand};
+    /// use tui_command_bar_widget::widgets::command_bar::CommandBar;
+    ///
+    /// let mut command_bar_widget = CommandBar::default();
+    /// command_bar_widget.input = String::from("open a file");
+    /// command_bar_widget.run_vi_command(
+    ///     Some(2),
+    ///     ViCommand::Operate(Operator::Delete, Motion::WordForward),
+    /// );
+    /// assert_eq!(command_bar_widget.input, "file");
+    /// ```
+    pub fn run_vi_command(&mut self, count: Option<usize>, command: ViCommand) {
+        if command.is_change() {
+            self.vi.last_change = Some(LastChange {
+                count,
+                command,
+                inserted: String::new(),
+            });
+        }
+        self.clamp_cursor();
+        let times = count.unwrap_or(1).max(1);
+        let len = self.grapheme_count();
+        let register = self.vi.register.take();
+        match command {
+            ViCommand::Move(motion) => {
+                if let Some(target) = self.vi_target(motion, times) {
+                    self.cursor = target;
+                }
+            }
+            ViCommand::Operate(operator, motion) => {
+                let Some(range) = self.vi_range(operator, motion, times) else {
+                    return;
+                };
+                match operator {
+                    Operator::Yank => {
+                        let bytes = self.byte_offset(range.start)..self.byte_offset(range.end);
+                        let copied = String::from(&self.input[bytes]);
+                        self.vi_store(register, &copied);
+                        self.cursor = range.start;
+                    }
+                    Operator::Delete | Operator::Change => {
+                        let start = range.start;
+                        let removed = self.remove_graphemes(range);
+                        self.vi_store(register, &removed);
+                        self.cursor = start;
+                        if operator == Operator::Change {
+                            self.vi_insert(start);
+                            self.vi.recording = true;
+                        }
+                    }
+                }
+            }
+            ViCommand::Insert(at) => {
+                let cursor = match at {
+                    InsertAt::Cursor => self.cursor,
+                    InsertAt::After => (self.cursor + 1).min(len),
+                    InsertAt::Start => 0,
+                    InsertAt::End => len,
+                };
+                self.vi_insert(cursor);
+                self.vi.recording = true;
+            }
+            ViCommand::Paste { after } => {
+                let text = match register {
+                    Some(name) => self.registers.borrow().get(name).map(String::from),
+                    None => self.registers.borrow_mut().yank(),
+                };
+                let Some(text) = text else {
+                    return;
+                };
+                // Each copy adds a grapheme at least, so copies past
+                // max_length would be left out anyway
+                let times = match self.max_length {
+                    Some(max) => times.min(max.saturating_sub(len)),
+                    None => times,
+                };
+                let text = text.repeat(times);
+                if after && len > 0 {
+                    self.cursor += 1;
+                }
+                let before = self.cursor;
+                self.insert_str(&text);
+                // The cursor is left on the last pasted grapheme
+                if self.cursor > before {
+                    self.cursor -= 1;
+                }
+            }
+            ViCommand::Undo => {
+                for _ in 0..times.min(self.undo_history.undo.len()) {
+                    self.undo();
+                }
+            }
+            ViCommand::Repeat => {
+                let Some(change) = self.vi.last_change.clone() else {
+                    return;
+                };
+                self.run_vi_command(count.or(change.count), change.command);
+                if self.vi.mode == ViMode::Insert {
+                    self.insert_str(&change.inserted);
+                    self.vi_normal();
+                }
+                // The repeated change is still the last one
+                self.vi.last_change = Some(LastChange {
+                    count: count.or(change.count),
+                    ..change
+                });
+            }
+        }
+        // In normal mode the cursor stays on a grapheme
+        if self.vi.mode == ViMode::Normal {
+            self.cursor = self.cursor.min(self.grapheme_count().saturating_sub(1));
+        }
+    }
+
+    /// Handle a key with vi editing
+    /// Returns true if the key was used, otherwise it's handled by the
+    /// Editing keymap, so Enter still submits.
+    fn handle_vi_key(&mut self, key: KeyEvent) -> bool {
+        let plain = is_typed(key);
+        match (self.vi.mode, key.code) {
+            (ViMode::Insert, KeyCode::Esc) if plain => {
+                self.vi_normal();
+                true
+            }
+            (ViMode::Normal, KeyCode::Esc) if !self.vi.keys.is_empty() => {
+                self.vi.keys.clear();
+                true
+            }
+            (ViMode::Normal, KeyCode::Char(c)) if plain => {
+                if let Some((count, command)) = self.vi.push(c) {
+                    self.run_vi_command(count, command);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Replace the input with a recalled entry and move the cursor to its end
+    fn recall(&mut self, entry: String) {
+        self.input = entry;
+        self.move_cursor_end();
+    }
+
+    /// Replace the input with the previous history entry
+    /// The input being edited is kept and restored by history_next
+    pub fn history_previous(&mut self) {
+        if let Some(entry) = self.history.back(&self.input) {
+            let entry = String::from(entry);
+            self.recall(entry);
+        }
+    }
+
+    /// Replace the input with the next history entry, or the original input
+    /// after the newest entry
+    pub fn history_next(&mut self) {
+        if let Some(entry) = self.history.forward() {
+            let entry = String::from(entry);
+            self.recall(entry);
+        }
+    }
+
+    /// Replace the input, placing the cursor at a byte offset in it
+    fn set_input(&mut self, input: String, byte_cursor: usize) {
+        self.cursor = input[..byte_cursor].graphemes(true).count();
+        self.input = input;
+    }
+
+    /// Complete the input using the completer
+    ///
+    /// A single candidate is inserted directly.
+    /// With several candidates the first call inserts the prefix they share,
+    /// if that adds anything, and later calls cycle through the candidates.
+    pub fn complete(&mut self) {
+        self.cycle_completion(true);
+    }
+
+    /// Cycle backwards through the completion candidates
+    pub fn complete_previous(&mut self) {
+        self.cycle_completion(false);
+    }
+
+    fn cycle_completion(&mut self, forward: bool) {
+        if self.completion.is_none() {
+            let completer = match &self.completer {
+                Some(completer) => Rc::clone(completer),
+                None => return,
+            };
+            self.clamp_cursor();
+            let byte_cursor = self.byte_offset(self.cursor);
+            let mut candidates = completer.complete(&self.input, byte_cursor);
+            debug!("Completion candidates: {:?}", candidates);
+            candidates.retain(|candidate| {
+                let valid = is_valid_span(&self.input, candidate);
+                if !valid {
+                    debug!("Dropped candidate with an invalid span: {:?}", candidate);
+                }
+                valid
+            });
+
+            let single = match candidates.len() {
+                0 => return,
+                1 => Some(candidates[0].clone()),
+                // Only insert a prefix that extends what was typed, fuzzy
+                // candidates may not start with it
+                _ => common_prefix(&candidates).filter(|prefix| {
+                    let typed = &self.input[prefix.span.clone()];
+                    prefix.replacement.len() > typed.len() && prefix.replacement.starts_with(typed)
+                }),
+            };
+            if let Some((input, cursor)) = single.and_then(|c| apply_candidate(&self.input, &c)) {
+                self.set_input(input, cursor);
+                return;
+            }
+            self.completion = Some(CompletionState::new(&self.input, byte_cursor, candidates));
+        }
+
+        if let Some(state) = self.completion.as_mut() {
+            if forward {
+                state.select_next();
+            } else {
+                state.select_previous();
+            }
+            if let Some((input, cursor)) = state.completed() {
+                self.set_input(input, cursor);
+            }
+        }
+    }
+
+    /// Stop cycling through completions, keeping the completed input
+    pub fn accept_completion(&mut self) {
+        self.completion = None;
+    }
+
+    /// Stop cycling through completions, restoring the original input
+    pub fn cancel_completion(&mut self) {
+        if let Some(state) = self.completion.take() {
+            self.set_input(state.original, state.cursor);
+        }
+    }
+
+    /// A menu of the candidates being cycled through, if there are any
+    /// Render it after the CommandBar, using CompletionMenu::area to place it
+    /// next to the CommandBar.
+    pub fn completion_menu(&self) -> Option<CompletionMenu<'_>> {
+        self.completion.as_ref().map(CompletionMenu::new)
+    }
+
+    /// Start a reverse incremental search through the history
+    pub fn start_search(&mut self) {
+        let draft = self.history.draft().map(String::from);
+        self.history.reset();
+        self.search = Some(ReverseSearch {
+            fuzzy: self.fuzzy_search,
+            draft,
+            ..Default::default()
+        });
+    }
+
+    /// Finish the search, replacing the input with the matched entry
+    pub fn accept_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            if let Some(entry) = search.matched(&self.history) {
+                let entry = String::from(entry);
+                self.recall(entry);
+            }
+        }
+    }
+
+    /// Cancel the search, leaving the input as it was before searching
+    /// Input recalled from the history is replaced with the draft it
+    /// replaced.
+    pub fn cancel_search(&mut self) {
+        if let Some(draft) = self.search.take().and_then(|search| search.draft) {
+            self.recall(draft);
+        }
+    }
+
+    /// The line shown in the command bar and the cursor position in it
+    /// While searching this is the search prompt and the matched entry.
+    fn display(&self) -> (String, usize) {
+        match &self.search {
+            None => (self.input.clone(), self.cursor.min(self.grapheme_count())),
+            Some(search) => {
+                let mut line = search.prompt();
+                let entry = search.matched(&self.history).unwrap_or("");
+                // Put the cursor at the start of the match, like readline
+                let before = search.match_start(&self.history);
+                let cursor = line.graphemes(true).count() + entry[..before].graphemes(true).count();
+                line.push_str(entry);
+                (line, cursor)
+            }
+        }
+    }
+
+    /// Grapheme range of the input that caused the last command error
+    /// Returns None while searching, when the input isn't shown
+    fn error_graphemes(&self) -> Option<Range<usize>> {
+        if self.search.is_some() {
+            return None;
+        }
+        let span = match &self.command_result {
+            Some(Err(e)) => e.span()?,
+            _ => return None,
+        };
+        // The input may have been changed directly since the error
+        let start = self.input.get(..span.start)?.graphemes(true).count();
+        let len = self.input.get(span)?.graphemes(true).count();
+        Some(start..start + len)
+    }
+
+    /// Title of the CommandBar block, with the result of the last command
+    fn title(&self) -> Spans<'_> {
+        let mut title = vec![Span::raw(self.title.as_str())];
+        match &self.command_result {
+            Some(Ok(CommandOutcome {
+                message: Some(message),
+                ..
+            })) => {
+                title.push(Span::raw(": "));
+                title.push(Span::raw(message.as_str()));
+            }
+            Some(Err(e)) => {
+                title.push(Span::raw(": "));
+                let style = self.error_style.remove_modifier(Modifier::UNDERLINED);
+                title.push(Span::styled(e.to_string(), style));
+            }
+            _ => (),
+        }
+        // Show the vi sub-mode, and the keys of a command being typed
+        if self.editing_keys == EditingKeys::Vi && self.input_mode == InputMode::Editing {
+            let mode = match self.vi.mode {
+                ViMode::Insert => " [insert]",
+                ViMode::Normal => " [normal]",
+            };
+            title.push(Span::styled(
+                mode,
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            if !self.vi.keys.is_empty() {
+                let keys: String = self.vi.keys.iter().collect();
+                title.push(Span::styled(
+                    format!(" {}", keys),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+        }
+        // Show the keys of a sequence waiting for its next key
+        if !self.key_database.pending.is_empty() {
+            title.push(Span::styled(
+                format!(" {}-", self.key_database.pending_keys()),
+                Style::default().fg(Color::Cyan),
+            ));
+        }
+        Spans::from(title)
+    }
+
+    /// Area inside the border where the input text is drawn
+    /// Overflow indicators take up a column on each side
+    fn text_area(&self, area: Rect) -> Rect {
+        let inset = if self.overflow_indicators { 2 } else { 1 };
+        Rect {
+            x: area.x + inset,
+            y: area.y + 1,
+            width: area.width.saturating_sub(2 * inset),
+            height: area.height.saturating_sub(2),
+        }
+    }
+
+    /// Scroll offset that keeps the cursor visible in a text area of width
+    ///
+    /// Starts from the current scroll_offset so the text only moves when the
+    /// cursor reaches an edge.
+    fn visible_offset(&self, line: &str, cursor: usize, width: u16) -> usize {
+        let widths: Vec<usize> = line.graphemes(true).map(|g| g.width()).collect();
+        let cursor = cursor.min(widths.len());
+        // The cursor takes up a cell after the text before it
+        let width = usize::from(width).max(1);
+        let span = |from: usize, to: usize| widths[from..to].iter().sum::<usize>() + 1;
+
+        let mut offset = self.scroll_offset.min(cursor);
+        while offset < cursor && span(offset, cursor) > width {
+            offset += 1;
+        }
+        // Scroll back when text was deleted and there is room on the right
+        while offset > 0 && span(offset - 1, widths.len()) <= width {
+            offset -= 1;
+        }
+
+        offset
+    }
+
+    /// Terminal position of the cursor when the CommandBar is rendered in area
+    /// Callers in editing mode can pass this to Frame::set_cursor
+    pub fn cursor_position(&self, area: Rect) -> (u16, u16) {
+        let text_area = self.text_area(area);
+        let (line, cursor) = self.display();
+        let offset = self.visible_offset(&line, cursor, text_area.width);
+        let width: usize = line
+            .graphemes(true)
+            .skip(offset)
+            .take(cursor - offset)
+            .map(|g| g.width())
+            .sum();
+        (text_area.x + width as u16, text_area.y)
+    }
+
+    /// Parse the input with the commands without running it
+    /// Returns None if there are no commands or the input is blank
+    fn parse_input(&self) -> Option<Result<(), CommandError>> {
+        if self.input.trim().is_empty() {
+            return None;
+        }
+        let commands = self.commands.as_ref()?;
+        Some(commands.parse(&self.input).map(|_| ()))
+    }
+
+    /// Handle a key press in the search prompt
+    /// Returns false if the key ended the search and should be handled as
+    /// an editing key.
+    fn handle_search_key(&mut self, key: KeyEvent) -> bool {
+        let action = self.editing_action(key);
+        let search = match self.search.as_mut() {
+            Some(search) => search,
+            None => return false,
+        };
+        match (action, key.code) {
+            (Some(Action::ReverseSearch), _) => search.step_back(&self.history),
+            (Some(Action::DeleteBackward), _) => search.pop(&self.history),
+            (Some(Action::Submit), _) => self.accept_search(),
+            (Some(Action::Cancel), _) => self.cancel_search(),
+            (None, KeyCode::Char(c)) if is_typed(key) => search.push(c, &self.history),
+            _ => {
+                self.accept_search();
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Handle a key press while the completion menu is shown
+    /// The keys bound to the history actions move through the menu too.
+    /// Returns false if the key closed the menu and should be handled as
+    /// an editing key.
+    fn handle_completion_key(&mut self, key: KeyEvent) -> bool {
+        match self.editing_action(key) {
+            Some(Action::Complete | Action::HistoryNext) => self.complete(),
+            Some(Action::CompletePrevious | Action::HistoryPrevious) => self.complete_previous(),
+            Some(Action::Submit) => self.accept_completion(),
+            Some(Action::Cancel) => self.cancel_completion(),
+            _ => {
+                self.accept_completion();
+                return false;
+            }
+        }
+        true
+    }
+
+    /// The action key is bound to on its own in the editing keymap
+    fn editing_action(&self, key: KeyEvent) -> Option<Action> {
+        self.keymaps
+            .get(&InputMode::Editing)
+            .and_then(|keymap| keymap.get(&KeySequence::from(key)))
+    }
+
+    /// Submit the input, unless the commands can't parse it
+    fn submit_input(&mut self) {
+        // Input the commands can't parse stays in the command bar with the
+        // error, so it can be fixed
+        if let Some(Err(e)) = self.parse_input() {
+            self.command_result = Some(Err(e));
+            return;
+        }
+        // Entering leaves edit mode and commits the text
+        match self.submit() {
+            Ok(_) => (),
+            Err(e) => {
+                error!("Send error on message: {}", e);
+            }
+        }
+        self.normal();
+    }
+
+    /// Run an action bound to a key
+    pub fn run_action(&mut self, action: Action) {
+        debug!("Running action {}", action);
+        match action {
+            Action::Edit => self.edit(),
+            Action::Submit => self.submit_input(),
+            Action::Cancel => self.normal(),
+            Action::DeleteBackward => self.delete_backward(),
+            Action::DeleteForward => self.delete_forward(),
+            Action::DeleteWordBackward => self.delete_word_backward(),
+            Action::DeleteWordForward => self.delete_word_forward(),
+            Action::KillToStart => self.kill_to_start(),
+            Action::KillToEnd => self.kill_to_end(),
+            Action::MoveLeft => self.move_cursor_left(),
+            Action::MoveRight => self.move_cursor_right(),
+            Action::MoveHome => self.move_cursor_home(),
+            Action::MoveEnd => self.move_cursor_end(),
+            Action::MoveWordLeft => self.move_word_left(),
+            Action::MoveWordRight => self.move_word_right(),
+            Action::TransposeChars => self.transpose_chars(),
+            Action::Yank => self.yank(),
+            Action::YankPop => self.yank_pop(),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::Complete => self.complete(),
+            Action::CompletePrevious => self.complete_previous(),
+            Action::HistoryPrevious => self.history_previous(),
+            Action::HistoryNext => self.history_next(),
+            Action::ReverseSearch => self.start_search(),
+        }
+    }
+
+    /// Handle a key press in editing mode
+    /// Returns true if the key was handled
+    fn handle_editing_key(&mut self, key: KeyEvent) -> bool {
+        self.registers.borrow_mut().kill_ring.start_key();
+        self.undo_history.start_key();
+        let before = self.snapshot();
+        // Characters typed one after another are undone together, but in vi
+        // normal mode they're commands
+        let typed = matches!(key.code, KeyCode::Char(_))
+            && is_typed(key)
+            && !(self.editing_keys == EditingKeys::Vi && self.vi.mode == ViMode::Normal);
+
+        let handled = self.dispatch_editing_key(key);
+
+        // Submitting or cancelling leaves editing mode and clears the undo
+        // history, so there's nothing to record
+        if self.input_mode == InputMode::Editing {
+            if self.input != before.input {
+                self.undo_history.record(before, typed);
+            } else if self.cursor != before.cursor {
+                self.undo_history.break_typing();
+            }
+        }
+        handled
+    }
+
+    /// Pass a key pressed in editing mode to the search, completion, vi
+    /// keys and keymap in turn
+    /// Returns true if the key was handled
+    fn dispatch_editing_key(&mut self, key: KeyEvent) -> bool {
+        if self.search.is_some() && self.handle_search_key(key) {
+            return true;
+        }
+        if self.completion.is_some() && self.handle_completion_key(key) {
+            return true;
+        }
+        if self.editing_keys == EditingKeys::Vi && self.handle_vi_key(key) {
+            return true;
+        }
+        if let Some(handled) = self.handle_bound_key(key) {
+            return handled;
+        }
+        self.type_key(key)
+    }
+
+    /// Type the character of a key that isn't bound, unless it's a chord
+    /// the application may want
+    /// Returns true if the key was typed
+    fn type_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char(c) if is_typed(key) => {
+                self.insert_char(c);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether keys start a longer sequence bound in the current mode
+    fn is_prefix(&self, keys: &[KeyCombination]) -> bool {
+        let keymap = self.keymaps.get(&self.input_mode);
+        (self.input_mode == InputMode::Normal && self.key_database.is_prefix(keys))
+            || keymap.is_some_and(|keymap| keymap.is_prefix(keys))
+    }
+
+    /// Whether keys are bound to a handler or action in the current mode
+    fn is_bound(&self, keys: &KeySequence) -> bool {
+        let keymap = self.keymaps.get(&self.input_mode);
+        (self.input_mode == InputMode::Normal && self.key_database.keys.contains_key(keys))
+            || keymap.is_some_and(|keymap| keymap.get(keys).is_some())
+    }
+
+    /// Run the handler or action bound to keys in the current mode, passing
+    /// handlers the last key pressed
+    /// Returns whether the key was used
+    fn run_binding(&mut self, keys: KeySequence, key: KeyEvent) -> bool {
+        if self.input_mode == InputMode::Normal && self.key_database.keys.contains_key(&keys) {
+            return self.run_key_handler(keys, key);
+        }
+        match self
+            .keymaps
+            .get(&self.input_mode)
+            .and_then(|k| k.get(&keys))
+        {
+            Some(action) => {
+                self.run_action(action);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Match a key against the bindings of the current mode
+    /// Returns None if the key isn't bound, otherwise whether it was used
+    fn handle_bound_key(&mut self, key: KeyEvent) -> Option<bool> {
+        let mode = self.input_mode;
+        // A key pressed after the timeout starts a new sequence
+        if self.key_database.timed_out(Instant::now()) {
+            self.resolve_pending_keys();
+        }
+
+        self.key_database.pending.push(key);
+        let keys = self.key_database.pending_keys();
+        if self.is_prefix(&keys.keys) {
+            // Wait for the next key of a longer sequence
+            self.key_database.pending_since = Some(Instant::now());
+            return Some(true);
+        }
+        if self.is_bound(&keys) {
+            self.key_database.take_pending();
+            return Some(self.run_binding(keys, key));
+        }
+
+        // The key doesn't continue the sequence, so finish the keys before
+        // it and start again from this key
+        self.key_database.pending.pop();
+        if self.key_database.pending.is_empty() {
+            return None;
+        }
+        self.resolve_pending_keys();
+        // Handle the key in the mode the pending keys left the CommandBar
+        // in. When handle_editing_key is already running it records the
+        // change, so the key is only dispatched.
+        Some(match (mode, self.input_mode) {
+            (_, InputMode::Normal) => self.handle_bound_key(key).unwrap_or(false),
+            (InputMode::Normal, InputMode::Editing) => self.handle_editing_key(key),
+            (InputMode::Editing, InputMode::Editing) => self.dispatch_editing_key(key),
+        })
+    }
+
+    /// Run the handler for keys, passing it the last key pressed
+    /// Returns whether the handler used the key
+    fn run_key_handler(&mut self, keys: KeySequence, key: KeyEvent) -> bool {
+        // The handler is taken out of the database while it runs so it can
+        // be given the CommandBar
+        match self.key_database.keys.remove(&keys) {
+            Some(mut f) => {
+                let outcome = f(self, key);
+                // Keep a handler registered while this one ran
+                self.key_database.keys.entry(keys).or_insert(f);
+                outcome == HandlerOutcome::Handled
+            }
+            None => false,
+        }
+    }
+
+    /// Finish a pending key sequence, running the handler for the keys
+    /// pressed so far if they have one
+    /// In Editing mode keys that aren't bound together are handled one at
+    /// a time, so typed characters aren't lost.
+    /// Returns whether any keys were pending
+    pub fn resolve_pending_keys(&mut self) -> bool {
+        let pending = self.key_database.take_pending();
+        let last = match pending.last() {
+            Some(last) => *last,
+            None => return false,
+        };
+        let keys = KeySequence::new(pending.iter().copied().map(KeyCombination::from).collect());
+        if self.is_bound(&keys) {
+            self.run_binding(keys, last);
+        } else if self.input_mode == InputMode::Editing {
+            // The keys would have been typed if they didn't start a
+            // sequence, so they're handled one at a time instead
+            for key in pending {
+                if self.input_mode != InputMode::Editing {
+                    break;
+                }
+                match self.editing_action(key) {
+                    Some(action) => self.run_action(action),
+                    None => {
+                        self.type_key(key);
+                    }
+                }
+            }
+        } else {
+            debug!("Key sequence {} is not bound", keys);
+        }
+        true
+    }
+
+    /// Finish a pending key sequence that timed out, recording the change
+    /// to the input so it can be undone
+    fn resolve_timed_out_keys(&mut self) -> bool {
+        let editing = self.input_mode == InputMode::Editing;
+        let before = self.snapshot();
+        let resolved = self.resolve_pending_keys();
+        if editing && self.input_mode == InputMode::Editing && self.input != before.input {
+            self.undo_history.start_key();
+            self.undo_history.record(before, false);
+        }
+        resolved
+    }
+
+    /// Time left to press the next key of a pending key sequence
+    /// Applications that read events themselves can wait this long for an
+    /// event, then call handle_timeout.
+    pub fn key_timeout(&self) -> Option<Duration> {
+        self.key_database.remaining(Instant::now())
+    }
+
+    /// Finish a pending key sequence if its timeout has run out
+    /// Returns whether the CommandBar changed and should be redrawn
+    pub fn handle_timeout(&mut self) -> bool {
+        if self.key_database.timed_out(Instant::now()) {
+            self.resolve_timed_out_keys()
+        } else {
+            false
+        }
+    }
+
+    /// Read an event from the terminal with crossterm and handle it
+    /// This blocks until an event is available.
+    /// Applications with their own event loop should read events themselves
+    /// and pass them to handle_key_event.
+    pub fn handle_event(&mut self) -> EventHandlerResult {
+        self.handle_event_from(&mut CrosstermEventSource)
+    }
+
+    /// Read an event from source and handle it
+    pub fn handle_event_from<S: EventSource + ?Sized>(
+        &mut self,
+        source: &mut S,
+    ) -> EventHandlerResult {
+        // Only wait as long as a pending key sequence has left
+        if let Some(timeout) = self.key_timeout() {
+            match source.poll(timeout) {
+                Ok(true) => (),
+                Ok(false) => {
+                    self.resolve_timed_out_keys();
+                    return EventHandlerResult::Ok;
+                }
+                Err(e) => {
+                    error!("Event poll error: {}", e);
+                    return EventHandlerResult::Err;
+                }
+            }
+        }
+        match source.read() {
+            Ok(e) => self.handle_key_event(e),
+            Err(e) => {
+                error!("Event read error: {}", e);
+                EventHandlerResult::Err
+            }
+        }
+    }
+
+    /// Handle an event that has already been read
+    /// If the widget is not registered to handle the event, it's returned
+    /// as Unhandled so it can be passed to the parent or other widgets
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+    /// use tui_command_bar_widget::widgets::command_bar::{CommandBar, EventHandlerResult, InputMode};
+    ///
+    /// let mut command_bar_widget = CommandBar {
+    ///     input_mode: InputMode::Editing,
+    ///     ..Default::default()
+    /// };
+    /// let event = Event::Key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+    /// assert_eq!(command_bar_widget.handle_key_event(event), EventHandlerResult::Ok);
+    /// assert_eq!(command_bar_widget.input, "q");
+    /// ```
+    pub fn handle_key_event(&mut self, event: Event) -> EventHandlerResult {
+        #[allow(unused_assignments)]
+        let mut handled = false;
+
+        match event {
+            Event::Key(key) => {
+                match self.input_mode {
+                    InputMode::Normal => {
+                        handled = self.handle_bound_key(key).unwrap_or(false);
+                    }
+                    InputMode::Editing => {
+                        let input = self.input.clone();
+                        handled = self.handle_editing_key(key);
+                        // A command error refers to the submitted input, so
+                        // drop it once the input is edited
+                        if self.input_mode == InputMode::Editing
+                            && self.input != input
+                            && matches!(self.command_result, Some(Err(_)))
+                        {
+                            self.command_result = None;
+                        }
+                    }
+                }
+            }
+            Event::Paste(ref text) => {
+                handled = self.input_mode == InputMode::Editing && self.paste(text);
+            }
+            Event::Resize(w, h) => {
+                debug!("Resize event: {:?}, {:?}", w, h);
+                handled = false;
+            }
+            Event::FocusGained | Event::FocusLost => {
+                handled = false;
+            }
+            Event::Mouse(e) => {
+                debug!("Mouse event: {:?}", e);
+                handled = false;
+            }
+        };
+        if handled {
+            EventHandlerResult::Ok
+        } else {
+            EventHandlerResult::Unhandled(event)
+        }
+    }
+}
+
+impl AsMut<CommandBar> for CommandBar {
+    fn as_mut(&mut self) -> &mut CommandBar {
+        self
+    }
+}
+
+impl Widget for CommandBar {
+    fn render(self, _area: Rect, _buf: &mut Buffer) {}
+}
+
+impl Widget for &CommandBar {
+    fn render(self, _area: Rect, _buf: &mut Buffer) {}
+}
+
+impl Widget for &mut CommandBar {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        debug!("area width: {:?}, height: {:?}", area.width, area.height);
+        debug!(
+            "buf width: {:?}, height: {:?}",
+            buf.area.width, buf.area.height
+        );
+
+        let text_area = self.text_area(area);
+        let (line, cursor) = self.display();
+        self.width = text_area.width;
+        self.scroll_offset = self.visible_offset(&line, cursor, self.width);
+
+        // Collect the graphemes that fit in the text area, split into the
+        // text before, in and after the part of the input with an error
+        let underline = self.error_graphemes().unwrap_or(0..0);
+        let mut visible = [String::new(), String::new(), String::new()];
+        let mut visible_width = 0;
+        let mut hidden_right = false;
+        for (i, g) in line.graphemes(true).enumerate().skip(self.scroll_offset) {
+            if visible_width + g.width() > usize::from(self.width) {
+                hidden_right = true;
+                break;
+            }
+            visible_width += g.width();
+            let part = if i < underline.start {
+                0
+            } else if i < underline.end {
+                1
+            } else {
+                2
+            };
+            visible[part].push_str(g);
+        }
+        let [before, error, after] = visible;
+
+        let mut spans = vec![
+            Span::raw(before),
+            Span::styled(error, self.error_style),
+            Span::raw(after),
+        ];
+        if self.overflow_indicators {
+            let left = if self.scroll_offset > 0 { "<" } else { " " };
+            let right = if hidden_right { ">" } else { " " };
+            let padding = usize::from(self.width) - visible_width;
+            spans.insert(0, Span::raw(left));
+            spans.push(Span::raw(" ".repeat(padding)));
+            spans.push(Span::raw(right));
+        }
+
+        let input = Paragraph::new(Text::from(Spans::from(spans)))
+            .style(match self.input_mode {
+                InputMode::Normal => self.normal_style,
+                InputMode::Editing => self.editing_style,
+            })
+            .block(Block::default().borders(Borders::ALL).title(self.title()));
+
+        input.render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::debug;
+
+    use tui::{
+        backend::TestBackend,
+        buffer::Buffer,
+        layout::Rect,
+        style::{Color, Modifier, Style},
+        Terminal,
+    };
+
+    use ::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+    use ::crossterm::event::{MouseEvent, MouseEventKind};
+
+    use crate::command::registry::{ArgKind, ArgSpec, Command, CommandError, CommandRegistry};
+    use crate::completion::completer::{Candidate, Completer, WordCompleter};
+    use crate::editing::paste::PastePolicy;
+    use crate::editing::vi::{EditingKeys, Motion, Operator, ViCommand, ViMode, MAX_COUNT};
+    use crate::key_hook::bindings::Conflict;
+    use crate::key_hook::key_combination::KeyCombination;
+    use crate::key_hook::key_hook::{HandlerOutcome, KeyHook};
+    use crate::key_hook::key_sequence::KeySequence;
+    use crate::key_hook::keymap::{Action, Keymap};
+    use crate::widgets::command_bar::{CommandBar, EventHandlerResult, InputMode};
+
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use crate::event_source::scripted::ScriptedEventSource;
+
+    fn handle_generic_event(
+        command_bar_widget: &mut CommandBar,
+        event: Event,
+    ) -> EventHandlerResult {
+        let mut source = ScriptedEventSource::new([event]);
+        command_bar_widget.handle_event_from(&mut source)
+    }
+
+    fn handle_error_event(command_bar_widget: &mut CommandBar) -> EventHandlerResult {
+        let mut source = ScriptedEventSource::default();
+        source.push_error(std::io::Error::other(String::from("read error")));
+        command_bar_widget.handle_event_from(&mut source)
+    }
+
+    /// Run an event test with various parameters
+    /// register_key is a key to register as a command key, or None if no key
+    /// should be registered.
+    /// start_mode is the editing mode to run the test in
+    /// input_event is the event to queue for the event handler
+    /// expected_event_result_option is the expected result from the event handler
+    /// expected_input_mode_option is the expected input mode state after the
+    /// event is processed.
+    /// other_tests is a closure of any other tests to run against the CommandBar
+    /// object.
+    fn run_event_test(
+        register_key: Option<char>,
+        start_mode: Option<InputMode>,
+        input_event: Option<Event>,
+        expected_event_result_option: Option<EventHandlerResult>,
+        expected_input_mode_option: Option<InputMode>,
+        other_tests: Option<&dyn Fn(CommandBar)>,
+    ) {
+        let mut command_bar_widget = CommandBar::default();
+        if let Some(k) = register_key {
+            command_bar_widget.set_command_key(k);
+        }
+
+        if let Some(start_mode) = start_mode {
+            command_bar_widget.input_mode = start_mode;
+        }
+
+        // If there is an input event, handle it and run any tests
+        if let Some(event) = input_event {
+            let event_result = handle_generic_event(&mut command_bar_widget, event);
+
+            if let Some(expected_event_result) = expected_event_result_option {
+                assert_eq!(event_result, expected_event_result);
+            }
+
+            if let Some(expected_input_mode) = expected_input_mode_option {
+                assert_eq!(command_bar_widget.input_mode, expected_input_mode);
+            }
+        }
+
+        // Run extra tests that were passed in
+        if let Some(f) = other_tests {
+            f(command_bar_widget);
+        }
+    }
+
+    #[test]
+    fn command_bar_registers_command_key() {
+        run_event_test(
+            Some(':'),
+            None,
+            None,
+            None,
+            None,
+            Some(&|command_bar_widget: CommandBar| {
+                assert!(command_bar_widget.command_key.is_some());
+                assert_eq!(command_bar_widget.command_key.unwrap(), ':'.into());
+            }),
+        );
+    }
+
+    #[test]
+    fn command_bar_handles_command_key() {
+        run_event_test(
+            Some(':'),
+            None,
+            Some(Event::Key(KeyEvent::new(
+                KeyCode::Char(':'),
+                KeyModifiers::NONE,
+            ))),
+            Some(EventHandlerResult::Ok),
+            Some(InputMode::Editing),
+            None,
+        );
+    }
+
+    #[test]
+    fn command_bar_handles_escape_key() {
+        run_event_test(
+            Some(':'),
+            Some(InputMode::Editing),
+            Some(Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))),
+            Some(EventHandlerResult::Ok),
+            Some(InputMode::Normal),
+            None,
+        );
+    }
+
+    #[test]
+    fn command_bar_handles_non_command_key_in_normal_mode() {
+        run_event_test(
+            Some(':'),
+            None,
+            Some(Event::Key(KeyEvent::new(
+                KeyCode::Char('a'),
+                KeyModifiers::NONE,
+            ))),
+            Some(EventHandlerResult::Unhandled(Event::Key(KeyEvent::new(
+                KeyCode::Char('a'),
+                KeyModifiers::NONE,
+            )))),
+            Some(InputMode::Normal),
+            None,
+        );
+    }
+
+    #[test]
+    fn command_bar_unregistered_handles_command_key_in_normal_mode() {
+        run_event_test(
+            None,
+            None,
+            Some(Event::Key(KeyEvent::new(
+                KeyCode::Char(':'),
+                KeyModifiers::NONE,
+            ))),
+            Some(EventHandlerResult::Unhandled(Event::Key(KeyEvent::new(
+                KeyCode::Char(':'),
+                KeyModifiers::NONE,
             )))),
             Some(InputMode::Normal),
             None,
@@ -512,166 +1983,1398 @@ mod tests {
     }
 
     #[test]
-    fn command_bar_handles_non_key_event_in_normal_mode() {
-        let mouse_event = MouseEvent {
-            kind: MouseEventKind::Moved,
-            column: 0,
-            row: 0,
-            modifiers: KeyModifiers::NONE,
-        };
-        run_event_test(
-            Some(':'),
-            None,
-            Some(Event::Mouse(mouse_event)),
-            Some(EventHandlerResult::Unhandled(Event::Mouse(mouse_event))),
-            Some(InputMode::Normal),
-            None,
+    fn command_bar_handles_non_key_event_in_normal_mode() {
+        let mouse_event = MouseEvent {
+            kind: MouseEventKind::Moved,
+            column: 0,
+            row: 0,
+            modifiers: KeyModifiers::NONE,
+        };
+        run_event_test(
+            Some(':'),
+            None,
+            Some(Event::Mouse(mouse_event)),
+            Some(EventHandlerResult::Unhandled(Event::Mouse(mouse_event))),
+            Some(InputMode::Normal),
+            None,
+        );
+    }
+
+    #[test]
+    fn command_bar_handles_non_key_event_in_normal_mode_with_unregistered_command_key() {
+        let mouse_event = MouseEvent {
+            kind: MouseEventKind::Moved,
+            column: 0,
+            row: 0,
+            modifiers: KeyModifiers::NONE,
+        };
+        run_event_test(
+            None,
+            None,
+            Some(Event::Mouse(mouse_event)),
+            Some(EventHandlerResult::Unhandled(Event::Mouse(mouse_event))),
+            Some(InputMode::Normal),
+            None,
+        );
+    }
+
+    #[test]
+    fn command_bar_unregistered_handles_command_key_in_editing_mode() {
+        // command editing via keyboard events requires a widget with valid
+        // length > 0, so we need to simulate the terminal
+        let backend = TestBackend::new(40, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| {
+                let area = Rect {
+                    x: 0,
+                    y: 0,
+                    width: 40,
+                    height: 4,
+                };
+                let mut command_bar_widget = CommandBar::default();
+
+                frame.render_widget(&mut command_bar_widget, area);
+            })
+            .unwrap();
+    }
+
+    /// Handle event read errors in normal mode, with unregistered command key
+    /// The current crossterm code doesn't seem to return read errors, but
+    /// we'll still test against the API
+    #[test]
+    fn command_bar_handles_event_read_error_in_normal_mode_with_unregistered_command_key() {
+        let mut command_bar_widget = CommandBar::default();
+
+        // test that event.read returning an error is handled correctly
+        let event_res = handle_error_event(&mut command_bar_widget);
+
+        assert!(matches!(event_res, EventHandlerResult::Err));
+        assert!(matches!(command_bar_widget.input_mode, InputMode::Normal));
+    }
+
+    /// Handle event read errors in normal mode
+    /// The current crossterm code doesn't seem to return read errors, but
+    /// we'll still test against the API
+    #[test]
+    fn command_bar_handles_event_read_error_in_normal_mode() {
+        let mut command_bar_widget = CommandBar::default();
+        command_bar_widget.register_key(':', CommandBar::command_key_handler);
+
+        // test that event.read returning an error is handled correctly
+        let event_res = handle_error_event(&mut command_bar_widget);
+
+        assert!(matches!(event_res, EventHandlerResult::Err));
+        assert!(matches!(command_bar_widget.input_mode, InputMode::Normal));
+    }
+
+    /// Handle event read errors in editing mode
+    /// The current crossterm code doesn't seem to return read errors, but
+    /// we'll still test against the API
+    #[test]
+    fn command_bar_handles_event_read_error_in_editing_mode() {
+        let mut command_bar_widget = CommandBar::default();
+        command_bar_widget.register_key(':', CommandBar::command_key_handler);
+
+        // enter editing mode
+        debug!("Entering editing mode");
+        command_bar_widget.input_mode = InputMode::Editing;
+
+        // test that event.read returning an error is handled correctly
+        let event_res = handle_error_event(&mut command_bar_widget);
+
+        assert!(matches!(event_res, EventHandlerResult::Err));
+        assert!(matches!(command_bar_widget.input_mode, InputMode::Editing));
+    }
+
+    #[test]
+    fn command_bar_sends_message() {
+        let (tx, rx) = mpsc::channel();
+        let mut command_bar_widget = CommandBar::default_with_tx_channel(tx);
+
+        command_bar_widget.register_key(':', CommandBar::command_key_handler);
+
+        let backend = TestBackend::new(40, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| {
+                let area = Rect {
+                    x: 0,
+                    y: 0,
+                    width: 40,
+                    height: 4,
+                };
+                frame.render_widget(&mut command_bar_widget, area);
+            })
+            .unwrap();
+        let event = Event::Key(KeyEvent::new(KeyCode::Char(':'), KeyModifiers::NONE));
+        handle_generic_event(&mut command_bar_widget, event);
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE));
+        handle_generic_event(&mut command_bar_widget, event);
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::NONE));
+        handle_generic_event(&mut command_bar_widget, event);
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        handle_generic_event(&mut command_bar_widget, event);
+
+        let received = rx.recv().unwrap();
+
+        assert_eq!(received, "ab");
+    }
+
+    /// Build a CommandBar in editing mode with room for input
+    fn editing_command_bar() -> CommandBar {
+        CommandBar {
+            input_mode: InputMode::Editing,
+            ..Default::default()
+        }
+    }
+
+    /// Send a sequence of unmodified key presses to the CommandBar
+    fn handle_key_codes(command_bar_widget: &mut CommandBar, codes: &[KeyCode]) {
+        for code in codes {
+            let event = Event::Key(KeyEvent::new(*code, KeyModifiers::NONE));
+            assert_eq!(
+                command_bar_widget.handle_key_event(event),
+                EventHandlerResult::Ok
+            );
+        }
+    }
+
+    #[test]
+    fn command_bar_handles_modified_command_key() {
+        let mut command_bar_widget = CommandBar::default();
+        let key: KeyCombination = "C-p".parse().unwrap();
+        command_bar_widget.set_command_key(key);
+
+        let plain_p = Event::Key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE));
+        assert_eq!(
+            command_bar_widget.handle_key_event(plain_p.clone()),
+            EventHandlerResult::Unhandled(plain_p)
+        );
+        let control_p = Event::Key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        assert_eq!(
+            command_bar_widget.handle_key_event(control_p.clone()),
+            EventHandlerResult::Ok
+        );
+        assert_eq!(command_bar_widget.input_mode, InputMode::Editing);
+
+        command_bar_widget.normal();
+        command_bar_widget.unregister_key(key);
+        assert_eq!(command_bar_widget.command_key, None);
+        assert_eq!(
+            command_bar_widget.handle_key_event(control_p.clone()),
+            EventHandlerResult::Unhandled(control_p)
+        );
+    }
+
+    #[test]
+    fn command_bar_key_handlers_own_their_state() {
+        let mut command_bar_widget = CommandBar::default();
+        let presses = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&presses);
+        command_bar_widget.register_key('c', move |_: &mut CommandBar, _| {
+            counter.set(counter.get() + 1);
+            HandlerOutcome::Handled
+        });
+        command_bar_widget.register_key('u', |_: &mut CommandBar, _| HandlerOutcome::Unhandled);
+
+        let key = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        for _ in 0..2 {
+            assert_eq!(
+                command_bar_widget.handle_key_event(key('c')),
+                EventHandlerResult::Ok
+            );
+        }
+        assert_eq!(presses.get(), 2);
+        assert_eq!(
+            command_bar_widget.handle_key_event(key('u')),
+            EventHandlerResult::Unhandled(key('u'))
+        );
+    }
+
+    #[test]
+    fn command_bar_key_handler_can_replace_itself() {
+        let mut command_bar_widget = CommandBar::default();
+        command_bar_widget.register_key('r', |cb: &mut CommandBar, _| {
+            cb.register_key('r', CommandBar::command_key_handler);
+            HandlerOutcome::Handled
+        });
+
+        let key = Event::Key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE));
+        command_bar_widget.handle_key_event(key.clone());
+        assert_eq!(command_bar_widget.input_mode, InputMode::Normal);
+        command_bar_widget.handle_key_event(key);
+        assert_eq!(command_bar_widget.input_mode, InputMode::Editing);
+    }
+
+    /// Build a CommandBar with handlers for sequences that record the
+    /// sequence that ran
+    fn command_bar_with_sequences(sequences: &[&str]) -> (CommandBar, Rc<RefCell<Vec<String>>>) {
+        let mut command_bar_widget = CommandBar::default();
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        for sequence in sequences {
+            let ran = Rc::clone(&ran);
+            let name = sequence.to_string();
+            let keys: KeySequence = sequence.parse().unwrap();
+            command_bar_widget.register_key(keys, move |_: &mut CommandBar, _| {
+                ran.borrow_mut().push(name.clone());
+                HandlerOutcome::Handled
+            });
+        }
+        (command_bar_widget, ran)
+    }
+
+    fn press(command_bar_widget: &mut CommandBar, keys: &str) -> Vec<EventHandlerResult> {
+        let keys: KeySequence = keys.parse().unwrap();
+        keys.keys
+            .iter()
+            .map(|k| {
+                command_bar_widget.handle_key_event(Event::Key(KeyEvent::new(k.code, k.modifiers)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn command_bar_lists_bindings_for_help() {
+        let (mut command_bar_widget, _) = command_bar_with_sequences(&["g g", "q"]);
+        command_bar_widget.key_database.describe('q', "quit");
+        command_bar_widget
+            .keymap_mut(InputMode::Normal)
+            .bind('q', Action::Edit);
+        command_bar_widget
+            .keymap_mut(InputMode::Normal)
+            .bind('g', Action::Edit);
+
+        let bindings: Vec<(String, String)> = command_bar_widget
+            .bindings(InputMode::Normal)
+            .into_iter()
+            .map(|b| (b.keys.to_string(), b.description))
+            .collect();
+        assert_eq!(
+            bindings,
+            [
+                (String::from("g"), String::from("start editing the command")),
+                (String::from("g g"), String::from("run a key handler")),
+                (String::from("q"), String::from("quit")),
+            ]
+        );
+        assert_eq!(
+            command_bar_widget.conflicts(InputMode::Normal),
+            vec![
+                Conflict::Shadowed {
+                    keys: 'g'.into(),
+                    by: "g g".parse().unwrap(),
+                },
+                Conflict::Duplicate {
+                    keys: 'q'.into(),
+                    action: Action::Edit,
+                },
+            ]
+        );
+        assert_eq!(command_bar_widget.overrides(InputMode::Normal).len(), 2);
+        assert!(command_bar_widget.overrides(InputMode::Editing).is_empty());
+        assert_eq!(
+            command_bar_widget.bindings(InputMode::Editing).len(),
+            Keymap::editing().bindings.len()
+        );
+
+        command_bar_widget.unregister_key('q');
+        assert!(command_bar_widget.key_database.descriptions.is_empty());
+    }
+
+    #[test]
+    fn command_bar_runs_key_sequences() {
+        let (mut command_bar_widget, ran) = command_bar_with_sequences(&["g g", "C-x C-s"]);
+
+        assert_eq!(
+            press(&mut command_bar_widget, "g"),
+            vec![EventHandlerResult::Ok]
+        );
+        assert!(ran.borrow().is_empty());
+        assert_eq!(
+            command_bar_widget.key_database.pending_keys().to_string(),
+            "g"
+        );
+        press(&mut command_bar_widget, "g C-x C-s");
+        assert_eq!(*ran.borrow(), vec!["g g", "C-x C-s"]);
+        assert!(command_bar_widget.key_database.pending.is_empty());
+    }
+
+    #[test]
+    fn command_bar_restarts_broken_key_sequences() {
+        let (mut command_bar_widget, ran) = command_bar_with_sequences(&["g", "g g", "C-x C-s"]);
+
+        // g runs before the key that broke its sequence is handled
+        let x = Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert_eq!(
+            press(&mut command_bar_widget, "g x"),
+            vec![EventHandlerResult::Ok, EventHandlerResult::Unhandled(x)]
+        );
+        assert_eq!(*ran.borrow(), vec!["g"]);
+
+        // C-x isn't bound on its own, so it's dropped and g starts again
+        press(&mut command_bar_widget, "C-x g g");
+        assert_eq!(*ran.borrow(), vec!["g", "g g"]);
+    }
+
+    #[test]
+    fn command_bar_key_sequence_timeout() {
+        let (mut command_bar_widget, ran) = command_bar_with_sequences(&["g", "g g", "C-x C-s"]);
+
+        // An empty scripted source times out straight away
+        let mut source = ScriptedEventSource::default();
+        source.push_str("g");
+        command_bar_widget.handle_event_from(&mut source);
+        assert!(command_bar_widget.key_timeout().is_some());
+        assert_eq!(
+            command_bar_widget.handle_event_from(&mut source),
+            EventHandlerResult::Ok
+        );
+        assert_eq!(*ran.borrow(), vec!["g"]);
+        assert_eq!(command_bar_widget.key_timeout(), None);
+
+        // Keys pressed after the timeout start a new sequence
+        command_bar_widget.key_database.timeout = Some(Duration::ZERO);
+        press(&mut command_bar_widget, "C-x C-s");
+        assert_eq!(*ran.borrow(), vec!["g"]);
+        press(&mut command_bar_widget, "g");
+        assert!(command_bar_widget.handle_timeout());
+        assert_eq!(*ran.borrow(), vec!["g", "g"]);
+
+        // Without a timeout the sequence waits for the next key
+        command_bar_widget.key_database.timeout = None;
+        press(&mut command_bar_widget, "C-x");
+        assert!(!command_bar_widget.handle_timeout());
+        assert_eq!(command_bar_widget.key_timeout(), None);
+        press(&mut command_bar_widget, "C-s");
+        assert_eq!(*ran.borrow(), vec!["g", "g", "C-x C-s"]);
+    }
+
+    #[test]
+    fn command_bar_renders_pending_keys() {
+        let (mut command_bar_widget, _) = command_bar_with_sequences(&["C-x C-s"]);
+        press(&mut command_bar_widget, "C-x");
+
+        let backend = TestBackend::new(20, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| f.render_widget(&mut command_bar_widget, f.size()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let title: String = (1..12).map(|x| buffer.get(x, 0).symbol.clone()).collect();
+        assert_eq!(title, "Command C-x");
+        assert_eq!(buffer.get(9, 0).fg, Color::Cyan);
+    }
+
+    /// A CommandBar editing with vi keys, in vi normal mode at cursor
+    fn vi_command_bar(input: &str, cursor: usize) -> CommandBar {
+        let mut command_bar_widget = CommandBar {
+            editing_keys: EditingKeys::Vi,
+            ..Default::default()
+        };
+        command_bar_widget.edit();
+        command_bar_widget.input = String::from(input);
+        command_bar_widget.vi.mode = ViMode::Normal;
+        command_bar_widget.cursor = cursor;
+        command_bar_widget
+    }
+
+    #[test]
+    fn command_bar_vi_motions() {
+        let mut command_bar_widget = vi_command_bar("open ./my-file.txt now", 0);
+        let moves = [
+            ("w", 5),
+            ("w", 7),
+            ("2 w", 10),
+            ("e", 13),
+            ("b", 10),
+            ("$", 21),
+            ("0", 0),
+            ("f .", 5),
+            ("f .", 14),
+            ("T /", 7),
+            ("t x", 15),
+            ("3 h", 12),
+            ("l", 13),
+            ("2 0 l", 21),
+        ];
+        for (keys, cursor) in moves {
+            press(&mut command_bar_widget, keys);
+            assert_eq!(command_bar_widget.cursor, cursor, "after {}", keys);
+        }
+        // A missing character doesn't move the cursor
+        press(&mut command_bar_widget, "F z");
+        assert_eq!(command_bar_widget.cursor, 21);
+        assert_eq!(command_bar_widget.input_mode, InputMode::Editing);
+    }
+
+    #[test]
+    fn command_bar_vi_operators() {
+        let mut command_bar_widget = vi_command_bar("write all the files now", 6);
+        press(&mut command_bar_widget, "d w");
+        assert_eq!(command_bar_widget.input, "write the files now");
+        press(&mut command_bar_widget, "2 d w");
+        assert_eq!(command_bar_widget.input, "write now");
+        assert_eq!(
+            command_bar_widget.registers.borrow().kill_ring.yank(),
+            Some("the files ")
+        );
+        press(&mut command_bar_widget, "y e");
+        assert_eq!(
+            command_bar_widget.registers.borrow().kill_ring.yank(),
+            Some("now")
+        );
+        press(&mut command_bar_widget, "0 P");
+        assert_eq!(command_bar_widget.input, "nowwrite now");
+        assert_eq!(command_bar_widget.cursor, 2);
+        press(&mut command_bar_widget, "x");
+        assert_eq!(command_bar_widget.input, "nowrite now");
+        press(&mut command_bar_widget, "$ d 0");
+        assert_eq!(command_bar_widget.input, "w");
+        press(&mut command_bar_widget, "d d");
+        assert_eq!(command_bar_widget.input, "");
+        assert_eq!(command_bar_widget.cursor, 0);
+
+        // cw on the last grapheme of a word only changes that grapheme
+        let mut command_bar_widget = vi_command_bar("open file", 3);
+        press(&mut command_bar_widget, "c w");
+        assert_eq!(command_bar_widget.input, "ope file");
+        let mut command_bar_widget = vi_command_bar("a b", 0);
+        press(&mut command_bar_widget, "c w");
+        assert_eq!(command_bar_widget.input, " b");
+        let mut command_bar_widget = vi_command_bar("a b c", 0);
+        press(&mut command_bar_widget, "c 2 w");
+        assert_eq!(command_bar_widget.input, " c");
+    }
+
+    #[test]
+    fn command_bar_vi_runs_a_zero_count_once() {
+        let mut command_bar_widget = vi_command_bar("open a file", 0);
+        command_bar_widget.run_vi_command(
+            Some(0),
+            ViCommand::Move(Motion::Find {
+                c: 'f',
+                forward: true,
+                till: false,
+            }),
+        );
+        assert_eq!(command_bar_widget.cursor, 7);
+        command_bar_widget.run_vi_command(
+            Some(0),
+            ViCommand::Operate(Operator::Change, Motion::WordForward),
+        );
+        assert_eq!(command_bar_widget.input, "open a ");
+    }
+
+    #[test]
+    fn command_bar_vi_clamps_counts() {
+        let huge = "9 9 9 9 9 9 9 9 9 9 9 9 9 9 9 9 9 9 9 9";
+        let mut command_bar_widget = vi_command_bar("open a file", 5);
+        let moves = [("w", 10), ("b", 0), ("e", 10), ("h", 0), ("l", 10)];
+        for (motion, cursor) in moves {
+            press(&mut command_bar_widget, &format!("{} {}", huge, motion));
+            assert_eq!(command_bar_widget.cursor, cursor, "after {}", motion);
+        }
+
+        // Pasting stops at max_length
+        command_bar_widget.max_length = Some(15);
+        press(&mut command_bar_widget, "0 y w");
+        press(&mut command_bar_widget, &format!("$ {} p", huge));
+        assert_eq!(command_bar_widget.input, "open a fileopen");
+        // Without max_length the count is clamped
+        command_bar_widget.max_length = None;
+        press(&mut command_bar_widget, &format!("{} P", huge));
+        assert_eq!(command_bar_widget.grapheme_count(), 15 + 5 * MAX_COUNT);
+
+        press(&mut command_bar_widget, &format!("{} u", huge));
+        assert_eq!(command_bar_widget.input, "open a file");
+    }
+
+    #[test]
+    fn command_bar_vi_changes_and_repeats() {
+        let mut command_bar_widget = vi_command_bar("open one two", 5);
+        press(&mut command_bar_widget, "c w");
+        assert_eq!(command_bar_widget.vi.mode, ViMode::Insert);
+        assert_eq!(command_bar_widget.input, "open  two");
+        press(&mut command_bar_widget, "1 Esc");
+        assert_eq!(command_bar_widget.input, "open 1 two");
+        assert_eq!(command_bar_widget.cursor, 5);
+
+        press(&mut command_bar_widget, "w .");
+        assert_eq!(command_bar_widget.input, "open 1 1");
+        press(&mut command_bar_widget, "0 i");
+        press(&mut command_bar_widget, "x Space Esc");
+        press(&mut command_bar_widget, "$ .");
+        assert_eq!(command_bar_widget.input, "x open 1 x 1");
+
+        press(&mut command_bar_widget, "u");
+        assert_eq!(command_bar_widget.input, "x open 1 1");
+        press(&mut command_bar_widget, "2 u");
+        assert_eq!(command_bar_widget.input, "open 1 two");
+
+        // Esc in vi normal mode stops editing
+        press(&mut command_bar_widget, "Esc");
+        assert_eq!(command_bar_widget.input_mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn command_bar_vi_submits_from_either_mode() {
+        let mut command_bar_widget = vi_command_bar("", 0);
+        command_bar_widget.vi.mode = ViMode::Insert;
+        press(&mut command_bar_widget, "q Esc A");
+        press(&mut command_bar_widget, "a Enter");
+        assert_eq!(command_bar_widget.history.entries, vec!["qa"]);
+
+        command_bar_widget.edit();
+        assert_eq!(command_bar_widget.vi.mode, ViMode::Insert);
+        press(&mut command_bar_widget, "w Esc Enter");
+        assert_eq!(command_bar_widget.history.entries, vec!["qa", "w"]);
+    }
+
+    #[test]
+    fn command_bar_renders_vi_mode() {
+        let mut command_bar_widget = vi_command_bar("open", 0);
+        press(&mut command_bar_widget, "2 d");
+
+        let backend = TestBackend::new(30, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| f.render_widget(&mut command_bar_widget, f.size()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let title: String = (1..21).map(|x| buffer.get(x, 0).symbol.clone()).collect();
+        assert_eq!(title, "Command [normal] 2d─");
+    }
+
+    #[test]
+    fn command_bar_remaps_editing_actions() {
+        let mut command_bar_widget = CommandBar::default();
+        let keymap = command_bar_widget.keymap_mut(InputMode::Normal);
+        keymap.bind('i', Action::Edit);
+        let keymap = command_bar_widget.keymap_mut(InputMode::Editing);
+        keymap.bind(
+            KeyCombination::new(KeyCode::Char('j'), KeyModifiers::CONTROL),
+            Action::Submit,
+        );
+        keymap.unbind(KeyCode::Enter);
+
+        press(&mut command_bar_widget, "i w q");
+        assert_eq!(command_bar_widget.input, "wq");
+        let enter = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(
+            command_bar_widget.handle_key_event(enter.clone()),
+            EventHandlerResult::Unhandled(enter)
+        );
+        press(&mut command_bar_widget, "C-j");
+        assert_eq!(command_bar_widget.input_mode, InputMode::Normal);
+        assert_eq!(command_bar_widget.history.entries, vec!["wq"]);
+    }
+
+    #[test]
+    fn command_bar_deletes_word_backward() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.input = String::from("open my  file");
+        command_bar_widget.cursor = 9;
+
+        press(&mut command_bar_widget, "C-w");
+        assert_eq!(command_bar_widget.input, "open file");
+        assert_eq!(command_bar_widget.cursor, 5);
+        press(&mut command_bar_widget, "C-w C-w");
+        assert_eq!(command_bar_widget.input, "file");
+        assert_eq!(command_bar_widget.cursor, 0);
+    }
+
+    #[test]
+    fn command_bar_moves_by_readline_keys() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.input = String::from("open ./my-file.txt");
+        command_bar_widget.cursor = 18;
+
+        let moves = [
+            ("M-b", 15),
+            ("M-b", 10),
+            ("M-b", 7),
+            ("M-f", 9),
+            ("C-a", 0),
+            ("M-f", 4),
+            ("C-f", 5),
+            ("C-e", 18),
+            ("C-b", 17),
+        ];
+        for (keys, cursor) in moves {
+            press(&mut command_bar_widget, keys);
+            assert_eq!(command_bar_widget.cursor, cursor, "after {}", keys);
+        }
+    }
+
+    #[test]
+    fn command_bar_kills_and_yanks() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.input = String::from("write all files");
+        command_bar_widget.cursor = 6;
+
+        press(&mut command_bar_widget, "M-d");
+        assert_eq!(command_bar_widget.input, "write  files");
+        press(&mut command_bar_widget, "C-k");
+        assert_eq!(command_bar_widget.input, "write ");
+        // Consecutive kills are yanked back together
+        assert_eq!(
+            command_bar_widget.registers.borrow().kill_ring.yank(),
+            Some("all files")
+        );
+
+        press(&mut command_bar_widget, "C-a C-y");
+        assert_eq!(command_bar_widget.input, "all fileswrite ");
+        assert_eq!(command_bar_widget.cursor, 9);
+        press(&mut command_bar_widget, "C-u");
+        assert_eq!(command_bar_widget.input, "write ");
+        assert_eq!(
+            command_bar_widget
+                .registers
+                .borrow()
+                .kill_ring
+                .entries
+                .len(),
+            2
+        );
+
+        command_bar_widget.max_length = Some(10);
+        press(&mut command_bar_widget, "C-e C-y");
+        assert_eq!(command_bar_widget.input, "write all ");
+    }
+
+    #[test]
+    fn command_bar_undoes_and_redoes_changes() {
+        let mut command_bar_widget = editing_command_bar();
+        press(&mut command_bar_widget, "o p e n Space");
+        press(&mut command_bar_widget, "Left Right f i l e C-w");
+        assert_eq!(command_bar_widget.input, "open ");
+
+        press(&mut command_bar_widget, "C-_");
+        assert_eq!(command_bar_widget.input, "open file");
+        // Typing is undone a run at a time, a moved cursor ends the run
+        press(&mut command_bar_widget, "C-7");
+        assert_eq!(command_bar_widget.input, "open ");
+        assert_eq!(command_bar_widget.cursor, 5);
+        press(&mut command_bar_widget, "C-z");
+        assert_eq!(command_bar_widget.input, "");
+        press(&mut command_bar_widget, "C-z");
+        assert_eq!(command_bar_widget.input, "");
+
+        press(&mut command_bar_widget, "M-_ M-_");
+        assert_eq!(command_bar_widget.input, "open file");
+        assert_eq!(command_bar_widget.cursor, 9);
+        // A new change can't be redone past
+        press(&mut command_bar_widget, "C-_ x M-_");
+        assert_eq!(command_bar_widget.input, "open x");
+    }
+
+    #[test]
+    fn command_bar_records_a_prefix_that_falls_through_once() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.keymap_mut(InputMode::Editing).bind(
+            "C-k C-k".parse::<KeySequence>().unwrap(),
+            Action::KillToStart,
+        );
+        command_bar_widget.input = String::from("abcd");
+        command_bar_widget.cursor = 2;
+
+        // C-k waits for the next key, then kills to the end when x doesn't
+        // continue the sequence, and x is typed
+        press(&mut command_bar_widget, "C-k x");
+        assert_eq!(command_bar_widget.input, "abx");
+        assert_eq!(command_bar_widget.undo_history.undo.len(), 1);
+        press(&mut command_bar_widget, "C-z");
+        assert_eq!(command_bar_widget.input, "abcd");
+    }
+
+    /// An editing CommandBar with j k bound to Cancel
+    fn command_bar_with_editing_sequence() -> CommandBar {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget
+            .keymap_mut(InputMode::Editing)
+            .bind("j k".parse::<KeySequence>().unwrap(), Action::Cancel);
+        command_bar_widget
+    }
+
+    #[test]
+    fn command_bar_types_a_prefix_that_times_out() {
+        let mut command_bar_widget = command_bar_with_editing_sequence();
+        command_bar_widget.key_database.timeout = Some(Duration::ZERO);
+
+        press(&mut command_bar_widget, "a j");
+        assert_eq!(command_bar_widget.input, "a");
+        assert!(command_bar_widget.handle_timeout());
+        assert_eq!(command_bar_widget.input, "aj");
+        assert_eq!(command_bar_widget.input_mode, InputMode::Editing);
+
+        // The typed prefix is its own change
+        press(&mut command_bar_widget, "C-z");
+        assert_eq!(command_bar_widget.input, "a");
+
+        // An empty scripted source times out straight away
+        command_bar_widget.key_database.timeout = Some(Duration::from_secs(60));
+        let mut source = ScriptedEventSource::default();
+        source.push_str("j");
+        command_bar_widget.handle_event_from(&mut source);
+        assert_eq!(
+            command_bar_widget.handle_event_from(&mut source),
+            EventHandlerResult::Ok
         );
+        assert_eq!(command_bar_widget.input, "aj");
     }
 
     #[test]
-    fn command_bar_handles_non_key_event_in_normal_mode_with_unregistered_command_key() {
-        let mouse_event = MouseEvent {
-            kind: MouseEventKind::Moved,
-            column: 0,
-            row: 0,
-            modifiers: KeyModifiers::NONE,
+    fn command_bar_types_a_broken_prefix() {
+        let mut command_bar_widget = command_bar_with_editing_sequence();
+
+        press(&mut command_bar_widget, "j x");
+        assert_eq!(command_bar_widget.input, "jx");
+        assert!(command_bar_widget.key_database.pending.is_empty());
+
+        // A prefix broken by a bound key is typed before the key runs
+        press(&mut command_bar_widget, "j Left j j k");
+        assert_eq!(command_bar_widget.input, "jxjj");
+        assert_eq!(command_bar_widget.input_mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn command_bar_clears_undo_history_when_editing_ends() {
+        let mut command_bar_widget = editing_command_bar();
+        press(&mut command_bar_widget, "w q Enter");
+        assert!(command_bar_widget.undo_history.undo.is_empty());
+
+        command_bar_widget.edit();
+        press(&mut command_bar_widget, "q Esc");
+        assert!(command_bar_widget.undo_history.undo.is_empty());
+        command_bar_widget.edit();
+        press(&mut command_bar_widget, "C-z");
+        assert_eq!(command_bar_widget.input, "q");
+    }
+
+    #[test]
+    fn command_bar_yank_pop_cycles_kills() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.input = String::from("one two three");
+        command_bar_widget.cursor = 13;
+        press(&mut command_bar_widget, "C-w Left C-w Left C-w");
+        assert_eq!(command_bar_widget.input, "  ");
+
+        press(&mut command_bar_widget, "C-e C-y");
+        assert_eq!(command_bar_widget.input, "  one");
+        press(&mut command_bar_widget, "M-y");
+        assert_eq!(command_bar_widget.input, "  two");
+        press(&mut command_bar_widget, "M-y");
+        assert_eq!(command_bar_widget.input, "  three");
+        press(&mut command_bar_widget, "M-y");
+        assert_eq!(command_bar_widget.input, "  one");
+        assert_eq!(command_bar_widget.cursor, 5);
+
+        // Yank-pop only replaces text right after a yank
+        press(&mut command_bar_widget, "Left M-y");
+        assert_eq!(command_bar_widget.input, "  one");
+    }
+
+    #[test]
+    fn command_bar_shares_registers() {
+        let mut first = editing_command_bar();
+        let mut second = CommandBar {
+            registers: Rc::clone(&first.registers),
+            ..editing_command_bar()
         };
-        run_event_test(
-            None,
-            None,
-            Some(Event::Mouse(mouse_event)),
-            Some(EventHandlerResult::Unhandled(Event::Mouse(mouse_event))),
-            Some(InputMode::Normal),
-            None,
+        press(&mut first, "l s C-u");
+        press(&mut second, "C-y");
+        assert_eq!(second.input, "ls");
+
+        // Vi named registers are shared too
+        second.editing_keys = EditingKeys::Vi;
+        press(&mut second, "Esc \" a y y");
+        first.editing_keys = EditingKeys::Vi;
+        press(&mut first, "Esc \" a p");
+        assert_eq!(first.input, "ls");
+        assert_eq!(first.registers.borrow().get('a'), Some("ls"));
+        assert_eq!(first.registers.borrow().kill_ring.entries, vec!["ls"]);
+    }
+
+    #[test]
+    fn command_bar_transposes_characters() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.input = String::from("qiut");
+        command_bar_widget.cursor = 2;
+
+        press(&mut command_bar_widget, "C-t");
+        assert_eq!(command_bar_widget.input, "quit");
+        assert_eq!(command_bar_widget.cursor, 3);
+        // At the end the last two characters are swapped
+        press(&mut command_bar_widget, "C-e C-t");
+        assert_eq!(command_bar_widget.input, "quti");
+        press(&mut command_bar_widget, "C-a C-t");
+        assert_eq!(command_bar_widget.input, "quti");
+    }
+
+    #[test]
+    fn command_bar_handles_events_read_by_the_app() {
+        let mut command_bar_widget = CommandBar::default();
+        command_bar_widget.register_key(':', CommandBar::command_key_handler);
+
+        let key = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        assert_eq!(
+            command_bar_widget.handle_key_event(key('x')),
+            EventHandlerResult::Unhandled(key('x'))
         );
+        assert_eq!(
+            command_bar_widget.handle_key_event(key(':')),
+            EventHandlerResult::Ok
+        );
+        assert_eq!(command_bar_widget.input_mode, InputMode::Editing);
+        assert_eq!(
+            command_bar_widget.handle_key_event(key('x')),
+            EventHandlerResult::Ok
+        );
+        assert_eq!(command_bar_widget.input, "x");
     }
 
     #[test]
-    fn command_bar_unregistered_handles_command_key_in_editing_mode() {
-        // command editing via keyboard events requires a widget with valid
-        // length > 0, so we need to simulate the terminal
-        let backend = TestBackend::new(40, 4);
+    fn command_bar_pastes_as_one_change() {
+        let paste = |text: &str| Event::Paste(String::from(text));
+        let mut command_bar_widget = CommandBar::default();
+        assert_eq!(
+            command_bar_widget.handle_key_event(paste("ls")),
+            EventHandlerResult::Unhandled(paste("ls"))
+        );
+
+        command_bar_widget.input_mode = InputMode::Editing;
+        press(&mut command_bar_widget, "o p e n Space");
+        assert_eq!(
+            command_bar_widget.handle_key_event(paste("one\r\ntwo\n")),
+            EventHandlerResult::Ok
+        );
+        assert_eq!(command_bar_widget.input, "open one two");
+        assert_eq!(command_bar_widget.cursor, 12);
+        press(&mut command_bar_widget, "C-z");
+        assert_eq!(command_bar_widget.input, "open ");
+
+        // Escape sequences and other control characters aren't pasted
+        command_bar_widget.handle_key_event(paste("\x1b[31mone\x1b[0m\ttwo"));
+        assert_eq!(command_bar_widget.input, "open one two");
+        press(&mut command_bar_widget, "C-z");
+
+        command_bar_widget.paste_policy = PastePolicy::Reject;
+        assert_eq!(
+            command_bar_widget.handle_key_event(paste("one\ntwo")),
+            EventHandlerResult::Unhandled(paste("one\ntwo"))
+        );
+        assert_eq!(command_bar_widget.input, "open ");
+
+        command_bar_widget.max_length = Some(8);
+        command_bar_widget.handle_key_event(paste("file.txt\n"));
+        assert_eq!(command_bar_widget.input, "open fil");
+    }
+
+    #[test]
+    fn command_bar_inserts_at_cursor() {
+        let mut command_bar_widget = editing_command_bar();
+        handle_key_codes(
+            &mut command_bar_widget,
+            &[
+                KeyCode::Char('a'),
+                KeyCode::Char('c'),
+                KeyCode::Left,
+                KeyCode::Char('b'),
+            ],
+        );
+        assert_eq!(command_bar_widget.input, "abc");
+        assert_eq!(command_bar_widget.cursor, 2);
+
+        handle_key_codes(
+            &mut command_bar_widget,
+            &[
+                KeyCode::Home,
+                KeyCode::Char('>'),
+                KeyCode::End,
+                KeyCode::Char('<'),
+            ],
+        );
+        assert_eq!(command_bar_widget.input, ">abc<");
+        assert_eq!(command_bar_widget.cursor, 5);
+    }
+
+    #[test]
+    fn command_bar_deletes_at_cursor() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.input = String::from("abcd");
+        command_bar_widget.cursor = 2;
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Delete]);
+        assert_eq!(command_bar_widget.input, "abd");
+        assert_eq!(command_bar_widget.cursor, 2);
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Backspace]);
+        assert_eq!(command_bar_widget.input, "ad");
+        assert_eq!(command_bar_widget.cursor, 1);
+
+        // Deleting past either end of the input does nothing
+        handle_key_codes(
+            &mut command_bar_widget,
+            &[
+                KeyCode::End,
+                KeyCode::Delete,
+                KeyCode::Home,
+                KeyCode::Backspace,
+            ],
+        );
+        assert_eq!(command_bar_widget.input, "ad");
+        assert_eq!(command_bar_widget.cursor, 0);
+    }
+
+    #[test]
+    fn command_bar_cursor_moves_by_grapheme() {
+        let mut command_bar_widget = editing_command_bar();
+        // e followed by a combining acute accent is one grapheme
+        command_bar_widget.input = String::from("e\u{301}x");
+        command_bar_widget.move_cursor_end();
+        assert_eq!(command_bar_widget.cursor, 2);
+
+        handle_key_codes(
+            &mut command_bar_widget,
+            &[KeyCode::Left, KeyCode::Backspace],
+        );
+        assert_eq!(command_bar_widget.input, "x");
+        assert_eq!(command_bar_widget.cursor, 0);
+    }
+
+    #[test]
+    fn command_bar_clamps_cursor_after_input_changes() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.input = String::from("abc");
+        command_bar_widget.cursor = 10;
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Left]);
+        assert_eq!(command_bar_widget.cursor, 2);
+    }
+
+    #[test]
+    fn command_bar_cursor_position() {
+        let mut command_bar_widget = editing_command_bar();
+        let area = Rect::new(2, 5, 40, 3);
+        assert_eq!(command_bar_widget.cursor_position(area), (3, 6));
+
+        // Wide characters take up two terminal cells
+        command_bar_widget.input = String::from("a\u{4e2d}b");
+        command_bar_widget.cursor = 2;
+        assert_eq!(command_bar_widget.cursor_position(area), (6, 6));
+    }
+
+    #[test]
+    fn command_bar_browses_history() {
+        let mut command_bar_widget = editing_command_bar();
+        for entry in ["first", "second", "second"] {
+            command_bar_widget.input = String::from(entry);
+            command_bar_widget.submit().unwrap();
+        }
+        assert_eq!(command_bar_widget.history.entries, vec!["first", "second"]);
+        // messages keeps every submitted line, even the repeats
+        assert_eq!(
+            command_bar_widget.messages,
+            vec!["first", "second", "second"]
+        );
+
+        command_bar_widget.input = String::from("draft");
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Up, KeyCode::Up]);
+        assert_eq!(command_bar_widget.input, "first");
+        assert_eq!(command_bar_widget.cursor, 5);
+
+        // Stepping past the oldest entry leaves the input alone
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Up]);
+        assert_eq!(command_bar_widget.input, "first");
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Down, KeyCode::Down]);
+        assert_eq!(command_bar_widget.input, "draft");
+
+        // Down without browsing does nothing
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Down]);
+        assert_eq!(command_bar_widget.input, "draft");
+    }
+
+    /// Build a CommandBar with a history of submitted entries
+    fn command_bar_with_history(entries: &[&str]) -> CommandBar {
+        let mut command_bar_widget = editing_command_bar();
+        for entry in entries {
+            command_bar_widget.input = String::from(*entry);
+            command_bar_widget.submit().unwrap();
+        }
+        command_bar_widget
+    }
+
+    fn control_r() -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL))
+    }
+
+    #[test]
+    fn command_bar_reverse_search_accepts_match() {
+        let mut command_bar_widget = command_bar_with_history(&["write", "wq", "quit"]);
+        command_bar_widget.input = String::from("draft");
+
+        handle_generic_event(&mut command_bar_widget, control_r());
+        assert!(command_bar_widget.search.is_some());
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Char('w')]);
+        // The input isn't touched until the search is accepted
+        assert_eq!(command_bar_widget.input, "draft");
+
+        handle_generic_event(&mut command_bar_widget, control_r());
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Enter]);
+        assert!(command_bar_widget.search.is_none());
+        assert_eq!(command_bar_widget.input, "write");
+        assert_eq!(command_bar_widget.input_mode, InputMode::Editing);
+    }
+
+    #[test]
+    fn command_bar_reverse_search_cancel_restores_draft() {
+        let mut command_bar_widget = command_bar_with_history(&["write"]);
+        command_bar_widget.input = String::from("draft");
+
+        handle_generic_event(&mut command_bar_widget, control_r());
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Char('w'), KeyCode::Esc]);
+        assert!(command_bar_widget.search.is_none());
+        assert_eq!(command_bar_widget.input, "draft");
+        assert_eq!(command_bar_widget.input_mode, InputMode::Editing);
+    }
+
+    #[test]
+    fn command_bar_reverse_search_cancel_restores_draft_after_browsing() {
+        let mut command_bar_widget = command_bar_with_history(&["open", "write"]);
+        handle_key_codes(
+            &mut command_bar_widget,
+            &[KeyCode::Char('d'), KeyCode::Char('r'), KeyCode::Up],
+        );
+        assert_eq!(command_bar_widget.input, "write");
+
+        handle_generic_event(&mut command_bar_widget, control_r());
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Char('o'), KeyCode::Esc]);
+        assert!(command_bar_widget.search.is_none());
+        assert_eq!(command_bar_widget.input, "dr");
+        assert_eq!(command_bar_widget.cursor, 2);
+        assert!(!command_bar_widget.history.is_browsing());
+    }
+
+    #[test]
+    fn command_bar_reverse_search_other_keys_accept_and_edit() {
+        let mut command_bar_widget = command_bar_with_history(&["write"]);
+
+        handle_generic_event(&mut command_bar_widget, control_r());
+        handle_key_codes(
+            &mut command_bar_widget,
+            &[KeyCode::Char('r'), KeyCode::Home],
+        );
+        assert!(command_bar_widget.search.is_none());
+        assert_eq!(command_bar_widget.input, "write");
+        assert_eq!(command_bar_widget.cursor, 0);
+    }
+
+    #[test]
+    fn command_bar_reverse_search_uses_the_keymap() {
+        let mut command_bar_widget = command_bar_with_history(&["write one", "write two"]);
+        let keymap = command_bar_widget.keymap_mut(InputMode::Editing);
+        keymap.unbind(KeyCombination::new(
+            KeyCode::Char('r'),
+            KeyModifiers::CONTROL,
+        ));
+        keymap.bind(
+            KeyCombination::new(KeyCode::Char('s'), KeyModifiers::CONTROL),
+            Action::ReverseSearch,
+        );
+        keymap.bind(
+            KeyCombination::new(KeyCode::Char('g'), KeyModifiers::CONTROL),
+            Action::Cancel,
+        );
+
+        press(&mut command_bar_widget, "C-s w C-s Enter");
+        assert_eq!(command_bar_widget.input, "write one");
+        press(&mut command_bar_widget, "C-s w C-g");
+        assert!(command_bar_widget.search.is_none());
+        assert_eq!(command_bar_widget.input, "write one");
+        command_bar_widget.input.clear();
+
+        // Alt chords end the search instead of being searched for
+        let alt_x = Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT));
+        press(&mut command_bar_widget, "C-s w");
+        assert_eq!(
+            press(&mut command_bar_widget, "M-x"),
+            vec![EventHandlerResult::Unhandled(alt_x)]
+        );
+        assert!(command_bar_widget.search.is_none());
+        assert_eq!(command_bar_widget.input, "write two");
+    }
+
+    #[test]
+    fn command_bar_reverse_search_renders_prompt() {
+        let mut command_bar_widget = command_bar_with_history(&["ls"]);
+        handle_generic_event(&mut command_bar_widget, control_r());
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Char('s')]);
+
+        let backend = TestBackend::new(40, 3);
         let mut terminal = Terminal::new(backend).unwrap();
+        let area = Rect::new(0, 0, 40, 3);
         terminal
-            .draw(|frame| {
-                let area = Rect {
-                    x: 0,
-                    y: 0,
-                    width: 40,
-                    height: 4,
-                };
-                let mut command_bar_widget = CommandBar::default();
-
-                frame.render_widget(&mut command_bar_widget, area);
-            })
+            .draw(|frame| frame.render_widget(&mut command_bar_widget, area))
             .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let rendered: String = (0..40).map(|x| buffer.get(x, 1).symbol.clone()).collect();
+        assert_eq!(rendered, "│(reverse-i-search)`s': ls             │");
+        // The cursor is on the matched text
+        assert_eq!(command_bar_widget.cursor_position(area), (25, 1));
     }
 
-    /// Handle event read errors in normal mode, with unregistered command key
-    /// The current crossterm code doesn't seem to return read errors, but
-    /// we'll still test against the API
     #[test]
-    fn command_bar_handles_event_read_error_in_normal_mode_with_unregistered_command_key() {
-        let mut command_bar_widget = CommandBar::default();
+    fn command_bar_completes_single_candidate() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.completer = Some(Rc::new(WordCompleter::new(&["quit", "write"])));
+        command_bar_widget.input = String::from("q arg");
+        command_bar_widget.cursor = 1;
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Tab]);
+        assert_eq!(command_bar_widget.input, "quit arg");
+        assert_eq!(command_bar_widget.cursor, 4);
+        assert!(command_bar_widget.completion.is_none());
+    }
 
-        // test that event.read returning an error is handled correctly
-        let event_res = handle_error_event(&mut command_bar_widget);
+    #[test]
+    fn command_bar_drops_candidates_with_invalid_spans() {
+        /// A completer with spans past the end and inside a character
+        struct Broken;
+
+        impl Completer for Broken {
+            fn complete(&self, input: &str, _cursor: usize) -> Vec<Candidate> {
+                vec![
+                    Candidate::new(0..input.len() + 5, "long"),
+                    Candidate::new(0..1, "split"),
+                    Candidate::new(0..2, "\u{e9}t\u{e9}"),
+                ]
+            }
+        }
 
-        assert!(match event_res {
-            EventHandlerResult::Err => true,
-            _ => false,
-        });
-        assert!(match command_bar_widget.input_mode {
-            InputMode::Normal => true,
-            InputMode::Editing => false,
-        });
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.completer = Some(Rc::new(Broken));
+        command_bar_widget.input = String::from("\u{e9}t");
+        command_bar_widget.move_cursor_end();
+
+        // Only the valid candidate is left, so it's inserted
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Tab]);
+        assert_eq!(command_bar_widget.input, "\u{e9}t\u{e9}t");
+        assert!(command_bar_widget.completion.is_none());
     }
 
-    /// Handle event read errors in normal mode
-    /// The current crossterm code doesn't seem to return read errors, but
-    /// we'll still test against the API
     #[test]
-    fn command_bar_handles_event_read_error_in_normal_mode() {
-        let mut command_bar_widget = CommandBar::default();
-        let mut closure = |cb: &mut CommandBar, key| cb.command_key_handler(key);
-        command_bar_widget.register_key(':', &mut closure);
+    fn command_bar_completes_common_prefix_then_cycles() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.completer =
+            Some(Rc::new(WordCompleter::new(&["write", "writeall", "quit"])));
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Char('w'), KeyCode::Tab]);
+        assert_eq!(command_bar_widget.input, "write");
+        assert!(command_bar_widget.completion.is_none());
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Tab]);
+        assert_eq!(command_bar_widget.input, "write");
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Tab]);
+        assert_eq!(command_bar_widget.input, "writeall");
+        assert_eq!(command_bar_widget.cursor, 8);
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::BackTab]);
+        assert_eq!(command_bar_widget.input, "write");
+
+        // Typing keeps the completed text and stops cycling
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Char(' ')]);
+        assert!(command_bar_widget.completion.is_none());
+        assert_eq!(command_bar_widget.input, "write ");
+    }
 
-        // test that event.read returning an error is handled correctly
-        let event_res = handle_error_event(&mut command_bar_widget);
+    #[test]
+    fn command_bar_completion_menu_navigation() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.completer = Some(Rc::new(WordCompleter::new(&["ab", "ac", "ad"])));
+        command_bar_widget.input = String::from("a");
+        command_bar_widget.move_cursor_end();
+        assert!(command_bar_widget.completion_menu().is_none());
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Tab]);
+        assert!(command_bar_widget.completion_menu().is_some());
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Down, KeyCode::Down]);
+        assert_eq!(command_bar_widget.input, "ad");
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Up]);
+        assert_eq!(command_bar_widget.input, "ac");
+
+        // Enter accepts the candidate without submitting
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Enter]);
+        assert!(command_bar_widget.completion_menu().is_none());
+        assert_eq!(command_bar_widget.input, "ac");
+        assert_eq!(command_bar_widget.input_mode, InputMode::Editing);
+    }
 
-        assert!(match event_res {
-            EventHandlerResult::Err => true,
-            _ => false,
-        });
-        assert!(match command_bar_widget.input_mode {
-            InputMode::Normal => true,
-            InputMode::Editing => false,
-        });
+    #[test]
+    fn command_bar_completion_menu_cancel_restores_input() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.completer = Some(Rc::new(WordCompleter::new(&["ab", "ac"])));
+        command_bar_widget.input = String::from("a b");
+        command_bar_widget.cursor = 1;
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Tab, KeyCode::Tab]);
+        assert_eq!(command_bar_widget.input, "ac b");
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Esc]);
+        assert_eq!(command_bar_widget.input, "a b");
+        assert_eq!(command_bar_widget.cursor, 1);
+        assert_eq!(command_bar_widget.input_mode, InputMode::Editing);
     }
 
-    /// Handle event read errors in editing mode
-    /// The current crossterm code doesn't seem to return read errors, but
-    /// we'll still test against the API
     #[test]
-    fn command_bar_handles_event_read_error_in_editing_mode() {
-        let mut command_bar_widget = CommandBar::default();
-        let mut closure = |cb: &mut CommandBar, key| cb.command_key_handler(key);
-        command_bar_widget.register_key(':', &mut closure);
+    fn command_bar_completion_menu_uses_the_keymap() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.completer = Some(Rc::new(WordCompleter::new(&["ab", "ac"])));
+        let keymap = command_bar_widget.keymap_mut(InputMode::Editing);
+        keymap.unbind(KeyCode::Tab);
+        keymap.bind(
+            KeyCombination::new(KeyCode::Char('n'), KeyModifiers::CONTROL),
+            Action::Complete,
+        );
+        keymap.bind(
+            KeyCombination::new(KeyCode::Char('g'), KeyModifiers::CONTROL),
+            Action::Cancel,
+        );
 
-        // enter editing mode
-        debug!("Entering editing mode");
-        command_bar_widget.input_mode = InputMode::Editing;
+        press(&mut command_bar_widget, "a C-n C-n");
+        assert_eq!(command_bar_widget.input, "ac");
+        // Down is bound to history-next, which moves through the menu
+        press(&mut command_bar_widget, "Down");
+        assert_eq!(command_bar_widget.input, "ab");
+        press(&mut command_bar_widget, "C-g");
+        assert!(command_bar_widget.completion.is_none());
+        assert_eq!(command_bar_widget.input, "a");
+
+        // Tab isn't bound any more, so it closes the menu and isn't handled
+        let tab = Event::Key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        press(&mut command_bar_widget, "C-n");
+        assert_eq!(
+            press(&mut command_bar_widget, "Tab"),
+            vec![EventHandlerResult::Unhandled(tab)]
+        );
+        assert!(command_bar_widget.completion.is_none());
+        assert_eq!(command_bar_widget.input, "ab");
+    }
 
-        // test that event.read returning an error is handled correctly
-        let event_res = handle_error_event(&mut command_bar_widget);
+    #[test]
+    fn command_bar_returns_unbound_chords() {
+        let mut command_bar_widget = editing_command_bar();
+        let chord = |c, modifiers| Event::Key(KeyEvent::new(KeyCode::Char(c), modifiers));
+        assert_eq!(
+            press(&mut command_bar_widget, "C-q M-x C-l M-."),
+            vec![
+                EventHandlerResult::Unhandled(chord('q', KeyModifiers::CONTROL)),
+                EventHandlerResult::Unhandled(chord('x', KeyModifiers::ALT)),
+                EventHandlerResult::Unhandled(chord('l', KeyModifiers::CONTROL)),
+                EventHandlerResult::Unhandled(chord('.', KeyModifiers::ALT)),
+            ]
+        );
+        assert_eq!(command_bar_widget.input, "");
+        press(&mut command_bar_widget, "x S-y");
+        assert_eq!(command_bar_widget.input, "xY");
+    }
 
-        assert!(match event_res {
-            EventHandlerResult::Err => true,
-            _ => false,
-        });
-        assert!(match command_bar_widget.input_mode {
-            InputMode::Normal => false,
-            InputMode::Editing => true,
-        });
+    fn command_bar_with_commands() -> CommandBar {
+        let mut commands = CommandRegistry::default();
+        commands
+            .register(
+                Command::new("goto", |invocation| {
+                    let line = invocation.get("line").and_then(|v| v.as_integer());
+                    Ok(Some(format!("line {}", line.unwrap_or(0))))
+                })
+                .arg(ArgSpec::required("line", ArgKind::Integer)),
+            )
+            .unwrap();
+        CommandBar {
+            commands: Some(commands),
+            ..editing_command_bar()
+        }
     }
 
     #[test]
-    fn command_bar_sends_message() {
-        let (tx, rx) = mpsc::channel();
-        let mut command_bar_widget = CommandBar::default_with_tx_channel(tx);
+    fn command_bar_dispatches_submitted_command() {
+        let mut command_bar_widget = command_bar_with_commands();
+        command_bar_widget.input = String::from("goto 7");
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Enter]);
+        assert_eq!(command_bar_widget.input_mode, InputMode::Normal);
+        let outcome = command_bar_widget.command_result.clone().unwrap().unwrap();
+        assert_eq!(outcome.invocation.command, "goto");
+        assert_eq!(outcome.message, Some(String::from("line 7")));
+        assert_eq!(command_bar_widget.history.entries, vec!["goto 7"]);
+    }
+
+    #[test]
+    fn command_bar_keeps_input_with_parse_error() {
+        let mut command_bar_widget = command_bar_with_commands();
+        command_bar_widget.input = String::from("goto x");
+        command_bar_widget.move_cursor_end();
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Enter]);
+        assert_eq!(command_bar_widget.input_mode, InputMode::Editing);
+        assert_eq!(command_bar_widget.input, "goto x");
+        assert!(command_bar_widget.history.entries.is_empty());
+        assert!(matches!(
+            command_bar_widget.command_result,
+            Some(Err(CommandError::InvalidArgument { .. }))
+        ));
+
+        // Moving the cursor keeps the error, editing the input drops it
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Left]);
+        assert!(command_bar_widget.command_result.is_some());
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Backspace]);
+        assert!(command_bar_widget.command_result.is_none());
+    }
 
-        let mut closure = |cb: &mut CommandBar, key| cb.command_key_handler(key);
-        command_bar_widget.register_key(':', &mut closure);
+    #[test]
+    fn command_bar_renders_command_error_in_title() {
+        let mut command_bar_widget = command_bar_with_commands();
+        command_bar_widget.input = String::from("quit");
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Enter]);
 
-        let backend = TestBackend::new(40, 4);
+        let backend = TestBackend::new(40, 3);
         let mut terminal = Terminal::new(backend).unwrap();
         terminal
             .draw(|frame| {
-                let area = Rect {
-                    x: 0,
-                    y: 0,
-                    width: 40,
-                    height: 4,
-                };
-                frame.render_widget(&mut command_bar_widget, area);
+                frame.render_widget(&mut command_bar_widget, Rect::new(0, 0, 40, 3));
             })
             .unwrap();
-        let event = Event::Key(KeyEvent::new(KeyCode::Char(':'), KeyModifiers::NONE));
-        handle_generic_event(&mut command_bar_widget, event);
-
-        let event = Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE));
-        handle_generic_event(&mut command_bar_widget, event);
 
-        let event = Event::Key(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::NONE));
-        handle_generic_event(&mut command_bar_widget, event);
-
-        let event = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
-        handle_generic_event(&mut command_bar_widget, event);
+        let buffer = terminal.backend().buffer();
+        let title: String = (1..31).map(|x| buffer.get(x, 0).symbol.clone()).collect();
+        assert_eq!(title, "Command: unknown command: quit");
+        assert_eq!(buffer.get(10, 0).fg, Color::Red);
+    }
 
-        let received = rx.recv().unwrap();
+    #[test]
+    fn command_bar_underlines_command_error() {
+        let mut command_bar_widget = command_bar_with_commands();
+        command_bar_widget.input = String::from("goto 'x");
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Enter]);
+        assert_renders_line(&mut command_bar_widget, "│goto 'x │");
 
-        assert_eq!(received, "ab");
+        let backend = TestBackend::new(10, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| {
+                frame.render_widget(&mut command_bar_widget, Rect::new(0, 0, 10, 3));
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let underlined: Vec<bool> = (1..9)
+            .map(|x| buffer.get(x, 1).modifier.contains(Modifier::UNDERLINED))
+            .collect();
+        assert_eq!(
+            underlined,
+            vec![false, false, false, false, false, true, true, false]
+        );
     }
 
     #[test]
@@ -680,8 +3383,7 @@ mod tests {
         let mut terminal = Terminal::new(backend).unwrap();
 
         let mut command_bar_widget = CommandBar::default();
-        let mut closure = |cb: &mut CommandBar, key| cb.command_key_handler(key);
-        command_bar_widget.register_key(':', &mut closure);
+        command_bar_widget.register_key(':', CommandBar::command_key_handler);
 
         terminal
             .draw(|frame| {
@@ -717,4 +3419,74 @@ mod tests {
         }
         terminal.backend().assert_buffer(&expected);
     }
+
+    /// Render the CommandBar in a 10 column terminal and check the middle line
+    fn assert_renders_line(command_bar_widget: &mut CommandBar, line: &str) {
+        let backend = TestBackend::new(10, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| {
+                frame.render_widget(&mut *command_bar_widget, Rect::new(0, 0, 10, 3));
+            })
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let rendered: String = (0..10).map(|x| buffer.get(x, 1).symbol.clone()).collect();
+        assert_eq!(rendered, line);
+    }
+
+    #[test]
+    fn command_bar_accepts_input_wider_than_bar() {
+        let mut command_bar_widget = editing_command_bar();
+        assert_renders_line(&mut command_bar_widget, "│        │");
+
+        let codes: Vec<KeyCode> = "abcdefghijkl".chars().map(KeyCode::Char).collect();
+        handle_key_codes(&mut command_bar_widget, &codes);
+        assert_eq!(command_bar_widget.input, "abcdefghijkl");
+
+        // The text scrolls left to leave room for the cursor at the end
+        assert_renders_line(&mut command_bar_widget, "│fghijkl │");
+        assert_eq!(command_bar_widget.scroll_offset, 5);
+        assert_eq!(
+            command_bar_widget.cursor_position(Rect::new(0, 0, 10, 3)),
+            (8, 1)
+        );
+    }
+
+    #[test]
+    fn command_bar_scrolls_to_cursor() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.input = String::from("abcdefghijkl");
+        command_bar_widget.move_cursor_end();
+        assert_renders_line(&mut command_bar_widget, "│fghijkl │");
+
+        // Moving inside the visible text doesn't scroll
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Left, KeyCode::Left]);
+        assert_renders_line(&mut command_bar_widget, "│fghijkl │");
+
+        handle_key_codes(&mut command_bar_widget, &[KeyCode::Home]);
+        assert_renders_line(&mut command_bar_widget, "│abcdefgh│");
+        assert_eq!(
+            command_bar_widget.cursor_position(Rect::new(0, 0, 10, 3)),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn command_bar_renders_overflow_indicators() {
+        let mut command_bar_widget = editing_command_bar();
+        command_bar_widget.overflow_indicators = true;
+        command_bar_widget.input = String::from("abcdefghijkl");
+        assert_renders_line(&mut command_bar_widget, "│ abcdef>│");
+
+        command_bar_widget.cursor = 8;
+        assert_renders_line(&mut command_bar_widget, "│<defghi>│");
+        assert_eq!(
+            command_bar_widget.cursor_position(Rect::new(0, 0, 10, 3)),
+            (7, 1)
+        );
+
+        command_bar_widget.move_cursor_end();
+        assert_renders_line(&mut command_bar_widget, "│<hijkl  │");
+    }
 }
@@ -0,0 +1,365 @@
+///
+/// Completion menu widget to show completion candidates next to a CommandBar
+///
+use unicode_width::UnicodeWidthStr;
+
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Widget},
+};
+
+use crate::completion::completer::{Candidate, CompletionState};
+
+/// A CompletionMenu lists the candidates being cycled through in a CommandBar
+///
+/// Like the Popup widget it clears the area under it, so render it after
+/// the rest of the frame.
+///
+/// # Example
+///
+/// ```
+/// use tui::{backend::TestBackend, layout::Rect, Terminal};
+/// use tui_command_bar_widget::completion::completer::{Candidate, CompletionState};
+/// use tui_command_bar_widget::widgets::completion_menu::CompletionMenu;
+///
+/// let state = CompletionState::new(
+///     "w",
+///     1,
+///     vec![Candidate::new(0..1, "write"), Candidate::new(0..1, "wq")],
+/// );
+/// let command_bar_area = Rect::new(0, 0, 20, 3);
+///
+/// let backend = TestBackend::new(20, 10);
+/// let mut terminal = Terminal::new(backend).unwrap();
+/// terminal
+///     .draw(|frame| {
+///         let menu = CompletionMenu::new(&state);
+///         let area = menu.area(command_bar_area, frame.size());
+///         frame.render_widget(menu, area);
+///     })
+///     .unwrap();
+/// ```
+pub struct CompletionMenu<'b> {
+    /// The candidates and the selected candidate
+    state: &'b CompletionState,
+    /// The maximum number of candidates shown at once
+    max_rows: u16,
+    /// Style of the menu
+    style: Style,
+    /// Style of the selected candidate
+    highlight_style: Style,
+    /// Style of candidate descriptions
+    description_style: Style,
+    /// Style of highlighted characters in candidates
+    match_style: Style,
+}
+
+impl<'b> CompletionMenu<'b> {
+    /// Build a menu for the candidates in state
+    pub fn new(state: &'b CompletionState) -> Self {
+        CompletionMenu {
+            state,
+            max_rows: 8,
+            style: Style::default(),
+            highlight_style: Style::default().add_modifier(Modifier::REVERSED),
+            description_style: Style::default().fg(Color::DarkGray),
+            match_style: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        }
+    }
+
+    /// Set the maximum number of candidates shown at once
+    pub fn max_rows(mut self, max_rows: u16) -> Self {
+        self.max_rows = max_rows.max(1);
+        self
+    }
+
+    /// Set the style of the menu
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the style of the selected candidate
+    pub fn highlight_style(mut self, style: Style) -> Self {
+        self.highlight_style = style;
+        self
+    }
+
+    /// Set the style of candidate descriptions
+    pub fn description_style(mut self, style: Style) -> Self {
+        self.description_style = style;
+        self
+    }
+
+    /// Set the style of highlighted characters in candidates, such as
+    /// fuzzy matches
+    pub fn match_style(mut self, style: Style) -> Self {
+        self.match_style = style;
+        self
+    }
+
+    /// Split a candidate into spans, highlighting the matched characters
+    fn candidate_spans(&self, candidate: &'b Candidate) -> Vec<Span<'b>> {
+        let mut spans = Vec::new();
+        let mut start = 0;
+        let mut highlighted = false;
+        for (i, (offset, _)) in candidate.replacement.char_indices().enumerate() {
+            let matched = candidate.matches.contains(&i);
+            if matched != highlighted {
+                spans.push(self.span(&candidate.replacement[start..offset], highlighted));
+                start = offset;
+                highlighted = matched;
+            }
+        }
+        spans.push(self.span(&candidate.replacement[start..], highlighted));
+        spans
+    }
+
+    fn span(&self, text: &'b str, highlighted: bool) -> Span<'b> {
+        if highlighted {
+            Span::styled(text, self.match_style)
+        } else {
+            Span::raw(text)
+        }
+    }
+
+    /// Width of the candidate column
+    fn replacement_width(&self) -> usize {
+        self.state
+            .candidates
+            .iter()
+            .map(|c| c.replacement.width())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Width of the description column, zero if no candidate has one
+    fn description_width(&self) -> usize {
+        self.state
+            .candidates
+            .iter()
+            .filter_map(|c| c.description.as_ref())
+            .map(|d| d.width())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Area to render the menu in, anchored to a CommandBar rendered in
+    /// command_bar_area and clipped to frame_area.
+    ///
+    /// The menu goes below the command bar if it fits, otherwise on the side
+    /// with more room.
+    pub fn area(&self, command_bar_area: Rect, frame_area: Rect) -> Rect {
+        let rows = (self.state.candidates.len() as u16).min(self.max_rows);
+        let height = rows + 2;
+
+        let mut width = self.replacement_width();
+        let description_width = self.description_width();
+        if description_width > 0 {
+            width += 2 + description_width;
+        }
+        let width = (width as u16).saturating_add(2).min(frame_area.width);
+
+        let below = frame_area
+            .bottom()
+            .saturating_sub(command_bar_area.bottom());
+        let above = command_bar_area.top().saturating_sub(frame_area.top());
+        let (y, height) = if below >= height || below >= above {
+            (command_bar_area.bottom(), height.min(below))
+        } else {
+            let height = height.min(above);
+            (command_bar_area.top() - height, height)
+        };
+
+        // Line the candidates up with the text in the command bar
+        let x = (command_bar_area.x + 1)
+            .min(frame_area.right().saturating_sub(width))
+            .max(frame_area.x);
+
+        Rect::new(x, y, width, height)
+    }
+}
+
+impl<'b> Widget for CompletionMenu<'b> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let block = Block::default().borders(Borders::ALL).style(self.style);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        if inner.height == 0 || inner.width == 0 {
+            return;
+        }
+
+        // Only scroll when the selected candidate is outside the rows that
+        // were shown last time, like a List does
+        let rows = usize::from(inner.height);
+        let selected = self.state.selected.unwrap_or(0);
+        let mut offset = self
+            .state
+            .offset
+            .get()
+            .min(self.state.candidates.len().saturating_sub(rows));
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + rows {
+            offset = selected + 1 - rows;
+        }
+        self.state.offset.set(offset);
+
+        let replacement_width = self.replacement_width();
+        for (row, (i, candidate)) in self
+            .state
+            .candidates
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(rows)
+            .enumerate()
+        {
+            let y = inner.y + row as u16;
+            let padding = replacement_width - candidate.replacement.width();
+            let mut spans = self.candidate_spans(candidate);
+            spans.push(Span::raw(" ".repeat(padding)));
+            if let Some(description) = &candidate.description {
+                spans.push(Span::raw("  "));
+                spans.push(Span::styled(description.as_str(), self.description_style));
+            }
+
+            let line = Rect::new(inner.x, y, inner.width, 1);
+            buf.set_style(line, self.style);
+            buf.set_spans(inner.x, y, &Spans::from(spans), inner.width);
+            if self.state.selected == Some(i) {
+                buf.set_style(line, self.highlight_style);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tui::{
+        buffer::Buffer,
+        layout::Rect,
+        style::{Color, Style},
+        widgets::Widget,
+    };
+
+    use crate::completion::completer::{Candidate, CompletionState};
+    use crate::widgets::completion_menu::CompletionMenu;
+
+    fn state(count: usize) -> CompletionState {
+        let candidates = (0..count)
+            .map(|i| Candidate::new(0..0, &format!("c{}", i)))
+            .collect();
+        CompletionState::new("", 0, candidates)
+    }
+
+    #[test]
+    fn completion_menu_anchors_below_or_above() {
+        let state = state(3);
+        let menu = CompletionMenu::new(&state);
+        let frame = Rect::new(0, 0, 40, 20);
+
+        assert_eq!(
+            menu.area(Rect::new(0, 0, 40, 3), frame),
+            Rect::new(1, 3, 4, 5)
+        );
+        assert_eq!(
+            menu.area(Rect::new(0, 17, 40, 3), frame),
+            Rect::new(1, 12, 4, 5)
+        );
+
+        // Without room on either side the menu is clipped
+        let frame = Rect::new(0, 0, 40, 6);
+        assert_eq!(
+            menu.area(Rect::new(0, 0, 40, 3), frame),
+            Rect::new(1, 3, 4, 3)
+        );
+    }
+
+    #[test]
+    fn completion_menu_highlights_matches() {
+        let state = CompletionState::new(
+            "",
+            0,
+            vec![Candidate::new(0..0, "write").with_matches(vec![0, 2, 3])],
+        );
+        let menu = CompletionMenu::new(&state).match_style(Style::default().fg(Color::Red));
+        let area = Rect::new(0, 0, 7, 3);
+        let mut buf = Buffer::empty(area);
+        menu.render(area, &mut buf);
+
+        let highlighted: Vec<bool> = (1..6).map(|x| buf.get(x, 1).fg == Color::Red).collect();
+        assert_eq!(highlighted, vec![true, false, true, true, false]);
+    }
+
+    /// The candidates a menu with room for rows candidates shows
+    fn visible(state: &CompletionState, rows: u16) -> Vec<String> {
+        let area = Rect::new(0, 0, 4, rows + 2);
+        let mut buf = Buffer::empty(area);
+        CompletionMenu::new(state).render(area, &mut buf);
+        (1..=rows)
+            .map(|y| (1..3).map(|x| buf.get(x, y).symbol.clone()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn completion_menu_keeps_its_scroll_offset() {
+        let mut state = state(5);
+        state.selected = Some(4);
+        assert_eq!(visible(&state, 2), vec!["c3", "c4"]);
+        // Moving up within the visible rows only moves the highlight
+        state.select_previous();
+        assert_eq!(visible(&state, 2), vec!["c3", "c4"]);
+        state.select_previous();
+        assert_eq!(visible(&state, 2), vec!["c2", "c3"]);
+        state.select_next();
+        assert_eq!(visible(&state, 2), vec!["c2", "c3"]);
+        // Wrapping around to the first candidate scrolls back to the top
+        state.selected = Some(4);
+        state.select_next();
+        assert_eq!(visible(&state, 2), vec!["c0", "c1"]);
+    }
+
+    #[test]
+    fn completion_menu_renders_descriptions_and_scrolls() {
+        let mut state = CompletionState::new(
+            "",
+            0,
+            vec![
+                Candidate::new(0..0, "write").with_description("save"),
+                Candidate::new(0..0, "wq"),
+                Candidate::new(0..0, "quit").with_description("exit"),
+            ],
+        );
+        state.selected = Some(2);
+        let menu = CompletionMenu::new(&state).max_rows(2);
+        let area = menu.area(Rect::new(0, 0, 20, 3), Rect::new(0, 0, 20, 10));
+        assert_eq!(area, Rect::new(1, 3, 13, 4));
+
+        let mut buf = Buffer::empty(area);
+        menu.highlight_style(Style::default())
+            .render(area, &mut buf);
+        let rendered: Vec<String> = (area.y..area.bottom())
+            .map(|y| {
+                (area.x..area.right())
+                    .map(|x| buf.get(x, y).symbol.clone())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "┌───────────┐",
+                "│wq         │",
+                "│quit   exit│",
+                "└───────────┘",
+            ]
+        );
+    }
+}
@@ -3,9 +3,11 @@
 ///
 #[warn(missing_docs)]
 #[warn(unsafe_code)]
-
 /// The command_bar module provides a CommandBar widget
 pub mod command_bar;
 
 /// The popup module provides code to wrap a CommandBar in a popup
 pub mod popup;
+
+/// The completion_menu module provides a list of completion candidates
+pub mod completion_menu;
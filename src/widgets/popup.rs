@@ -1,24 +1,27 @@
 ///
 /// Popup widget to wrap a CommandBar in a popup
 ///
+use crossterm::event::{Event, KeyEvent};
 use tui::{buffer::Buffer, layout::Rect, widgets::Widget};
 
 use super::command_bar::{CommandBar, EventHandlerResult, InputMode};
-use crate::key_hook::key_hook::KeyHook;
+use crate::event_source::event_source::EventSource;
+use crate::key_hook::key_hook::{HandlerOutcome, KeyHook};
+use crate::key_hook::key_sequence::KeySequence;
 
 /// A Popup widget that wraps a CommandBar in a popup or dialog
-pub struct Popup<'a> {
+pub struct Popup {
     /// Whether the popup should be shown
     pub show_popup: bool,
     /// command_bar is the CommandBar widget
-    pub command_bar: CommandBar<'a>,
+    pub command_bar: CommandBar,
 }
 
 /// Overriding derivable_impls clippy to explictly show how the fields
 /// are initialized.
 #[allow(clippy::derivable_impls)]
-impl<'a> Default for Popup<'a> {
-    fn default() -> Popup<'a> {
+impl Default for Popup {
+    fn default() -> Popup {
         Popup {
             command_bar: CommandBar::default(),
             show_popup: false,
@@ -26,30 +29,60 @@ impl<'a> Default for Popup<'a> {
     }
 }
 
-impl<'a> KeyHook<'a, CommandBar<'a>> for Popup<'a> {
-    fn register_key(&mut self, key: char, f: &'a dyn Fn(&mut CommandBar<'a>, char)) {
-        self.command_bar.command_key = Some(key);
-        self.command_bar.key_database.keys.insert(key, f);
+impl KeyHook<CommandBar> for Popup {
+    fn register_key<K, F>(&mut self, key: K, f: F)
+    where
+        K: Into<KeySequence>,
+        F: FnMut(&mut CommandBar, KeyEvent) -> HandlerOutcome + 'static,
+    {
+        self.command_bar.register_key(key, f);
     }
 
-    fn unregister_key(&mut self, key: char) {
-        self.command_bar.key_database.keys.remove(&key);
-
-        // Unset the command key if it matches
-        if let Some(command_key) = self.command_bar.command_key {
-            if command_key == key {
-                self.command_bar.command_key = None;
-            }
-        }
+    fn unregister_key<K: Into<KeySequence>>(&mut self, key: K) {
+        self.command_bar.unregister_key(key);
     }
 }
 
-impl<'a> Popup<'a> {
-    /// Handle an event
+impl Popup {
+    /// Read an event from the terminal and handle it
     /// If the widget is not registered to handle the event, pass it to the parent
     pub fn handle_event(&mut self) -> EventHandlerResult {
         let res = self.command_bar.handle_event();
-        if res == EventHandlerResult::Ok {
+        self.update_visibility(&res);
+        res
+    }
+
+    /// Read an event from source and handle it
+    pub fn handle_event_from<S: EventSource + ?Sized>(
+        &mut self,
+        source: &mut S,
+    ) -> EventHandlerResult {
+        let res = self.command_bar.handle_event_from(source);
+        self.update_visibility(&res);
+        res
+    }
+
+    /// Handle an event that has already been read
+    /// If the widget is not registered to handle the event, pass it to the parent
+    pub fn handle_key_event(&mut self, event: Event) -> EventHandlerResult {
+        let res = self.command_bar.handle_key_event(event);
+        self.update_visibility(&res);
+        res
+    }
+
+    /// Finish a pending key sequence if its timeout has run out
+    /// Returns whether the Popup changed and should be redrawn
+    pub fn handle_timeout(&mut self) -> bool {
+        let changed = self.command_bar.handle_timeout();
+        if changed {
+            self.update_visibility(&EventHandlerResult::Ok);
+        }
+        changed
+    }
+
+    /// Show the popup while the CommandBar is being edited
+    fn update_visibility(&mut self, res: &EventHandlerResult) {
+        if *res == EventHandlerResult::Ok {
             match self.command_bar.input_mode {
                 InputMode::Normal => {
                     self.show_popup = false;
@@ -59,25 +92,29 @@ impl<'a> Popup<'a> {
                 }
             }
         }
+    }
+}
 
-        res
+impl AsMut<CommandBar> for Popup {
+    fn as_mut(&mut self) -> &mut CommandBar {
+        &mut self.command_bar
     }
 }
 
-impl<'a> Widget for Popup<'a> {
+impl Widget for Popup {
     fn render(self, area: Rect, buf: &mut Buffer) {
         self.command_bar.render(area, buf);
     }
 }
 
-impl<'a> Widget for &mut Popup<'a> {
+impl Widget for &mut Popup {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let command_bar = &mut self.command_bar;
         command_bar.render(area, buf);
     }
 }
 
-impl<'a> Widget for &'a Popup<'a> {
+impl Widget for &Popup {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let command_bar = &self.command_bar;
         command_bar.render(area, buf);
@@ -86,7 +123,13 @@ impl<'a> Widget for &'a Popup<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::widgets::command_bar::CommandBar;
+    use std::time::Duration;
+
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::key_hook::key_hook::KeyHook;
+    use crate::key_hook::key_sequence::KeySequence;
+    use crate::widgets::command_bar::{CommandBar, EventHandlerResult};
     use crate::widgets::popup::Popup;
 
     #[test]
@@ -101,4 +144,43 @@ mod tests {
         assert_eq!(popup.command_bar.messages, command_bar.messages);
         assert_eq!(popup.command_bar.width, command_bar.width);
     }
+
+    #[test]
+    fn popup_shows_while_editing() {
+        let mut popup = Popup::default();
+        popup.register_key(':', CommandBar::command_key_handler);
+
+        let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        assert_eq!(
+            popup.handle_key_event(key(KeyCode::Char(':'))),
+            EventHandlerResult::Ok
+        );
+        assert!(popup.show_popup);
+        assert_eq!(
+            popup.handle_key_event(key(KeyCode::Esc)),
+            EventHandlerResult::Ok
+        );
+        assert!(!popup.show_popup);
+        assert_eq!(
+            popup.handle_key_event(key(KeyCode::Char('x'))),
+            EventHandlerResult::Unhandled(key(KeyCode::Char('x')))
+        );
+    }
+
+    #[test]
+    fn popup_shows_when_a_key_sequence_times_out() {
+        let mut popup = Popup::default();
+        popup.register_key('g', CommandBar::command_key_handler);
+        popup.register_key(
+            "g g".parse::<KeySequence>().unwrap(),
+            CommandBar::command_key_handler,
+        );
+        popup.command_bar.key_database.timeout = Some(Duration::ZERO);
+
+        let key = Event::Key(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE));
+        assert_eq!(popup.handle_key_event(key), EventHandlerResult::Ok);
+        assert!(!popup.show_popup);
+        assert!(popup.handle_timeout());
+        assert!(popup.show_popup);
+    }
 }
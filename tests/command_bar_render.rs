@@ -1,20 +1,8 @@
 #[cfg(test)]
 use tui::{backend::TestBackend, buffer::Buffer, layout::Rect, style::Color, Terminal};
 
-use mockall::*;
-
 use tui_command_bar_widget::widgets::command_bar::CommandBar;
 
-use std::sync::Mutex;
-
-lazy_static! {
-    /// The context object is vulnerable to race conditions
-    /// Use a mutex so only one test at a time has access to update it
-    /// The lock will last for the length of the let block in the tests
-    /// Based on the mock_struct_with_static_method.rs tests
-    static ref EVENT_READ_MUTEX: Mutex<()> = Mutex::new(());
-}
-
 #[test]
 fn command_bar_renders() {
     let backend = TestBackend::new(40, 3);